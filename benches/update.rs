@@ -0,0 +1,83 @@
+//! Timings of a simulation step and of the neighbor queries it is dominated by, at sizes from a
+//! small sketch to what only the GPU path handles interactively. Run with
+//! `cargo bench --no-default-features`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use differential_lines::{vec2, NeighborIndexKind, ParticleSystem, Vec2};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::f32::consts::TAU;
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// A ring of `num_particles` spaced about two units apart, between the default shortest and
+/// longest edges, so that the first step neither splits nor merges much.
+fn ring(num_particles: usize) -> ParticleSystem {
+    let mut ps = ParticleSystem::with_seed(42);
+    ps.spawn_particles(num_particles, num_particles as f32 * 2.0 / TAU);
+    ps
+}
+
+/// `len` points scattered over a square about as densely as the folds of a grown curve, where
+/// each has a few dozen others within the default influence radius.
+fn scattered(len: usize) -> Vec<Vec2> {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+    let side = (len as f32).sqrt() * 3.5;
+    (0..len)
+        .map(|_| vec2(rng.gen::<f32>() * side, rng.gen::<f32>() * side))
+        .collect()
+}
+
+fn update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update");
+    group.sample_size(10);
+    for num_particles in SIZES {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_particles),
+            &num_particles,
+            |b, &num_particles| {
+                b.iter_batched(
+                    || ring(num_particles),
+                    |mut ps| ps.update(),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Rebuilds each index and queries it around every point, like a step does.
+fn neighbors(c: &mut Criterion) {
+    let radius = ParticleSystem::new().influence_radius;
+    let mut group = c.benchmark_group("neighbors");
+    group.sample_size(10);
+    for len in SIZES {
+        let positions = scattered(len);
+        for kind in [NeighborIndexKind::Grid, NeighborIndexKind::Quadtree] {
+            let mut index = kind.build();
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", kind), len),
+                &positions,
+                |b, positions| {
+                    b.iter(|| {
+                        index.rebuild(radius, positions);
+                        let mut found = 0;
+                        for &center in positions {
+                            index.for_each_candidate(center, radius, &mut |j| {
+                                if (positions[j] - center).length() <= radius {
+                                    found += 1;
+                                }
+                            });
+                        }
+                        found
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, update, neighbors);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! A persistent image the curves are drawn into, additively by default, fading out at a steady
+//! rate, which leaves trails of the growth that don't depend on the window and can be exported as
+//! they are.
+
+use crate::raster::{BlendMode, Canvas};
+use crate::Color;
+use glam::Vec2;
+
+/// An offscreen canvas that keeps what is drawn into it, faded towards the background by
+/// `decay` every step.
+pub struct Accumulation {
+    canvas: Canvas,
+    /// The fraction of what was drawn that fades out every step, from 0 to keep everything to 1
+    /// to keep only the latest step.
+    pub decay: f32,
+    pub background: Color,
+    /// How what is drawn combines with the trails, `BlendMode::Add` unless set otherwise.
+    pub blend_mode: BlendMode,
+}
+
+impl Accumulation {
+    /// Creates an accumulation of `width` by `height` pixels showing the plane around `center`
+    /// with `scale` pixels per simulation unit, which stay put whatever the window does.
+    pub fn new(
+        width: usize,
+        height: usize,
+        center: Vec2,
+        scale: f32,
+        decay: f32,
+        background: Color,
+    ) -> Self {
+        let mut canvas = Canvas::new(width, height);
+        canvas.set_view(center, scale);
+        let mut accumulation = Accumulation {
+            canvas,
+            decay,
+            background,
+            blend_mode: BlendMode::Add,
+        };
+        accumulation.clear();
+        accumulation
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    /// Fades what was drawn so far by `steps` steps worth of `decay`.
+    pub fn fade(&mut self, steps: usize) {
+        let kept = (1.0 - self.decay.clamp(0.0, 1.0)).powi(steps as i32);
+        let [r, g, b, _] = self.background;
+        self.canvas.fill([r, g, b, 1.0 - kept]);
+    }
+
+    /// Lets `draw` blend into the canvas through `blend_mode`, with the view it was created with.
+    pub fn draw<F>(&mut self, draw: F)
+    where
+        F: FnOnce(&mut Canvas),
+    {
+        self.canvas.set_blend_mode(self.blend_mode);
+        draw(&mut self.canvas);
+        self.canvas.set_blend_mode(BlendMode::Normal);
+    }
+
+    /// Covers everything drawn with the opaque background.
+    pub fn clear(&mut self) {
+        let [r, g, b, _] = self.background;
+        self.canvas.fill([r, g, b, 1.0]);
+    }
+}
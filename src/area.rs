@@ -0,0 +1,53 @@
+//! A pressure that keeps the area enclosed by closed curves near a target, like the turgor inside
+//! a cell membrane.
+
+use crate::cyclic;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// The area enclosed by the closed polygon through `points`, positive when they go around
+/// counterclockwise and negative when clockwise.
+pub fn signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].perp_dot(points[cyclic::offset(i, 1, n)]))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Inflates closed curves that enclose less than `target` and deflates those enclosing more,
+/// so they neither collapse nor balloon out as they grow, but fold up inside a steady area.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AreaPreservation {
+    /// The area each closed curve is pushed toward, in square simulation units.
+    pub target: f32,
+    /// How hard particles are pushed along the curve's normal when the area is off by the whole
+    /// target or more. Smaller errors push proportionally less.
+    pub strength: f32,
+}
+
+impl AreaPreservation {
+    /// The force on each point of the closed polygon through `points`, outward when its area is
+    /// below the target and inward when above.
+    pub fn forces(&self, points: &[Vec2]) -> Vec<Vec2> {
+        let n = points.len();
+        if n < 3 || self.target <= 0.0 {
+            return vec![Vec2::ZERO; n];
+        }
+
+        let area = signed_area(points);
+        let error = ((self.target - area.abs()) / self.target).clamp(-1.0, 1.0);
+        // The area grows fastest by moving each point perpendicular to the chord between its
+        // neighbors, which points outward for counterclockwise polygons.
+        let outward = if area < 0.0 { -1.0 } else { 1.0 };
+
+        (0..n)
+            .map(|i| {
+                let chord = points[cyclic::offset(i, 1, n)] - points[cyclic::offset(i, -1, n)];
+                let normal = -chord.perp().normalize_or_zero() * outward;
+                normal * error * self.strength
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,33 @@
+//! Point attractors and repulsors used to steer the growth.
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// A point pulling particles toward it, or pushing them away when `strength` is negative.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Attractor {
+    pub position: Vec2,
+    /// Distance travelled per step by a particle right next to the attractor.
+    pub strength: f32,
+    /// Distance at which the force has dropped to half its strength.
+    pub falloff: f32,
+}
+
+impl Attractor {
+    pub fn new(position: Vec2, strength: f32, falloff: f32) -> Self {
+        Attractor {
+            position,
+            strength,
+            falloff,
+        }
+    }
+
+    /// The force exerted on a particle at `p`.
+    pub fn force_at(&self, p: Vec2) -> Vec2 {
+        let offset = self.position - p;
+        let falloff2 = self.falloff * self.falloff;
+        let weight = falloff2 / (falloff2 + offset.length_squared());
+
+        offset.normalize_or_zero() * self.strength * weight
+    }
+}
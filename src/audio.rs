@@ -0,0 +1,232 @@
+//! Sound levels driving the parameters of the simulation, so that growth pulses with music for
+//! live visuals. Capturing sound from an input device needs the `audio` feature.
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// The frequency ranges of `AudioLevels`, in hertz.
+const BASS: (f32, f32) = (20.0, 250.0);
+const MIDS: (f32, f32) = (250.0, 4000.0);
+const TREBLE: (f32, f32) = (4000.0, 16000.0);
+
+/// How loud some sound is overall and within a few frequency bands, as amplitudes where a full
+/// scale sine is 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AudioLevels {
+    pub rms: f32,
+    pub bass: f32,
+    pub mids: f32,
+    pub treble: f32,
+}
+
+impl AudioLevels {
+    /// Measures a window of mono `samples` recorded at `sample_rate`.
+    pub fn measure(samples: &[f32], sample_rate: u32) -> Self {
+        if samples.is_empty() {
+            return AudioLevels::default();
+        }
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+
+        // A Hann window keeps loud bands from leaking into quiet ones.
+        let n = samples.len();
+        let windowed: Vec<f32> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s * (0.5 - 0.5 * (TAU * i as f32 / n as f32).cos()))
+            .collect();
+        let band = |(low, high): (f32, f32)| {
+            let bin = |frequency: f32| (frequency * n as f32 / sample_rate as f32) as usize;
+            let bins = bin(low).max(1)..bin(high).min(n / 2);
+            // The window halves the amplitude on average, which the factor 4 / n makes up for.
+            bins.map(|k| {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (i, s) in windowed.iter().enumerate() {
+                    let angle = TAU * (k * i % n) as f32 / n as f32;
+                    re += s * angle.cos();
+                    im -= s * angle.sin();
+                }
+                (re * re + im * im) * (4.0 / n as f32).powi(2)
+            })
+            .sum::<f32>()
+            .sqrt()
+        };
+
+        AudioLevels {
+            rms,
+            bass: band(BASS),
+            mids: band(MIDS),
+            treble: band(TREBLE),
+        }
+    }
+
+    pub fn get(&self, source: AudioSource) -> f32 {
+        match source {
+            AudioSource::Rms => self.rms,
+            AudioSource::Bass => self.bass,
+            AudioSource::Mids => self.mids,
+            AudioSource::Treble => self.treble,
+        }
+    }
+
+    /// These levels moved toward `target`, keeping `smoothing` of them, from 0 to just below 1.
+    pub fn smoothed(&self, target: AudioLevels, smoothing: f32) -> Self {
+        let smoothing = smoothing.clamp(0.0, 0.999);
+        let mix = |from: f32, to: f32| from * smoothing + to * (1.0 - smoothing);
+        AudioLevels {
+            rms: mix(self.rms, target.rms),
+            bass: mix(self.bass, target.bass),
+            mids: mix(self.mids, target.mids),
+            treble: mix(self.treble, target.treble),
+        }
+    }
+}
+
+/// Which of the `AudioLevels` drives a parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSource {
+    /// The overall loudness.
+    Rms,
+    /// From 20 to 250 Hz.
+    Bass,
+    /// From 250 to 4000 Hz.
+    Mids,
+    /// From 4000 to 16000 Hz.
+    Treble,
+}
+
+/// Sets a parameter from `min` in silence to `max` once the level of `source` times `gain`
+/// reaches 1.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AudioMapping {
+    pub source: AudioSource,
+    /// The dotted path of the parameter within `[simulation]`, like in schedules.
+    pub parameter: String,
+    pub min: f64,
+    pub max: f64,
+    #[serde(default = "default_gain")]
+    pub gain: f32,
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+impl AudioMapping {
+    pub fn value(&self, levels: &AudioLevels) -> f64 {
+        let t = (levels.get(self.source) * self.gain).clamp(0.0, 1.0) as f64;
+        self.min + (self.max - self.min) * t
+    }
+}
+
+#[cfg(feature = "audio")]
+pub use input::AudioInput;
+
+/// Sound captured from an input device, of which the latest window is measured.
+#[cfg(feature = "audio")]
+mod input {
+    use super::AudioLevels;
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::{FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig};
+    use std::collections::VecDeque;
+    use std::error::Error;
+    use std::sync::{Arc, Mutex};
+
+    pub struct AudioInput {
+        samples: Arc<Mutex<VecDeque<f32>>>,
+        sample_rate: u32,
+        // Capturing stops when the stream is dropped.
+        _stream: Stream,
+    }
+
+    impl AudioInput {
+        /// Starts capturing from the first input device whose name contains `device`, or the
+        /// default one, keeping the last `window` samples mixed down to mono.
+        pub fn start(device: Option<&str>, window: usize) -> Result<Self, Box<dyn Error>> {
+            let host = cpal::default_host();
+            let device = match device {
+                Some(name) => host
+                    .input_devices()?
+                    .find(|device| device.name().is_ok_and(|n| n.contains(name)))
+                    .ok_or_else(|| format!("no input device named {}", name))?,
+                None => host
+                    .default_input_device()
+                    .ok_or("no default input device")?,
+            };
+            let supported = device.default_input_config()?;
+            let format = supported.sample_format();
+            let config: StreamConfig = supported.into();
+
+            let samples = Arc::new(Mutex::new(VecDeque::with_capacity(window)));
+            let stream = match format {
+                SampleFormat::F32 => build::<f32>(&device, &config, &samples, window)?,
+                SampleFormat::I16 => build::<i16>(&device, &config, &samples, window)?,
+                SampleFormat::U16 => build::<u16>(&device, &config, &samples, window)?,
+                SampleFormat::I32 => build::<i32>(&device, &config, &samples, window)?,
+                format => return Err(format!("unsupported sample format {}", format).into()),
+            };
+            stream.play()?;
+
+            Ok(AudioInput {
+                samples,
+                sample_rate: config.sample_rate.0,
+                _stream: stream,
+            })
+        }
+
+        /// The levels of the latest window of sound.
+        pub fn levels(&self) -> AudioLevels {
+            let samples = self.samples.lock().unwrap();
+            let (front, back) = samples.as_slices();
+            AudioLevels::measure(&[front, back].concat(), self.sample_rate)
+        }
+    }
+
+    fn build<T>(
+        device: &cpal::Device,
+        config: &StreamConfig,
+        samples: &Arc<Mutex<VecDeque<f32>>>,
+        window: usize,
+    ) -> Result<Stream, cpal::BuildStreamError>
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
+        let channels = config.channels.max(1) as usize;
+        let samples = Arc::clone(samples);
+        device.build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let mut samples = samples.lock().unwrap();
+                for frame in data.chunks(channels) {
+                    let sum: f32 = frame.iter().map(|&s| f32::from_sample(s)).sum();
+                    if samples.len() >= window {
+                        samples.pop_front();
+                    }
+                    samples.push_back(sum / frame.len() as f32);
+                }
+            },
+            |err| eprintln!("Audio input failed: {}", err),
+            None,
+        )
+    }
+}
+
+/// Stands in for the input without the `audio` feature, failing to start.
+#[cfg(not(feature = "audio"))]
+pub enum AudioInput {}
+
+#[cfg(not(feature = "audio"))]
+impl AudioInput {
+    pub fn start(
+        _device: Option<&str>,
+        _window: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("capturing audio needs the audio feature".into())
+    }
+
+    pub fn levels(&self) -> AudioLevels {
+        match *self {}
+    }
+}
@@ -0,0 +1,77 @@
+//! An image drawn behind the growth, e.g. a photograph or a scanned texture for the curves to be
+//! composed over.
+
+use crate::raster;
+use crate::Color;
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How the image is laid into the frame it is drawn behind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fit {
+    /// As large as fits whole in the frame, keeping its proportions.
+    #[default]
+    Contain,
+    /// As small as fills the whole frame, keeping its proportions and cropping the rest.
+    Cover,
+    /// Stretched to the frame.
+    Stretch,
+}
+
+pub struct Backdrop {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    /// Multiplies the alpha of every pixel.
+    pub opacity: f32,
+    pub fit: Fit,
+}
+
+impl Backdrop {
+    pub fn open<P: AsRef<Path>>(path: P, opacity: f32, fit: Fit) -> image::ImageResult<Self> {
+        let image = image::open(path)?.to_rgba8();
+        let (width, height) = image.dimensions();
+        let pixels = image
+            .pixels()
+            .map(|p| p.0.map(|c| c as f32 / 255.0))
+            .collect();
+
+        Ok(Backdrop {
+            width: width as usize,
+            height: height as usize,
+            pixels,
+            opacity,
+            fit,
+        })
+    }
+
+    /// The `(min, max)` corners the image covers when fitted into the frame between `min` and
+    /// `max`, which it sticks out of when covering it.
+    pub fn placement(&self, (min, max): (Vec2, Vec2)) -> (Vec2, Vec2) {
+        let frame = max - min;
+        let image = vec2(self.width as f32, self.height as f32).max(Vec2::ONE);
+        let size = match self.fit {
+            Fit::Contain => image * (frame / image).min_element(),
+            Fit::Cover => image * (frame / image).max_element(),
+            Fit::Stretch => frame,
+        };
+        let center = (min + max) / 2.0;
+        (center - size / 2.0, center + size / 2.0)
+    }
+
+    /// The color at `uv`, from `(0, 0)` at the top left corner of the image to `(1, 1)` at the
+    /// bottom right one, blended between the nearest pixels and faded by `opacity`.
+    pub fn sample(&self, uv: Vec2) -> Color {
+        let mut color = raster::bilinear(&self.pixels, self.width, self.height, uv);
+        color[3] *= self.opacity.clamp(0.0, 1.0);
+        color
+    }
+
+    /// The image fitted into `frame` as a mesh of colored vertices, see `raster::image_grid()`.
+    pub fn grid(&self, frame: (Vec2, Vec2), cells: usize) -> (Vec<(Vec2, Color)>, Vec<[usize; 3]>) {
+        let size = (self.width, self.height);
+        raster::image_grid(size, self.placement(frame), cells, |uv| self.sample(uv))
+    }
+}
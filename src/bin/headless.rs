@@ -0,0 +1,18 @@
+//! Grows a curve without opening a window and writes the result to disk, like the app does with
+//! `--headless`. See the `cli` module for the options, or run it with `--help`.
+
+use differential_lines::cli::{self, Args};
+use std::process;
+
+fn main() {
+    let args = Args::parse("headless");
+    let result = match (&args.sweep, &args.play) {
+        (Some(sweep), _) => cli::run_sweep(&args, sweep),
+        (None, Some(play)) => cli::run_play(&args, play),
+        (None, None) => cli::run_headless(args),
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
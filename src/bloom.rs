@@ -0,0 +1,311 @@
+//! A glow around the brightest parts of an image, for a neon look without compositing it
+//! elsewhere: the parts brighter than a threshold are blurred and added back on top.
+//!
+//! `Bloom::apply()` runs on the CPU. With the `gpu` feature, `GpuBloom` runs the same passes in
+//! wgpu compute shaders, which keeps up with blooming a window sized image every frame.
+
+use crate::raster::Canvas;
+use crate::Color;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bloom {
+    /// How bright a component has to be to glow, only what is above it spreading.
+    pub threshold: f32,
+    /// How far the glow spreads, in pixels.
+    pub radius: f32,
+    /// Multiplies the glow added back.
+    pub intensity: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 0.5,
+            radius: 8.0,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl Bloom {
+    /// Adds the glow to `canvas`.
+    pub fn apply(&self, canvas: &mut Canvas) {
+        let (width, height) = (canvas.width(), canvas.height());
+        let kernel = self.kernel();
+        let bright: Vec<Color> = canvas
+            .pixels()
+            .iter()
+            .map(|pixel| {
+                let mut bright = [0.0; 4];
+                for c in 0..3 {
+                    bright[c] = (pixel[c] - self.threshold).max(0.0);
+                }
+                bright
+            })
+            .collect();
+        let blurred = blur(&bright, width, height, &kernel, (1, 0));
+        let blurred = blur(&blurred, width, height, &kernel, (0, 1));
+
+        for (pixel, glow) in canvas.pixels_mut().iter_mut().zip(blurred) {
+            for c in 0..3 {
+                pixel[c] += self.intensity * glow[c];
+            }
+        }
+    }
+
+    /// The weights of a Gaussian reaching `radius` pixels out at three standard deviations, from
+    /// the middle outwards.
+    fn kernel(&self) -> Vec<f32> {
+        let sigma = (self.radius / 3.0).max(f32::EPSILON);
+        let weights: Vec<f32> = (0..=self.radius.max(0.0).ceil() as usize)
+            .map(|x| (-((x * x) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+        weights.iter().map(|weight| weight / total).collect()
+    }
+}
+
+/// Blurs `pixels` along `(dx, dy)` with the symmetric `kernel`, repeating the pixels at the edges.
+fn blur(
+    pixels: &[Color],
+    width: usize,
+    height: usize,
+    kernel: &[f32],
+    (dx, dy): (usize, usize),
+) -> Vec<Color> {
+    let mut blurred = vec![[0.0; 4]; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0; 4];
+            let mut add = |(tx, ty): (usize, usize), weight: f32| {
+                let pixel = pixels[ty * width + tx];
+                for c in 0..4 {
+                    sum[c] += weight * pixel[c];
+                }
+            };
+            add((x, y), kernel[0]);
+            for (offset, &weight) in kernel.iter().enumerate().skip(1) {
+                let (ox, oy) = (offset * dx, offset * dy);
+                add(((x + ox).min(width - 1), (y + oy).min(height - 1)), weight);
+                add((x.saturating_sub(ox), y.saturating_sub(oy)), weight);
+            }
+            blurred[y * width + x] = sum;
+        }
+    }
+    blurred
+}
+
+#[cfg(feature = "gpu")]
+pub use self::gpu::GpuBloom;
+
+#[cfg(feature = "gpu")]
+mod gpu {
+    use super::Bloom;
+    use crate::gpu::{self, GpuError};
+    use crate::raster::Canvas;
+    use bytemuck::{Pod, Zeroable};
+
+    /// Matches `@workgroup_size` of the bloom shaders in both dimensions.
+    const WORKGROUP_SIZE: u32 = 8;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct Params {
+        width: u32,
+        height: u32,
+        radius: u32,
+        threshold: f32,
+        intensity: f32,
+        sigma: f32,
+        _padding: [u32; 2],
+    }
+
+    /// A GPU device with the bloom shaders loaded.
+    pub struct GpuBloom {
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        layout: wgpu::BindGroupLayout,
+        passes: [wgpu::ComputePipeline; 4],
+    }
+
+    impl GpuBloom {
+        /// Picks the default adapter and compiles the shaders, blocking until done.
+        pub fn new() -> Result<Self, GpuError> {
+            let (device, queue) = gpu::open_device(wgpu::Limits::downlevel_defaults())?;
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("bloom"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("bloom.wgsl").into()),
+            });
+
+            let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            };
+            let storage = wgpu::BufferBindingType::Storage { read_only: false };
+            let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    entry(0, wgpu::BufferBindingType::Uniform),
+                    entry(1, storage),
+                    entry(2, storage),
+                    entry(3, storage),
+                ],
+            });
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+            let pipeline = |entry_point| {
+                device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(entry_point),
+                    layout: Some(&pipeline_layout),
+                    module: &module,
+                    entry_point,
+                    compilation_options: Default::default(),
+                    cache: None,
+                })
+            };
+
+            Ok(GpuBloom {
+                passes: [
+                    pipeline("threshold"),
+                    pipeline("blur_x"),
+                    pipeline("blur_y"),
+                    pipeline("composite"),
+                ],
+                device,
+                queue,
+                layout,
+            })
+        }
+
+        /// Whether `canvas` fits into the buffers the device can bind.
+        pub fn fits(&self, canvas: &Canvas) -> bool {
+            let size = std::mem::size_of_val(canvas.pixels()) as u64;
+            size <= self.device.limits().max_storage_buffer_binding_size as u64
+        }
+
+        /// Adds the glow of `bloom` to `canvas` like `Bloom::apply()` does, which has to `fit()`.
+        pub fn apply(&mut self, bloom: &Bloom, canvas: &mut Canvas) -> Result<(), GpuError> {
+            let (width, height) = (canvas.width() as u32, canvas.height() as u32);
+            let size = std::mem::size_of_val(canvas.pixels()) as u64;
+            if size == 0 {
+                return Ok(());
+            }
+            let params = Params {
+                width,
+                height,
+                radius: bloom.radius.max(0.0).ceil() as u32,
+                threshold: bloom.threshold,
+                intensity: bloom.intensity,
+                sigma: (bloom.radius / 3.0).max(f32::EPSILON),
+                _padding: [0; 2],
+            };
+
+            let buffer = |label, size: u64, usage| {
+                self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            };
+            let storage = wgpu::BufferUsages::STORAGE;
+            let copy_dst = wgpu::BufferUsages::COPY_DST;
+            let uniform = buffer(
+                "params",
+                std::mem::size_of::<Params>() as u64,
+                wgpu::BufferUsages::UNIFORM | copy_dst,
+            );
+            let image = buffer(
+                "image",
+                size,
+                storage | copy_dst | wgpu::BufferUsages::COPY_SRC,
+            );
+            let glow = buffer("glow", size, storage);
+            let scratch = buffer("scratch", size, storage);
+            let readback = buffer("readback", size, wgpu::BufferUsages::MAP_READ | copy_dst);
+            self.queue
+                .write_buffer(&uniform, 0, bytemuck::bytes_of(&params));
+            self.queue
+                .write_buffer(&image, 0, bytemuck::cast_slice(canvas.pixels()));
+
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.layout,
+                entries: &[&uniform, &image, &glow, &scratch]
+                    .iter()
+                    .enumerate()
+                    .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                        binding: binding as u32,
+                        resource: buffer.as_entire_binding(),
+                    })
+                    .collect::<Vec<_>>(),
+            });
+
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_bind_group(0, &bind_group, &[]);
+                for pipeline in &self.passes {
+                    pass.set_pipeline(pipeline);
+                    pass.dispatch_workgroups(
+                        width.div_ceil(WORKGROUP_SIZE),
+                        height.div_ceil(WORKGROUP_SIZE),
+                        1,
+                    );
+                }
+            }
+            encoder.copy_buffer_to_buffer(&image, 0, &readback, 0, size);
+            self.queue.submit(Some(encoder.finish()));
+
+            let pixels = gpu::read_back(&self.device, &readback, size)?;
+            canvas.pixels_mut().copy_from_slice(&pixels);
+            Ok(())
+        }
+    }
+}
+
+/// Applies blooms on the GPU once `enable_gpu()` found one, and on the CPU otherwise.
+#[derive(Default)]
+pub struct BloomPass {
+    #[cfg(feature = "gpu")]
+    gpu: Option<GpuBloom>,
+}
+
+impl BloomPass {
+    #[cfg(feature = "gpu")]
+    pub fn enable_gpu(&mut self) -> Result<(), crate::gpu::GpuError> {
+        self.gpu = Some(GpuBloom::new()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "gpu")]
+    pub fn uses_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    /// Adds the glow of `bloom` to `canvas`. Canvases too large for the GPU are bloomed on the
+    /// CPU, and so is everything after the GPU fails once.
+    pub fn apply(&mut self, bloom: &Bloom, canvas: &mut Canvas) {
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = &mut self.gpu {
+            if gpu.fits(canvas) {
+                match gpu.apply(bloom, canvas) {
+                    Ok(()) => return,
+                    Err(_) => self.gpu = None,
+                }
+            }
+        }
+        bloom.apply(canvas);
+    }
+}
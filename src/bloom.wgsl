@@ -0,0 +1,83 @@
+// The bloom passes, see bloom.rs.
+//
+// The parts of the image above the threshold are copied into the glow, blurred horizontally into
+// the scratch buffer and back vertically, then added on top of the image.
+
+struct Params {
+    width: u32,
+    height: u32,
+    radius: u32,
+    threshold: f32,
+    intensity: f32,
+    sigma: f32,
+    _padding: vec2<u32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> image: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read_write> glow: array<vec4<f32>>;
+@group(0) @binding(3) var<storage, read_write> scratch: array<vec4<f32>>;
+
+fn weight(offset: u32) -> f32 {
+    let x = f32(offset);
+    return exp(-x * x / (2.0 * params.sigma * params.sigma));
+}
+
+// The sum of the weights of the whole kernel, which they are divided by.
+fn total_weight() -> f32 {
+    var total = weight(0u);
+    for (var offset = 1u; offset <= params.radius; offset++) {
+        total += 2.0 * weight(offset);
+    }
+    return total;
+}
+
+fn index(x: u32, y: u32) -> u32 {
+    return y * params.width + x;
+}
+
+@compute @workgroup_size(8, 8)
+fn threshold(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.width || id.y >= params.height {
+        return;
+    }
+    let i = index(id.x, id.y);
+    glow[i] = vec4(max(image[i].rgb - vec3(params.threshold), vec3(0.0)), 0.0);
+}
+
+@compute @workgroup_size(8, 8)
+fn blur_x(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.width || id.y >= params.height {
+        return;
+    }
+    var sum = weight(0u) * glow[index(id.x, id.y)];
+    for (var offset = 1u; offset <= params.radius; offset++) {
+        let after = min(id.x + offset, params.width - 1u);
+        let before = select(0u, id.x - offset, id.x >= offset);
+        sum += weight(offset) * (glow[index(after, id.y)] + glow[index(before, id.y)]);
+    }
+    scratch[index(id.x, id.y)] = sum / total_weight();
+}
+
+@compute @workgroup_size(8, 8)
+fn blur_y(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.width || id.y >= params.height {
+        return;
+    }
+    var sum = weight(0u) * scratch[index(id.x, id.y)];
+    for (var offset = 1u; offset <= params.radius; offset++) {
+        let after = min(id.y + offset, params.height - 1u);
+        let before = select(0u, id.y - offset, id.y >= offset);
+        sum += weight(offset) * (scratch[index(id.x, after)] + scratch[index(id.x, before)]);
+    }
+    glow[index(id.x, id.y)] = sum / total_weight();
+}
+
+@compute @workgroup_size(8, 8)
+fn composite(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.width || id.y >= params.height {
+        return;
+    }
+    let i = index(id.x, id.y);
+    image[i] = vec4(image[i].rgb + params.intensity * glow[i].rgb, image[i].a);
+}
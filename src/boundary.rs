@@ -0,0 +1,230 @@
+//! Shapes the growth can be confined to.
+
+use crate::svg;
+use crate::Polyline;
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// What happens to a particle that would move out of the boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BoundaryResponse {
+    /// Stop the particle where its move crosses the boundary.
+    Clamp,
+    /// Bounce the rest of the move back inside, mirrored about the boundary.
+    Reflect,
+}
+
+/// A region of the plane the particles are kept inside of.
+#[derive(Deserialize, Serialize)]
+pub enum Boundary {
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    Rect {
+        min: Vec2,
+        max: Vec2,
+    },
+    ImageMask(ImageMask),
+    /// The area enclosed by a set of polygons, using the even-odd rule so inner rings are holes.
+    SvgPath(Vec<Polyline>),
+}
+
+/// A grayscale image laid over the plane. Pixels at least as bright as the threshold are inside.
+#[derive(Deserialize, Serialize)]
+pub struct ImageMask {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+    center: Vec2,
+    /// Simulation units covered by one pixel.
+    scale: f32,
+    threshold: f32,
+}
+
+impl ImageMask {
+    /// Loads an image as a mask centered at `center`, scaled so that its width spans `width`
+    /// simulation units.
+    pub fn open<P: AsRef<Path>>(path: P, center: Vec2, width: f32) -> image::ImageResult<Self> {
+        let image = image::open(path)?.to_luma8();
+        let (w, h) = image.dimensions();
+        let values = image.pixels().map(|p| p.0[0] as f32 / 255.0).collect();
+
+        Ok(ImageMask {
+            width: w as usize,
+            height: h as usize,
+            values,
+            center,
+            scale: width / w as f32,
+            threshold: 0.5,
+        })
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// The mask value under `p`, or 0 outside of the image.
+    pub fn sample(&self, p: Vec2) -> f32 {
+        let local = (p - self.center) / self.scale;
+        let x = (local.x + self.width as f32 / 2.0).floor();
+        let y = (self.height as f32 / 2.0 - local.y).floor();
+
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return 0.0;
+        }
+        self.values[y as usize * self.width + x as usize]
+    }
+
+    /// The slope of the mask at `p`, pointing toward brighter pixels.
+    pub fn gradient(&self, p: Vec2) -> Vec2 {
+        let h = self.scale;
+        let dx = self.sample(p + vec2(h, 0.0)) - self.sample(p - vec2(h, 0.0));
+        let dy = self.sample(p + vec2(0.0, h)) - self.sample(p - vec2(0.0, h));
+        vec2(dx, dy)
+    }
+}
+
+impl Boundary {
+    /// Parses SVG path data into a polygonal boundary, see `svg::parse_path()`.
+    pub fn svg_path(d: &str) -> Result<Self, svgtypes::Error> {
+        Ok(Boundary::SvgPath(svg::parse_path(d, 1.0)?))
+    }
+
+    pub fn contains(&self, p: Vec2) -> bool {
+        match self {
+            Boundary::Circle { center, radius } => (p - *center).length() <= *radius,
+            Boundary::Rect { min, max } => p.cmpge(*min).all() && p.cmple(*max).all(),
+            Boundary::ImageMask(mask) => mask.sample(p) >= mask.threshold,
+            Boundary::SvgPath(polygons) => {
+                let mut inside = false;
+                for_each_segment(polygons, |a, b| {
+                    if (a.y > p.y) != (b.y > p.y)
+                        && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x)
+                    {
+                        inside = !inside;
+                    }
+                });
+                inside
+            }
+        }
+    }
+
+    /// The outward facing normal of the boundary near `p`.
+    fn normal_at(&self, p: Vec2) -> Vec2 {
+        let normal = match self {
+            Boundary::Circle { center, .. } => p - *center,
+            Boundary::Rect { min, max } => {
+                let distances = [p.x - min.x, max.x - p.x, p.y - min.y, max.y - p.y];
+                let normals = [
+                    vec2(-1.0, 0.0),
+                    vec2(1.0, 0.0),
+                    vec2(0.0, -1.0),
+                    vec2(0.0, 1.0),
+                ];
+                let closest = (0..4)
+                    .min_by(|&a, &b| distances[a].abs().partial_cmp(&distances[b].abs()).unwrap())
+                    .unwrap();
+                normals[closest]
+            }
+            // The mask gets darker going out, so the normal points down its gradient.
+            Boundary::ImageMask(mask) => -mask.gradient(p),
+            Boundary::SvgPath(polygons) => {
+                let mut closest = (f32::INFINITY, Vec2::ZERO);
+                for_each_segment(polygons, |a, b| {
+                    let ab = b - a;
+                    let t =
+                        ((p - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                    let distance = (p - (a + ab * t)).length();
+                    if distance < closest.0 {
+                        closest = (distance, ab.perp());
+                    }
+                });
+
+                let normal = closest.1.normalize_or_zero();
+                if self.contains(p + normal) {
+                    -normal
+                } else {
+                    normal
+                }
+            }
+        };
+
+        normal.normalize_or_zero()
+    }
+
+    /// Returns where a particle moving from `from` to `to` ends up once confined to the boundary.
+    ///
+    /// Moves that stay inside are left untouched. Particles that start outside are left free, so
+    /// that a boundary added mid-run doesn't snap the whole curve.
+    pub fn confine(&self, from: Vec2, to: Vec2, response: BoundaryResponse) -> Vec2 {
+        if self.contains(to) || !self.contains(from) {
+            return to;
+        }
+
+        // Bisect the move to find the last point still inside.
+        let (mut inside, mut outside) = (0.0, 1.0);
+        for _ in 0..16 {
+            let t = (inside + outside) / 2.0;
+            if self.contains(from.lerp(to, t)) {
+                inside = t;
+            } else {
+                outside = t;
+            }
+        }
+        let hit = from.lerp(to, inside);
+
+        match response {
+            BoundaryResponse::Clamp => hit,
+            BoundaryResponse::Reflect => {
+                let normal = self.normal_at(from.lerp(to, outside));
+                let rest = to - hit;
+                let reflected = hit + rest - normal * (2.0 * rest.dot(normal));
+                if self.contains(reflected) {
+                    reflected
+                } else {
+                    hit
+                }
+            }
+        }
+    }
+}
+
+/// A horizontal line the particles can't fall through, so that growth pulled down by gravity
+/// comes to rest and piles up on it.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Floor {
+    /// The height of the line, in simulation units.
+    pub height: f32,
+    /// The fraction of their sideways move particles touching the floor lose, from 0 for sliding
+    /// freely to 1 for sticking where they land.
+    #[serde(default)]
+    pub friction: f32,
+}
+
+impl Floor {
+    /// Where a particle moving from `from` to `to` ends up, stopped on the floor if it would
+    /// sink below it.
+    pub fn confine(&self, from: Vec2, to: Vec2) -> Vec2 {
+        if to.y >= self.height {
+            return to;
+        }
+        let slide = (to.x - from.x) * (1.0 - self.friction.clamp(0.0, 1.0));
+        vec2(from.x + slide, self.height)
+    }
+}
+
+fn for_each_segment<F>(polygons: &[Polyline], mut f: F)
+where
+    F: FnMut(Vec2, Vec2),
+{
+    for polygon in polygons {
+        let points = &polygon.points;
+        for i in 0..points.len() {
+            f(points[i], points[(i + 1) % points.len()]);
+        }
+    }
+}
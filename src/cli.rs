@@ -0,0 +1,845 @@
+//! The command line shared by the app and the headless binary, so either can be scripted in
+//! parameter sweeps without editing any file.
+//!
+//! ```text
+//! [--config <path>] [--steps <n>] [--seed <u64>] [--shape <shape>] [--svg <path>]
+//! [--text <text> --font <path>] [--particles <n>] [--radius <r>] [--out <path>]
+//! [--resume <path>] [--save-state <path>] [--history-svg <path>] [--replay <path>] [--gpu]
+//! [--headless] [--3d]
+//! ```
+//!
+//! Settings come from `config.toml`, with the other options overriding the spawn settings. The
+//! output format follows the extension of `--out`: `.json` for raw geometry, `.png` for an image
+//! of the `[export]` size, `.obj` or `.ply` for a solid extruded as set in `[mesh]`, `.dxf` for
+//! cutting as sized in `[dxf]`, `.gcode` for plotters as set in `[gcode]`, SVG otherwise.
+//!
+//! `--save-state` writes a checkpoint of the whole simulation once done, which `--resume` grows
+//! further for another `--steps`, ignoring the spawn settings.
+//!
+//! `--history-svg` also writes the outlines kept as set in the `[history]` config section, each
+//! as its own layer, see `History::to_svg()`.
+//!
+//! `--replay` records every step to a compact replay file, see `replay`, for the `play` subcommand
+//! to render later.
+//!
+//! `--gpu` computes the forces on the GPU, when built with the `gpu` feature.
+//!
+//! `--3d` grows a single closed curve in space instead, see `SpaceCurve`, written as an OBJ file
+//! unless `--out` ends in `.png`.
+//!
+//! ```text
+//! sweep --param <path>=<start>:<end>:<count> [--param <path>=<value>,...] [--dir <path>]
+//!       [--format svg|png|json|obj|ply|dxf|gcode] [--parallel]
+//! ```
+//!
+//! The `sweep` subcommand grows the curves for every combination of the given values of config
+//! parameters, e.g. `--param simulation.curvature_bias=0:1:5`, with the same seed and the other
+//! options as above. Each run is written to `--dir` as `sweep-<index>.<format>`, along with
+//! `sweep.csv` listing the parameters and metrics of each one.
+//!
+//! ```text
+//! play <replay> [--out <path>] [--width <px>] [--height <px>] [--every <n>] [--fps <n>]
+//! ```
+//!
+//! The `play` subcommand renders the steps of a replay without simulating them again, framed to
+//! fit all of them, into a GIF, a video through ffmpeg as set in `[video]`, or otherwise a
+//! directory of numbered PNG frames. `--every` skips steps to speed the growth up.
+
+use crate::config::TextConfig;
+use crate::raster::{self, Canvas};
+use crate::replay::{Frame, ReplayHeader};
+use crate::sweep::{self, Metrics, SweepParameter};
+use crate::{
+    BloomPass, Config, GifRecorder, History, Mesh, MetricsLog, OrbitCamera, ParticleSystem,
+    ReplayReader, ReplayWriter, ShaderPass, SpaceCurve, SpawnShape, VideoRecorder,
+};
+use clap::{value_t, values_t, App, Arg, ArgMatches, SubCommand};
+use glam::Vec2;
+use rayon::prelude::*;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+/// The parsed command line.
+#[derive(Clone, Debug)]
+pub struct Args {
+    pub config: PathBuf,
+    pub steps: u64,
+    /// Random when not given.
+    pub seed: Option<u64>,
+    pub shape: Option<SpawnShape>,
+    pub svg: Option<PathBuf>,
+    /// Always given along with `font`.
+    pub text: Option<String>,
+    pub font: Option<PathBuf>,
+    pub particles: Option<usize>,
+    pub radius: Option<f32>,
+    pub output: Option<PathBuf>,
+    pub resume: Option<PathBuf>,
+    pub save_state: Option<PathBuf>,
+    pub history_svg: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    /// The CSV file rows of `MetricsLog` are appended to.
+    pub metrics: Option<PathBuf>,
+    pub gpu: bool,
+    /// Runs without opening a window, like the headless binary always does.
+    pub headless: bool,
+    /// Grows a curve in space instead of the plane, from `--3d`.
+    pub space: bool,
+    /// Opens a second window showing the debug views, from `--debug-window`.
+    pub debug_window: bool,
+    /// Set by the `sweep` subcommand.
+    pub sweep: Option<Sweep>,
+    /// Set by the `play` subcommand.
+    pub play: Option<Play>,
+}
+
+/// The options of the `sweep` subcommand.
+#[derive(Clone, Debug)]
+pub struct Sweep {
+    pub parameters: Vec<SweepParameter>,
+    pub dir: PathBuf,
+    /// The extension of the files written, which sets their format like `--out` does.
+    pub format: String,
+    /// Runs several simulations at once.
+    pub parallel: bool,
+}
+
+/// The options of the `play` subcommand.
+#[derive(Clone, Debug)]
+pub struct Play {
+    pub replay: PathBuf,
+    /// A `.gif`, a video, or otherwise the directory the frames are written to as PNGs.
+    pub output: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    /// Only every this many recorded steps is rendered.
+    pub every: usize,
+    /// Frames per second of GIFs and videos, the `[video]` one when not given.
+    pub fps: Option<u32>,
+}
+
+impl Args {
+    /// Parses the arguments the program was started with, printing the usage and exiting if they
+    /// are invalid or `--help` was given.
+    pub fn parse(name: &str) -> Self {
+        Args::from_matches(&app(name).get_matches())
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Self {
+        let path = |name: &str| path_of(matches, name);
+
+        Args {
+            config: path("config").unwrap(),
+            steps: value_t!(matches, "steps", u64).unwrap_or_else(|err| err.exit()),
+            seed: parsed(matches, "seed"),
+            shape: parsed(matches, "shape"),
+            svg: path("svg"),
+            text: matches.value_of("text").map(str::to_string),
+            font: path("font"),
+            particles: parsed(matches, "particles"),
+            radius: parsed(matches, "radius"),
+            output: path("output"),
+            resume: path("resume"),
+            save_state: path("save-state"),
+            history_svg: path("history-svg"),
+            replay: path("replay"),
+            metrics: path("metrics"),
+            gpu: matches.is_present("gpu"),
+            headless: matches.is_present("headless"),
+            space: matches.is_present("3d"),
+            debug_window: matches.is_present("debug-window"),
+            sweep: matches.subcommand_matches("sweep").map(|matches| Sweep {
+                parameters: values_t!(matches, "param", SweepParameter)
+                    .unwrap_or_else(|err| err.exit()),
+                dir: path_of(matches, "dir").unwrap(),
+                format: matches.value_of("format").unwrap().to_string(),
+                parallel: matches.is_present("parallel"),
+            }),
+            play: matches.subcommand_matches("play").map(|matches| Play {
+                replay: path_of(matches, "replay").unwrap(),
+                output: path_of(matches, "output").unwrap(),
+                width: parsed(matches, "width").unwrap(),
+                height: parsed(matches, "height").unwrap(),
+                every: parsed(matches, "every").unwrap(),
+                fps: parsed(matches, "fps"),
+            }),
+        }
+    }
+
+    /// Overrides the spawn settings of `config` with the ones given.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(shape) = &self.shape {
+            config.spawn.shape = shape.clone();
+        }
+        if let Some(svg) = &self.svg {
+            config.spawn.svg = Some(svg.clone());
+        }
+        if let (Some(text), Some(font)) = (&self.text, &self.font) {
+            config.spawn.text = Some(TextConfig {
+                text: text.clone(),
+                font: font.clone(),
+            });
+        }
+        if let Some(particles) = self.particles {
+            config.spawn.particles = particles;
+        }
+        if let Some(radius) = self.radius {
+            config.spawn.radius = radius;
+        }
+    }
+}
+
+fn path_of(matches: &ArgMatches, name: &str) -> Option<PathBuf> {
+    matches.value_of_os(name).map(PathBuf::from)
+}
+
+/// The value of argument `name` if given, exiting with an error if it doesn't parse.
+fn parsed<T: FromStr>(matches: &ArgMatches, name: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    if !matches.is_present(name) {
+        return None;
+    }
+    Some(value_t!(matches, name, T).unwrap_or_else(|err| err.exit()))
+}
+
+fn app(name: &str) -> App<'static, 'static> {
+    let value = |name: &'static str, value_name: &'static str, help: &'static str| {
+        Arg::with_name(name)
+            .long(name)
+            .value_name(value_name)
+            .takes_value(true)
+            .help(help)
+    };
+
+    App::new(name.to_string())
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Grows curves by differential line growth")
+        .arg(value("config", "PATH", "The settings to grow with").default_value("config.toml"))
+        .arg(
+            value("steps", "N", "How many steps to grow for without a window")
+                .default_value("1000"),
+        )
+        .arg(value(
+            "seed",
+            "U64",
+            "Grows the same curves again for the same seed",
+        ))
+        .arg(value(
+            "shape",
+            "SHAPE",
+            "circle, ring, line, rectangle, star, random_blob or a TOML polygon",
+        ))
+        .arg(value("svg", "PATH", "Grows from the shapes of an SVG file"))
+        .arg(value("text", "TEXT", "Grows from the outlines of some text").requires("font"))
+        .arg(value("font", "PATH", "The font to set --text in").requires("text"))
+        .arg(value(
+            "particles",
+            "N",
+            "How many particles the initial curve has",
+        ))
+        .arg(value("radius", "R", "How large the initial curve is"))
+        .arg(
+            value(
+                "output",
+                "PATH",
+                "Where to write the result, as .json, .png, .obj, .ply, .dxf, .gcode or SVG",
+            )
+            .visible_alias("out"),
+        )
+        .arg(value("resume", "PATH", "Continues a saved simulation"))
+        .arg(value(
+            "save-state",
+            "PATH",
+            "Saves the whole simulation once done",
+        ))
+        .arg(value(
+            "history-svg",
+            "PATH",
+            "Writes the outlines kept every [history] steps as SVG layers",
+        ))
+        .arg(value(
+            "replay",
+            "PATH",
+            "Records every step to a replay file for the play subcommand",
+        ))
+        .arg(value(
+            "metrics",
+            "PATH",
+            "Appends measurements of the curves every [metrics] steps to a CSV file",
+        ))
+        .arg(
+            Arg::with_name("gpu").long("gpu").help(
+                "Computes the forces, and blooms in the app, on the GPU, with the gpu feature",
+            ),
+        )
+        .arg(
+            Arg::with_name("headless")
+                .long("headless")
+                .help("Grows for --steps without opening a window and writes the result"),
+        )
+        .arg(
+            Arg::with_name("3d")
+                .long("3d")
+                .help("Grows a single curve in space, written as OBJ without a window"),
+        )
+        .arg(
+            Arg::with_name("debug-window")
+                .long("debug-window")
+                .help("Opens a second window with debug views of the forces and neighbors"),
+        )
+        .subcommand(
+            SubCommand::with_name("sweep")
+                .about("Grows for --steps with every combination of some parameters")
+                .arg(
+                    value(
+                        "param",
+                        "PATH=VALUES",
+                        "A config parameter and its values, as <start>:<end>:<count> or a list",
+                    )
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(true)
+                    .validator(|value| value.parse::<SweepParameter>().map(|_| ())),
+                )
+                .arg(value("dir", "PATH", "Where to write the runs").default_value("output/sweep"))
+                .arg(
+                    value("format", "FORMAT", "How to write each run")
+                        .possible_values(&["svg", "png", "json", "obj", "ply", "dxf", "gcode"])
+                        .default_value("svg"),
+                )
+                .arg(
+                    Arg::with_name("parallel")
+                        .long("parallel")
+                        .help("Runs several simulations at once"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("play")
+                .about("Renders a replay recorded with --replay")
+                .arg(
+                    Arg::with_name("replay")
+                        .value_name("REPLAY")
+                        .help("The replay to render")
+                        .required(true),
+                )
+                .arg(
+                    value(
+                        "output",
+                        "PATH",
+                        "A .gif, a video, or a directory to write PNG frames to",
+                    )
+                    .visible_alias("out")
+                    .default_value("output/play"),
+                )
+                .arg(value("width", "PX", "The width of the frames").default_value("1920"))
+                .arg(value("height", "PX", "The height of the frames").default_value("1080"))
+                .arg(
+                    value("every", "N", "Renders only every this many recorded steps")
+                        .default_value("1"),
+                )
+                .arg(value("fps", "N", "Frames per second of GIFs and videos")),
+        )
+}
+
+/// Grows the curves for `args.steps` without a window and writes them as `args.output`, or to
+/// `output/diff-lines-<seed>-<steps>.svg` by default.
+pub fn run_headless(args: Args) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load_or_default(&args.config)?;
+    args.apply(&mut config);
+    if args.space {
+        return run_space(&args, &config);
+    }
+
+    let mut ps = match &args.resume {
+        Some(path) => ParticleSystem::load_state(path)?,
+        None => config.new_particle_system(args.seed.unwrap_or_else(rand::random))?,
+    };
+    if args.gpu {
+        enable_gpu(&mut ps)?;
+    }
+    let path = args.output.clone();
+    let stacks_history = config.mesh.stack_history
+        && path
+            .as_ref()
+            .and_then(|path| path.extension())
+            .is_some_and(|extension| extension == "obj" || extension == "ply");
+    let mut history = (args.history_svg.is_some() || stacks_history)
+        .then(|| History::new(config.history.every, config.history.depth));
+    let mut metrics = match &args.metrics {
+        Some(path) => Some(MetricsLog::create(path, config.metrics.every)?),
+        None => None,
+    };
+    let mut replay = match &args.replay {
+        Some(path) => Some(ReplayWriter::create(path, &ps)?),
+        None => None,
+    };
+    let start = Instant::now();
+    for _ in 0..args.steps {
+        if ps.is_finished() {
+            println!("Every curve reached its growth budget");
+            break;
+        }
+        config.simulation.apply_schedules(&mut ps, &[])?;
+        let step_start = Instant::now();
+        ps.update();
+        check_script(&mut ps)?;
+        if let Some(metrics) = &mut metrics {
+            metrics.record(&ps, step_start.elapsed())?;
+        }
+        if let Some(history) = &mut history {
+            history.record(&ps);
+        }
+        if let Some(replay) = &mut replay {
+            replay.record(&ps)?;
+        }
+    }
+    let elapsed = start.elapsed();
+    if let (Some(replay), Some(replay_path)) = (replay, &args.replay) {
+        replay.finish()?;
+        println!("Saved the replay to {}", replay_path.display());
+    }
+
+    // A resumed simulation keeps the seed it was started with.
+    let (seed, steps) = (ps.seed(), ps.steps());
+    let path = path.unwrap_or_else(|| {
+        PathBuf::from("output").join(format!("diff-lines-{}-{}.svg", seed, steps))
+    });
+    save(&ps, &config, history.as_ref(), &path)?;
+
+    if let (Some(history_path), Some(history)) = (&args.history_svg, &history) {
+        if let Some(dir) = history_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(history_path, history.to_svg())?;
+        println!(
+            "Saved {} outlines to {}",
+            history.len(),
+            history_path.display()
+        );
+    }
+
+    if let Some(state_path) = &args.save_state {
+        ps.save_state(state_path)?;
+        println!("Saved the state to {}", state_path.display());
+    }
+
+    println!(
+        "Grew {} particles in {} steps ({:.2?}) with seed {}, saved {}",
+        ps.num_particles(),
+        steps,
+        elapsed,
+        seed,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Renders the frames of the replay of `play` as it says, with the colors of `args.config`.
+pub fn run_play(args: &Args, play: &Play) -> Result<(), Box<dyn Error>> {
+    let config = Config::load_or_default(&args.config)?;
+    let (width, height) = (play.width.max(1), play.height.max(1));
+
+    // A first pass finds the view fitting every frame, so that it stays still while playing.
+    let mut reader = ReplayReader::open(&play.replay)?;
+    let header = *reader.header();
+    let transforms = header.symmetry_transforms();
+    let bounds = match header.torus {
+        Some(torus) => Some((-torus.size() / 2.0, torus.size() / 2.0)),
+        None => {
+            let mut bounds: Option<(Vec2, Vec2)> = None;
+            while let Some(frame) = reader.next_frame()? {
+                if let Some((min, max)) = frame.bounding_box(&transforms) {
+                    bounds = Some(bounds.map_or((min, max), |(lo, hi)| (lo.min(min), hi.max(max))));
+                }
+            }
+            bounds
+        }
+    };
+    let (center, scale) = raster::fit_view(
+        bounds.unwrap_or((Vec2::ZERO, Vec2::ZERO)),
+        width,
+        height,
+        20.0,
+    );
+
+    let mut video_config = config.video.clone();
+    video_config.fps = play.fps.unwrap_or(video_config.fps).max(1);
+    let extension = play
+        .output
+        .extension()
+        .and_then(|extension| extension.to_str());
+    let mut gif = (extension == Some("gif")).then(|| GifRecorder::new(1000 / video_config.fps));
+    let mut video = match extension {
+        Some("mp4" | "mkv" | "mov" | "webm") => Some(VideoRecorder::start(
+            &play.output,
+            width,
+            height,
+            &video_config,
+        )?),
+        _ => None,
+    };
+    if gif.is_none() && video.is_none() {
+        fs::create_dir_all(&play.output)?;
+    }
+
+    let mut reader = ReplayReader::open(&play.replay)?;
+    let mut canvas = Canvas::new(width, height);
+    canvas.set_view(center, scale);
+    let mut rendered = 0;
+    let mut index = 0;
+    while let Some(frame) = reader.next_frame()? {
+        index += 1;
+        if (index - 1) % play.every.max(1) != 0 {
+            continue;
+        }
+        draw_frame(&mut canvas, &frame, &header, &config, scale);
+        let shown = config.post.applied(&canvas, frame.step);
+        if let Some(gif) = &mut gif {
+            gif.add_frame(&shown);
+        } else if let Some(video) = &mut video {
+            video.add_frame(&shown)?;
+        } else {
+            shown.save_png(play.output.join(format!("{:05}.png", rendered)))?;
+        }
+        rendered += 1;
+    }
+
+    if let Some(gif) = gif {
+        gif.save(&play.output)?;
+    }
+    if let Some(video) = video {
+        let status = video.finish()?;
+        if !status.success() {
+            return Err(format!("ffmpeg failed with {}", status).into());
+        }
+    }
+    println!(
+        "Rendered {} of {} steps to {}",
+        rendered,
+        index,
+        play.output.display()
+    );
+    Ok(())
+}
+
+/// Draws a frame of a replay over the background, as its curves and their copies under its
+/// symmetry seen with `scale` pixels per simulation unit.
+fn draw_frame(
+    canvas: &mut Canvas,
+    frame: &Frame,
+    header: &ReplayHeader,
+    config: &Config,
+    scale: f32,
+) {
+    canvas.fill(config.colors.background);
+    // There are no particles drawn over the lines, so they are drawn opaque.
+    let [r, g, b, _] = config.colors.line;
+    let transforms = header.symmetry_transforms();
+    for (i, next) in frame.edges() {
+        let start = frame.positions[i];
+        let end = match &header.torus {
+            Some(torus) => start + torus.offset(start, frame.positions[next]),
+            None => frame.positions[next],
+        };
+        for &transform in &transforms {
+            canvas.line(
+                transform * start,
+                transform * end,
+                1.5 / scale,
+                [r, g, b, 1.0],
+            );
+        }
+    }
+}
+
+/// Grows a curve in space for `args.steps` and writes it as `args.output`, or to
+/// `output/diff-lines-3d-<seed>-<steps>.obj` by default.
+fn run_space(args: &Args, config: &Config) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut curve = config.new_space_curve(seed);
+    let start = Instant::now();
+    for _ in 0..args.steps {
+        curve.update();
+    }
+    let elapsed = start.elapsed();
+
+    let path = args.output.clone().unwrap_or_else(|| {
+        PathBuf::from("output").join(format!("diff-lines-3d-{}-{}.obj", seed, curve.steps()))
+    });
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("png") => render_space_png(&curve, config).save(&path)?,
+        _ => fs::write(&path, curve.to_obj())?,
+    }
+
+    println!(
+        "Grew {} particles in space in {} steps ({:.2?}) with seed {}, saved {}",
+        curve.num_particles(),
+        curve.steps(),
+        elapsed,
+        seed,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Renders the curve at the configured export size, as seen by the default camera backed up to
+/// fit all of it.
+fn render_space_png(curve: &SpaceCurve, config: &Config) -> image::RgbaImage {
+    let (width, height) = (config.export.width, config.export.height);
+    let mut camera = OrbitCamera::default();
+    if let Some(bounds) = curve.bounding_box() {
+        camera.fit(bounds);
+    }
+    // The field of view spans 2 units vertically, which fills the shorter side of the image.
+    let scale = width.min(height) as f32 / 2.0;
+
+    // There are no particles drawn over the lines, so they are drawn opaque.
+    let [r, g, b, _] = config.colors.line;
+
+    let steps = curve.steps();
+    render_tiled_post(config, width, height, Vec2::ZERO, scale, steps, |canvas| {
+        canvas.fill(config.colors.background);
+        canvas.draw_space_curve(curve, &camera, [r, g, b, 1.0], 1.5 / scale);
+    })
+}
+
+/// Grows the curves for `args.steps` with every combination of the parameters of `sweep`,
+/// all from the same seed.
+pub fn run_sweep(args: &Args, sweep: &Sweep) -> Result<(), Box<dyn Error>> {
+    let mut base = Config::load_or_default(&args.config)?;
+    args.apply(&mut base);
+    let seed = args.seed.unwrap_or_else(rand::random);
+
+    // Every combination is checked before growing any, so a typo doesn't waste a long sweep.
+    let runs = sweep::combinations(&sweep.parameters)
+        .into_iter()
+        .map(|values| {
+            let config = sweep.parameters.iter().zip(&values).try_fold(
+                base.clone(),
+                |config, (parameter, &value)| {
+                    sweep::with_parameter(&config, &parameter.path, value)
+                },
+            )?;
+            Ok((values, config))
+        })
+        .collect::<Result<Vec<_>, sweep::SweepError>>()?;
+    fs::create_dir_all(&sweep.dir)?;
+
+    let grow = |(index, (values, config)): (usize, &(Vec<f64>, Config))| {
+        let start = Instant::now();
+        let mut ps = config
+            .new_particle_system(seed)
+            .map_err(|err| err.to_string())?;
+        for _ in 0..args.steps {
+            if ps.is_finished() {
+                break;
+            }
+            config
+                .simulation
+                .apply_schedules(&mut ps, &[])
+                .map_err(|err| err.to_string())?;
+            ps.update();
+            check_script(&mut ps).map_err(|err| err.to_string())?;
+        }
+        let seconds = start.elapsed().as_secs_f64();
+
+        let file = format!("sweep-{}.{}", index, sweep.format);
+        save(&ps, config, None, &sweep.dir.join(&file)).map_err(|err| err.to_string())?;
+        let metrics = Metrics::of(&ps);
+        println!(
+            "Grew {} particles with {:?}, saved {}",
+            metrics.particles, values, file
+        );
+        Ok::<_, String>(sweep::csv_row(index, values, &metrics, seconds, &file))
+    };
+    let rows: Vec<String> = if sweep.parallel {
+        runs.par_iter()
+            .enumerate()
+            .map(grow)
+            .collect::<Result<_, _>>()?
+    } else {
+        runs.iter()
+            .enumerate()
+            .map(grow)
+            .collect::<Result<_, _>>()?
+    };
+
+    let csv_path = sweep.dir.join("sweep.csv");
+    let mut csv = sweep::csv_header(&sweep.parameters);
+    for row in rows {
+        csv.push('\n');
+        csv.push_str(&row);
+    }
+    csv.push('\n');
+    fs::write(&csv_path, csv)?;
+    println!(
+        "Swept {} combinations with seed {}, saved {}",
+        runs.len(),
+        seed,
+        csv_path.display()
+    );
+    Ok(())
+}
+
+/// Writes `ps` to `path` in the format its extension stands for: `.json` for raw geometry,
+/// `.png` for an image of the `[export]` size, `.obj` or `.ply` for a solid, `.dxf` for cutting,
+/// `.gcode` for plotters, SVG otherwise.
+/// Solids stack `history` when given and `[mesh]` asks for it.
+fn save(
+    ps: &ParticleSystem,
+    config: &Config,
+    history: Option<&History>,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => fs::write(path, ps.to_json())?,
+        Some("png") => render_png(ps, config)?.save(path)?,
+        Some("obj") => fs::write(path, mesh_of(ps, config, history).to_obj())?,
+        Some("ply") => fs::write(path, mesh_of(ps, config, history).to_ply())?,
+        Some("dxf") => fs::write(path, ps.to_dxf(&config.dxf))?,
+        Some("gcode") => fs::write(path, ps.to_gcode(&config.gcode))?,
+        _ => fs::write(path, ps.to_svg())?,
+    }
+    Ok(())
+}
+
+/// The solid extruded from `ps`, or stacked from `history`, as set in `[mesh]`.
+pub fn mesh_of(ps: &ParticleSystem, config: &Config, history: Option<&History>) -> Mesh {
+    let mesh = &config.mesh;
+    match history {
+        Some(history) if mesh.stack_history => Mesh::stack(history, mesh.thickness, mesh.height),
+        _ => Mesh::extrude(ps, mesh.thickness, mesh.height),
+    }
+}
+
+/// Renders the whole growth at the configured export size, drawn like in the app, over the
+/// backdrop if `[backdrop]` exports it and with the `[post]` effects. On a torus, renders a
+/// seamless tile of it instead, as wide as the export, which leaves both out as they wouldn't
+/// tile.
+fn render_png(ps: &ParticleSystem, config: &Config) -> image::ImageResult<image::RgbaImage> {
+    let draw = |canvas: &mut Canvas| {
+        for obstacle in &ps.obstacles {
+            canvas.fill_polygon(&obstacle.polygon, [0.3, 0.3, 0.35, 1.0]);
+        }
+        canvas.draw_particle_system(
+            ps,
+            config.colors.line,
+            &config.render.thickness,
+            config.render.subdivisions,
+        );
+    };
+    let (width, height) = (config.export.width, config.export.height);
+    let background = config.colors.background;
+    if let Some(torus) = &ps.torus {
+        return Ok(raster::render_seamless(ps, torus, width, background, draw));
+    }
+
+    let backdrop = if config.backdrop.export {
+        config.backdrop.load()?
+    } else {
+        None
+    };
+    let bounds = ps.bounding_box().unwrap_or((Vec2::ZERO, Vec2::ZERO));
+    let (center, scale) = raster::fit_view(bounds, width, height, 20.0);
+    let frame = raster::view_bounds(center, scale, width, height);
+    let steps = ps.steps();
+    Ok(render_tiled_post(
+        config,
+        width,
+        height,
+        center,
+        scale,
+        steps,
+        |canvas| {
+            canvas.fill(background);
+            if let Some(backdrop) = &backdrop {
+                canvas.draw_backdrop(backdrop, frame);
+            }
+            draw(canvas);
+        },
+    ))
+}
+
+/// Like `raster::render_tiled()`, with the `[post]` effects applied to the image as of `frame`.
+pub fn render_tiled_post<F>(
+    config: &Config,
+    width: usize,
+    height: usize,
+    center: Vec2,
+    scale: f32,
+    frame: u64,
+    draw: F,
+) -> image::RgbaImage
+where
+    F: FnMut(&mut Canvas),
+{
+    let post = &config.post;
+    let size = Vec2::new(width as f32, height as f32);
+    raster::render_tiled_with(
+        width,
+        height,
+        center,
+        scale,
+        post.margin(),
+        draw,
+        |tile, corner| post.apply_to_part(tile, corner, size, frame),
+    )
+}
+
+/// Why the script of `ps` stopped running during the last step, if it did.
+#[cfg(feature = "scripting")]
+pub fn check_script(ps: &mut ParticleSystem) -> Result<(), Box<dyn Error>> {
+    match ps.take_script_error() {
+        Some(err) => Err(format!("The script stopped: {}", err).into()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn check_script(_ps: &mut ParticleSystem) -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
+
+/// Computes the forces of `ps` on the GPU from now on, if built with the `gpu` feature.
+#[cfg(feature = "gpu")]
+pub fn enable_gpu(ps: &mut ParticleSystem) -> Result<(), Box<dyn Error>> {
+    Ok(ps.enable_gpu()?)
+}
+
+#[cfg(not(feature = "gpu"))]
+pub fn enable_gpu(_ps: &mut ParticleSystem) -> Result<(), Box<dyn Error>> {
+    Err("--gpu needs the gpu feature".into())
+}
+
+/// Blooms on the GPU from now on, if built with the `gpu` feature.
+#[cfg(feature = "gpu")]
+pub fn enable_gpu_bloom(pass: &mut BloomPass) -> Result<(), Box<dyn Error>> {
+    Ok(pass.enable_gpu()?)
+}
+
+#[cfg(not(feature = "gpu"))]
+pub fn enable_gpu_bloom(_pass: &mut BloomPass) -> Result<(), Box<dyn Error>> {
+    Err("--gpu needs the gpu feature".into())
+}
+
+/// Compiles the fragment shader at `path` to draw with, if built with the `gpu` feature.
+#[cfg(feature = "gpu")]
+pub fn load_shader(pass: &mut ShaderPass, path: &Path) -> Result<(), Box<dyn Error>> {
+    Ok(pass.load(path)?)
+}
+
+#[cfg(not(feature = "gpu"))]
+pub fn load_shader(_pass: &mut ShaderPass, _path: &Path) -> Result<(), Box<dyn Error>> {
+    Err("shaders need the gpu feature".into())
+}
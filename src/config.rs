@@ -0,0 +1,1007 @@
+//! Settings loaded from a TOML file, shared by the interactive app and the headless binary.
+//!
+//! Every field is optional, anything left out keeps its default value:
+//!
+//! ```toml
+//! [window]
+//! width = 1280
+//! height = 720
+//!
+//! [simulation]
+//! max_edge_length = 4.0
+//! curvature_bias = 0.5
+//!
+//! [spawn]
+//! shape = "star"
+//! particles = 50
+//! # svg = "logo.svg"
+//! # text = { text = "grow", font = "fonts/NotoSans-Regular.ttf" }
+//!
+//! [colors]
+//! background = [0.0, 0.0, 0.0, 0.2]
+//! fill = { solid = [1.0, 0.5, 0.2, 1.0] }
+//! ```
+
+use crate::audio::{AudioLevels, AudioMapping};
+use crate::midi::{MidiControls, MidiMapping};
+#[cfg(feature = "scripting")]
+use crate::script::{Script, ScriptError};
+use crate::svg::{self, SvgError};
+use crate::sweep::{self, SweepError};
+use crate::text::{self, TextError};
+use crate::{
+    AdaptiveRadius, AreaPreservation, Backdrop, BlendMode, Bloom, Cap, Color, Coloring,
+    DirectionField, DirectionSource, DxfOptions, Expiry, Fill, Fit, Floor, GcodeOptions, GrayScott,
+    GrowthBudget, Hotspot, ImageMask, Integration, Jitter, Join, MappedParameter,
+    NeighborIndexKind, NoiseField, Obstacle, ParameterMap, ParticleSystem, PostEffects,
+    ReactionDiffusion, Schedule, Separation, SpaceCurve, SpawnShape, SplitPolicy, SplitThrottle,
+    Springs, Symmetry, Thickness, Torus,
+};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub simulation: SimulationConfig,
+    pub spawn: SpawnConfig,
+    pub colors: ColorConfig,
+    pub render: RenderConfig,
+    pub backdrop: BackdropConfig,
+    pub accumulation: AccumulationConfig,
+    pub post: PostEffects,
+    pub history: HistoryConfig,
+    pub undo: UndoConfig,
+    pub overlay: OverlayConfig,
+    pub metrics: MetricsConfig,
+    pub export: ExportConfig,
+    pub mesh: MeshConfig,
+    pub dxf: DxfOptions,
+    pub gcode: GcodeOptions,
+    pub gif: GifConfig,
+    pub video: VideoConfig,
+    pub audio: AudioConfig,
+    pub midi: MidiConfig,
+    pub osc: OscConfig,
+    pub ndi: NdiConfig,
+    pub spout: SpoutConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    /// The font of the statistics overlay. Without it, nannou's default font is looked for in an
+    /// `assets` directory next to the app, and the overlay stays blank if there is none.
+    pub font: Option<PathBuf>,
+}
+
+/// The tunable parameters of `ParticleSystem`, see its fields for what each one does.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    /// How many steps the app runs per second, whatever its frame rate.
+    pub steps_per_second: f32,
+    /// When above 0, the app runs this many steps every frame instead, however long it took.
+    pub steps_per_frame: usize,
+    /// Whether the app draws the particles part of the way between the last two steps, for
+    /// smooth motion when running fewer steps than frames.
+    pub interpolate: bool,
+    pub dt: f32,
+    pub sub_steps: usize,
+    pub integration: Integration,
+    pub particle_radius: f32,
+    pub influence_radius: f32,
+    pub attraction_weight: f32,
+    pub pressure_weight: f32,
+    pub inter_curve_pressure: f32,
+    pub bending_stiffness: f32,
+    pub springs: Option<Springs>,
+    pub adaptive_radius: AdaptiveRadius,
+    pub separation: Option<Separation>,
+    pub torus: Option<Torus>,
+    pub symmetry: Option<Symmetry>,
+    pub max_edge_length: f32,
+    pub min_edge_length: f32,
+    pub max_split_neighbors: usize,
+    pub curvature_bias: f32,
+    pub prevent_crossings: bool,
+    pub reconnect_distance: Option<f32>,
+    pub neighbor_index: NeighborIndexKind,
+    pub max_age: Option<f32>,
+    pub expiry: Expiry,
+    pub stiffening: f32,
+    pub coloring: Coloring,
+    pub color_smoothing: f32,
+    pub gravity: Vec2,
+    pub floor: Option<Floor>,
+    pub obstacles: Vec<Obstacle>,
+    pub hotspots: Vec<Hotspot>,
+    /// Loaded by `load_parameter_maps()`, since `apply()` doesn't read any files.
+    pub parameter_maps: Vec<ParameterMapConfig>,
+    /// Loaded by `load_direction_field()`, like `parameter_maps`.
+    pub direction_field: Option<DirectionFieldConfig>,
+    /// A Rhai script loaded by `load_script()`, with the `scripting` feature.
+    pub script: Option<PathBuf>,
+    pub area_preservation: Option<AreaPreservation>,
+    pub budget: GrowthBudget,
+    pub throttle: SplitThrottle,
+    pub split: SplitPolicy,
+    pub jitter: Jitter,
+    /// A Gray-Scott field coupled to the curves, kept running across reloads unless its cells
+    /// move.
+    pub reaction_diffusion: Option<GrayScott>,
+    /// Parameters changing over time, applied by `apply_schedules()`.
+    pub schedules: Vec<Schedule>,
+}
+
+/// A field combing the growth, see `DirectionField`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectionFieldConfig {
+    pub source: DirectionSourceConfig,
+    pub anisotropy: f32,
+}
+
+/// Where the directions come from, see `DirectionSource`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum DirectionSourceConfig {
+    /// In radians counterclockwise from the x axis.
+    Constant { angle: f32 },
+    Radial {
+        #[serde(default)]
+        center: Vec2,
+    },
+    /// The gradient of a grayscale image, placed like a parameter map.
+    Image {
+        image: PathBuf,
+        #[serde(default)]
+        center: Vec2,
+        #[serde(default = "default_map_width")]
+        width: f32,
+    },
+    Noise {
+        seed: u32,
+        /// Frequency of the noise, in cycles per simulation unit.
+        #[serde(default = "default_noise_scale")]
+        scale: f32,
+    },
+}
+
+/// A grayscale image scaling one of the simulation parameters, see `ParameterMap`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParameterMapConfig {
+    pub image: PathBuf,
+    pub parameter: MappedParameter,
+    #[serde(default)]
+    pub center: Vec2,
+    /// The width the image spans, in simulation units.
+    #[serde(default = "default_map_width")]
+    pub width: f32,
+    /// The scale on black pixels and outside of the image.
+    #[serde(default)]
+    pub min: f32,
+    /// The scale on white pixels.
+    #[serde(default = "default_map_max")]
+    pub max: f32,
+}
+
+fn default_map_width() -> f32 {
+    400.0
+}
+
+fn default_map_max() -> f32 {
+    1.0
+}
+
+fn default_noise_scale() -> f32 {
+    NoiseField::new(0).scale
+}
+
+/// The curve the simulation starts from.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpawnConfig {
+    pub shape: SpawnShape,
+    pub particles: usize,
+    pub radius: f32,
+    /// How many concentric copies of `shape` to start from, at evenly spaced radii up to
+    /// `radius`, see `ParticleSystem::spawn_nested()`.
+    pub nested: usize,
+    /// The radii of the concentric copies of `shape`, overriding `nested` when not empty.
+    pub radii: Vec<f32>,
+    /// An SVG file whose shapes are used instead of `shape`, scaled to fit in `radius`.
+    pub svg: Option<PathBuf>,
+    /// Text whose outlines are used instead of `shape`, scaled to fit in `radius`.
+    pub text: Option<TextConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextConfig {
+    pub text: String,
+    /// A TrueType or OpenType font file.
+    pub font: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    /// Drawn over the previous frame every frame, so a translucent background leaves trails.
+    pub background: Color,
+    /// The edges between particles.
+    pub line: Color,
+    pub fill: Fill,
+    /// The newest past outline, older ones fading out from it.
+    pub history: Color,
+}
+
+/// How the app and its recordings draw the curves.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    pub thickness: Thickness,
+    /// Draws each edge as this many pieces of a smooth spline through the particles instead of
+    /// a straight line, which only pays off for sparse curves. 1 keeps the edges straight.
+    pub subdivisions: usize,
+    /// How the edges meet where the curves turn.
+    pub join: Join,
+    /// How the ends of open curves are drawn.
+    pub cap: Cap,
+    /// A WGSL fragment shader drawing the edges and particles instead, see `ShaderPass`.
+    pub shader: Option<PathBuf>,
+}
+
+/// An image drawn behind the curves, see `Backdrop`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackdropConfig {
+    pub image: Option<PathBuf>,
+    pub opacity: f32,
+    /// How the image is laid into the window, or into the frames recorded from it.
+    pub fit: Fit,
+    /// Draws the image behind high resolution PNG exports too, fitted into the whole export.
+    pub export: bool,
+}
+
+/// The trails the app draws the curves into while toggled on with `B`, see `Accumulation`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccumulationConfig {
+    /// The fraction of the trails that fades out every step.
+    pub decay: f32,
+    /// Pixels of the accumulation per pixel of the window it starts from, above 1 for exports
+    /// sharper than the window.
+    pub resolution: f32,
+    /// How the curves combine with the trails.
+    pub blend: BlendMode,
+    /// A glow around the brightest trails, added to what is shown and saved but not to the
+    /// trails themselves.
+    pub bloom: Option<Bloom>,
+}
+
+/// The past outlines the app keeps to draw growth rings, see `History`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// An outline is kept every this many steps.
+    pub every: u64,
+    /// How many outlines are kept, dropping the oldest.
+    pub depth: usize,
+    /// In simulation units.
+    pub thickness: f32,
+}
+
+/// The earlier states the app keeps to step back to, see `Undo`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UndoConfig {
+    /// A state is kept every this many steps.
+    pub every: u64,
+    /// How many states are kept, dropping the oldest. 0 turns undoing off.
+    pub depth: usize,
+}
+
+/// The forces the app draws over every particle while the overlay is toggled on with `A`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverlayConfig {
+    /// Simulation units per unit of force.
+    pub scale: f32,
+    /// In pixels.
+    pub thickness: f32,
+    /// The color of each force, or none to leave it out.
+    pub pressure: Option<Color>,
+    pub attraction: Option<Color>,
+    /// The pressure and attraction together.
+    pub net: Option<Color>,
+}
+
+/// How often `--metrics` logs a row, see `MetricsLog`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub every: u64,
+}
+
+/// The size of high resolution PNG exports, which fit the whole growth whatever the window shows.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// How the curves are extruded into solids for OBJ and PLY exports, see `Mesh`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshConfig {
+    /// How wide the walls are, in simulation units.
+    pub thickness: f32,
+    /// How tall the walls are, or each layer when stacking the history.
+    pub height: f32,
+    /// Stacks the outlines kept every `[history]` steps instead of extruding the curves as they
+    /// are, the oldest at the bottom.
+    pub stack_history: bool,
+}
+
+/// How GIFs are captured in the app.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GifConfig {
+    /// The size of the GIF relative to the window.
+    pub scale: f32,
+    /// Only every this many steps is captured, to keep the file small.
+    pub every: u64,
+    /// How long each frame is shown, in milliseconds.
+    pub frame_ms: u32,
+}
+
+/// How the app listens to sound, with the `audio` feature, and which parameters it drives.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// Part of the name of the input device to capture, or the default one when unset.
+    pub device: Option<String>,
+    /// How many of the latest samples are measured each frame.
+    pub window: usize,
+    /// How much of the levels of the previous frame are kept, from 0 to just below 1.
+    pub smoothing: f32,
+    pub mappings: Vec<AudioMapping>,
+}
+
+/// Which MIDI controller the app reads, with the `midi` feature, and which parameters its
+/// controls drive.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiConfig {
+    /// Part of the name of the input port to connect to, or the first one when unset.
+    pub port: Option<String>,
+    pub mappings: Vec<MidiMapping>,
+}
+
+/// Where the app listens for OSC messages, with the `osc` feature.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OscConfig {
+    /// The UDP port to listen on, or none to not listen at all.
+    pub port: Option<u16>,
+    /// The address every message starts with.
+    pub prefix: String,
+}
+
+/// How the app announces itself when sending frames over NDI, with the `ndi` feature.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NdiConfig {
+    /// The name of the source shown to receivers, like OBS.
+    pub name: String,
+    /// The NDI runtime library, or where it is usually installed when unset.
+    pub library: Option<PathBuf>,
+    /// The frame rate receivers are told to expect.
+    pub fps: u32,
+}
+
+/// How the app announces itself when sharing frames through Spout, with the `spout` feature.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpoutConfig {
+    /// The name of the sender shown to receivers, like Resolume.
+    pub name: String,
+    /// SpoutLibrary.dll from the Spout SDK, looked for next to the app and on the `PATH` when
+    /// unset.
+    pub library: Option<PathBuf>,
+}
+
+/// The encoding settings passed to ffmpeg when recording video.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoConfig {
+    /// The ffmpeg executable, looked up in `PATH` unless it is a path.
+    pub ffmpeg: String,
+    /// An ffmpeg video encoder, like `libx264` or `libvpx-vp9`.
+    pub codec: String,
+    pub fps: u32,
+    /// Target bitrate in ffmpeg's notation, like `8M`.
+    pub bitrate: String,
+}
+
+/// Why the configured initial curves or images couldn't be loaded.
+#[derive(Debug)]
+pub enum SpawnError {
+    Svg(SvgError),
+    Text(TextError),
+    Image(image::ImageError),
+    #[cfg(feature = "scripting")]
+    Script(ScriptError),
+    /// A setting needs a feature the crate was built without.
+    Feature(&'static str),
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnError::Svg(err) => write!(f, "{}", err),
+            SpawnError::Text(err) => write!(f, "{}", err),
+            SpawnError::Image(err) => write!(f, "{}", err),
+            #[cfg(feature = "scripting")]
+            SpawnError::Script(err) => write!(f, "{}", err),
+            SpawnError::Feature(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+impl From<SvgError> for SpawnError {
+    fn from(err: SvgError) -> Self {
+        SpawnError::Svg(err)
+    }
+}
+
+impl From<TextError> for SpawnError {
+    fn from(err: TextError) -> Self {
+        SpawnError::Text(err)
+    }
+}
+
+impl From<image::ImageError> for SpawnError {
+    fn from(err: image::ImageError) -> Self {
+        SpawnError::Image(err)
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl From<ScriptError> for SpawnError {
+    fn from(err: ScriptError) -> Self {
+        SpawnError::Script(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            width: 800,
+            height: 600,
+            font: None,
+        }
+    }
+}
+
+impl Default for SimulationConfig {
+    /// Matches the defaults of `ParticleSystem`.
+    fn default() -> Self {
+        let ps = ParticleSystem::with_seed(0);
+        SimulationConfig {
+            steps_per_second: 60.0,
+            steps_per_frame: 0,
+            interpolate: true,
+            dt: ps.dt,
+            sub_steps: ps.sub_steps,
+            integration: ps.integration,
+            particle_radius: ps.particle_radius,
+            influence_radius: ps.influence_radius,
+            attraction_weight: ps.attraction_weight,
+            pressure_weight: ps.pressure_weight,
+            inter_curve_pressure: ps.inter_curve_pressure,
+            bending_stiffness: ps.bending_stiffness,
+            springs: ps.springs,
+            adaptive_radius: ps.adaptive_radius,
+            separation: ps.separation,
+            torus: ps.torus,
+            symmetry: ps.symmetry,
+            max_edge_length: ps.max_edge_length,
+            min_edge_length: ps.min_edge_length,
+            max_split_neighbors: ps.max_split_neighbors,
+            curvature_bias: ps.curvature_bias,
+            prevent_crossings: ps.prevent_crossings,
+            reconnect_distance: ps.reconnect_distance,
+            neighbor_index: ps.neighbor_index,
+            max_age: ps.max_age,
+            expiry: ps.expiry,
+            stiffening: ps.stiffening,
+            coloring: ps.coloring,
+            color_smoothing: ps.color_smoothing,
+            gravity: ps.gravity,
+            floor: ps.floor,
+            obstacles: ps.obstacles,
+            hotspots: ps.hotspots,
+            parameter_maps: Vec::new(),
+            direction_field: None,
+            script: None,
+            area_preservation: None,
+            budget: GrowthBudget::default(),
+            throttle: SplitThrottle::default(),
+            split: SplitPolicy::default(),
+            jitter: Jitter::default(),
+            reaction_diffusion: None,
+            schedules: Vec::new(),
+        }
+    }
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        SpawnConfig {
+            shape: SpawnShape::Ring,
+            particles: 100,
+            radius: 100.0,
+            nested: 1,
+            radii: Vec::new(),
+            svg: None,
+            text: None,
+        }
+    }
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        ColorConfig {
+            background: [0.01, 0.01, 0.01, 0.2],
+            line: [0.8, 0.8, 0.8, 0.1],
+            fill: Fill::Radial {
+                inner: [1.0, 0.8, 0.3, 1.0],
+                outer: [0.6, 0.1, 0.3, 1.0],
+            },
+            history: [0.8, 0.8, 0.8, 0.5],
+        }
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            thickness: Thickness::default(),
+            subdivisions: 1,
+            join: Join::default(),
+            cap: Cap::default(),
+            shader: None,
+        }
+    }
+}
+
+impl Default for BackdropConfig {
+    fn default() -> Self {
+        BackdropConfig {
+            image: None,
+            opacity: 1.0,
+            fit: Fit::Contain,
+            export: false,
+        }
+    }
+}
+
+impl Default for AccumulationConfig {
+    fn default() -> Self {
+        AccumulationConfig {
+            decay: 0.02,
+            resolution: 1.0,
+            blend: BlendMode::Add,
+            bloom: None,
+        }
+    }
+}
+
+impl BackdropConfig {
+    pub fn load(&self) -> image::ImageResult<Option<Backdrop>> {
+        self.image
+            .as_ref()
+            .map(|image| Backdrop::open(image, self.opacity, self.fit))
+            .transpose()
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            every: 20,
+            depth: 16,
+            thickness: 0.5,
+        }
+    }
+}
+
+impl Default for UndoConfig {
+    fn default() -> Self {
+        UndoConfig {
+            every: 25,
+            depth: 40,
+        }
+    }
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        OverlayConfig {
+            scale: 2.0,
+            thickness: 1.0,
+            pressure: Some([1.0, 0.3, 0.3, 1.0]),
+            attraction: Some([0.3, 1.0, 0.3, 1.0]),
+            net: Some([1.0, 1.0, 1.0, 1.0]),
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig { every: 10 }
+    }
+}
+
+impl Default for MeshConfig {
+    fn default() -> Self {
+        MeshConfig {
+            thickness: 1.0,
+            height: 10.0,
+            stack_history: false,
+        }
+    }
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            width: 4000,
+            height: 4000,
+        }
+    }
+}
+
+impl AudioConfig {
+    /// The values `mappings` give their parameters at `levels`.
+    pub fn overrides(&self, levels: &AudioLevels) -> Vec<(&str, f64)> {
+        self.mappings
+            .iter()
+            .map(|mapping| (mapping.parameter.as_str(), mapping.value(levels)))
+            .collect()
+    }
+}
+
+impl MidiConfig {
+    /// The values `mappings` give their parameters at `controls`, for the controls moved yet.
+    pub fn overrides(&self, controls: &MidiControls) -> Vec<(&str, f64)> {
+        self.mappings
+            .iter()
+            .filter_map(|mapping| Some((mapping.parameter.as_str(), mapping.value(controls)?)))
+            .collect()
+    }
+}
+
+impl Default for NdiConfig {
+    fn default() -> Self {
+        NdiConfig {
+            name: "differential-lines".to_string(),
+            library: None,
+            fps: 60,
+        }
+    }
+}
+
+impl Default for SpoutConfig {
+    fn default() -> Self {
+        SpoutConfig {
+            name: "differential-lines".to_string(),
+            library: None,
+        }
+    }
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        OscConfig {
+            port: None,
+            prefix: "/diffline".to_string(),
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            device: None,
+            window: 2048,
+            smoothing: 0.5,
+            mappings: Vec::new(),
+        }
+    }
+}
+
+impl Default for GifConfig {
+    fn default() -> Self {
+        GifConfig {
+            scale: 0.5,
+            every: 2,
+            frame_ms: 40,
+        }
+    }
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        VideoConfig {
+            ffmpeg: "ffmpeg".to_string(),
+            codec: "libx264".to_string(),
+            fps: 60,
+            bitrate: "8M".to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Like `load()`, but a missing file gives the default configuration instead of an error.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        match Config::load(path) {
+            Err(ConfigError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                Ok(Config::default())
+            }
+            result => result,
+        }
+    }
+
+    /// Creates a curve growing in space with the forces and growth settings of `[simulation]`,
+    /// starting as a ring of the `[spawn]` size whatever its shape.
+    pub fn new_space_curve(&self, seed: u64) -> SpaceCurve {
+        let mut curve = SpaceCurve::with_seed(seed);
+        let simulation = &self.simulation;
+        curve.influence_radius = simulation.influence_radius;
+        curve.attraction_weight = simulation.attraction_weight;
+        curve.pressure_weight = simulation.pressure_weight;
+        curve.max_edge_length = simulation.max_edge_length;
+        curve.min_edge_length = simulation.min_edge_length;
+        curve.max_split_neighbors = simulation.max_split_neighbors;
+        curve.curvature_bias = simulation.curvature_bias;
+        curve.spawn_ring(self.spawn.particles, self.spawn.radius);
+        curve
+    }
+
+    /// Creates a particle system with these settings and spawns its initial curves.
+    pub fn new_particle_system(&self, seed: u64) -> Result<ParticleSystem, SpawnError> {
+        let mut ps = ParticleSystem::with_seed(seed);
+        self.simulation.apply(&mut ps);
+        ps.parameter_maps = self.simulation.load_parameter_maps()?;
+        ps.direction_field = self.simulation.load_direction_field()?;
+        self.simulation.load_script(&mut ps)?;
+        self.spawn.spawn(&mut ps)?;
+        Ok(ps)
+    }
+}
+
+impl SimulationConfig {
+    /// Overwrites the parameters of a running system, leaving its particles untouched.
+    pub fn apply(&self, ps: &mut ParticleSystem) {
+        self.apply_parameters(ps);
+        ps.obstacles = self.obstacles.clone();
+    }
+
+    /// Like `apply()`, but leaves the obstacles alone, which the app lets users draw.
+    fn apply_parameters(&self, ps: &mut ParticleSystem) {
+        ps.dt = self.dt;
+        ps.sub_steps = self.sub_steps;
+        ps.integration = self.integration;
+        ps.particle_radius = self.particle_radius;
+        ps.influence_radius = self.influence_radius;
+        ps.attraction_weight = self.attraction_weight;
+        ps.pressure_weight = self.pressure_weight;
+        ps.inter_curve_pressure = self.inter_curve_pressure;
+        ps.bending_stiffness = self.bending_stiffness;
+        ps.springs = self.springs;
+        ps.adaptive_radius = self.adaptive_radius;
+        ps.separation = self.separation;
+        ps.torus = self.torus;
+        ps.symmetry = self.symmetry;
+        ps.max_edge_length = self.max_edge_length;
+        ps.min_edge_length = self.min_edge_length;
+        ps.max_split_neighbors = self.max_split_neighbors;
+        ps.curvature_bias = self.curvature_bias;
+        ps.prevent_crossings = self.prevent_crossings;
+        ps.reconnect_distance = self.reconnect_distance;
+        ps.neighbor_index = self.neighbor_index;
+        ps.max_age = self.max_age;
+        ps.expiry = self.expiry;
+        ps.stiffening = self.stiffening;
+        ps.coloring = self.coloring;
+        ps.color_smoothing = self.color_smoothing;
+        ps.gravity = self.gravity;
+        ps.floor = self.floor;
+        ps.hotspots = self.hotspots.clone();
+        ps.area_preservation = self.area_preservation;
+        ps.budget = self.budget;
+        ps.throttle = self.throttle;
+        ps.split = self.split.clone();
+        ps.jitter = self.jitter;
+        let seed = ps.seed();
+        match (&mut ps.reaction_diffusion, self.reaction_diffusion) {
+            (Some(rd), Some(settings)) => rd.configure(settings),
+            (rd, settings) => {
+                *rd = settings.map(|settings| ReactionDiffusion::new(settings, seed));
+            }
+        }
+    }
+
+    /// These parameters with the values `schedules` give them at `time`, then the values of
+    /// `overrides` at their dotted paths.
+    pub fn scheduled(
+        &self,
+        time: f32,
+        overrides: &[(&str, f64)],
+    ) -> Result<SimulationConfig, SweepError> {
+        let mut root = toml::Value::try_from(self)?;
+        for schedule in &self.schedules {
+            if let Some(value) = schedule.value_at(time as f64) {
+                sweep::set_parameter(&mut root, &schedule.parameter, value)?;
+            }
+        }
+        for &(path, value) in overrides {
+            sweep::set_parameter(&mut root, path, value)?;
+        }
+        Ok(root.try_into()?)
+    }
+
+    /// Sets the parameters of `ps` to the values `schedules` give them at its current time, and
+    /// those of `overrides`, like the ones driven by `AudioConfig`. Call it before every step.
+    /// Does nothing without any schedules or overrides.
+    pub fn apply_schedules(
+        &self,
+        ps: &mut ParticleSystem,
+        overrides: &[(&str, f64)],
+    ) -> Result<(), SweepError> {
+        if self.schedules.is_empty() && overrides.is_empty() {
+            return Ok(());
+        }
+        self.scheduled(ps.time(), overrides)?.apply_parameters(ps);
+        Ok(())
+    }
+
+    /// Replaces the script of `ps` with the one set in `script`, if any.
+    #[cfg(feature = "scripting")]
+    pub fn load_script(&self, ps: &mut ParticleSystem) -> Result<(), SpawnError> {
+        ps.script = self.script.as_ref().map(Script::load).transpose()?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    pub fn load_script(&self, _ps: &mut ParticleSystem) -> Result<(), SpawnError> {
+        match self.script {
+            Some(_) => Err(SpawnError::Feature("scripts need the scripting feature")),
+            None => Ok(()),
+        }
+    }
+
+    pub fn load_parameter_maps(&self) -> image::ImageResult<Vec<ParameterMap>> {
+        self.parameter_maps
+            .iter()
+            .map(|map| {
+                Ok(
+                    ParameterMap::open(&map.image, map.center, map.width, map.parameter)?
+                        .with_range(map.min, map.max),
+                )
+            })
+            .collect()
+    }
+
+    pub fn load_direction_field(&self) -> image::ImageResult<Option<DirectionField>> {
+        let config = match &self.direction_field {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+
+        let source = match &config.source {
+            DirectionSourceConfig::Constant { angle } => {
+                DirectionSource::Constant { angle: *angle }
+            }
+            DirectionSourceConfig::Radial { center } => DirectionSource::Radial { center: *center },
+            DirectionSourceConfig::Image {
+                image,
+                center,
+                width,
+            } => DirectionSource::ImageGradient(ImageMask::open(image, *center, *width)?),
+            DirectionSourceConfig::Noise { seed, scale } => {
+                let mut noise = NoiseField::new(*seed);
+                noise.scale = *scale;
+                DirectionSource::Noise(Box::new(noise))
+            }
+        };
+        Ok(Some(DirectionField::new(source, config.anisotropy)))
+    }
+}
+
+impl SpawnConfig {
+    /// Spawns the curves from `svg` if set, then `text`, falling back to `shape`.
+    pub fn spawn(&self, ps: &mut ParticleSystem) -> Result<(), SpawnError> {
+        let spacing = (ps.min_edge_length + ps.max_edge_length) / 2.0;
+        match (&self.svg, &self.text) {
+            (Some(path), _) => {
+                ps.spawn_polylines(&svg::open(path, self.radius, spacing)?);
+            }
+            (None, Some(text)) => {
+                let outlines = text::open(&text.font, &text.text, self.radius, spacing)?;
+                ps.spawn_polylines(&outlines);
+            }
+            (None, None) if self.nested > 1 || !self.radii.is_empty() => {
+                ps.spawn_nested(
+                    &self.shape,
+                    self.particles,
+                    self.radius,
+                    &self.nested_radii(),
+                );
+            }
+            (None, None) => {
+                ps.spawn(&self.shape, self.particles, self.radius);
+            }
+        }
+        Ok(())
+    }
+
+    /// The radii of the concentric copies of `shape`, from the outermost in.
+    pub fn nested_radii(&self) -> Vec<f32> {
+        if !self.radii.is_empty() {
+            return self.radii.clone();
+        }
+        let count = self.nested.max(1);
+        (0..count)
+            .map(|k| self.radius * (count - k) as f32 / count as f32)
+            .collect()
+    }
+}
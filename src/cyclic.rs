@@ -0,0 +1,85 @@
+//! Arithmetic on indices into closed curves, where stepping past either end wraps around to the
+//! other.
+
+/// Wraps `index` into `0..len`, for offsets of any size in either direction. `len` must not be 0.
+pub fn wrap(index: isize, len: usize) -> usize {
+    debug_assert!(len > 0, "wrapping into an empty range");
+    index.rem_euclid(len as isize) as usize
+}
+
+/// The index `delta` steps after `index`, or before it when negative, around a closed curve of
+/// `len` points.
+pub fn offset(index: usize, delta: isize, len: usize) -> usize {
+    wrap(index as isize + delta, len)
+}
+
+/// The fewest steps between `a` and `b` around a closed curve of `len` points, in either
+/// direction.
+pub fn distance(a: usize, b: usize, len: usize) -> usize {
+    let forward = offset(b, -(a as isize), len);
+    forward.min(len - forward)
+}
+
+/// The indices within `k` steps of `index` around a closed curve of `len` points, in order from
+/// `k` steps before it to `k` steps after. Each index comes up only once, even when the curve is
+/// too short to hold `2 * k + 1` points.
+pub fn window(index: usize, k: usize, len: usize) -> impl Iterator<Item = usize> {
+    let before = k.min(len.saturating_sub(1) / 2);
+    let after = k.min(len.saturating_sub(1) - before);
+    (-(before as isize)..=after as isize)
+        .take(len)
+        .map(move |delta| offset(index, delta, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_keeps_indices_in_range() {
+        assert_eq!(wrap(0, 5), 0);
+        assert_eq!(wrap(4, 5), 4);
+        assert_eq!(wrap(5, 5), 0);
+        assert_eq!(wrap(12, 5), 2);
+    }
+
+    #[test]
+    fn wrap_counts_negative_indices_back_from_the_end() {
+        assert_eq!(wrap(-1, 5), 4);
+        assert_eq!(wrap(-2, 5), 3);
+        assert_eq!(wrap(-5, 5), 0);
+        assert_eq!(wrap(-7, 5), 3);
+        assert_eq!(wrap(-1, 1), 0);
+    }
+
+    #[test]
+    fn offset_steps_both_ways() {
+        assert_eq!(offset(0, -1, 4), 3);
+        assert_eq!(offset(3, 1, 4), 0);
+        assert_eq!(offset(1, -6, 4), 3);
+        assert_eq!(offset(2, 9, 4), 3);
+    }
+
+    #[test]
+    fn distance_takes_the_shorter_way_around() {
+        assert_eq!(distance(0, 0, 6), 0);
+        assert_eq!(distance(1, 4, 6), 3);
+        assert_eq!(distance(0, 5, 6), 1);
+        assert_eq!(distance(5, 0, 6), 1);
+        assert_eq!(distance(1, 3, 10), distance(3, 1, 10));
+    }
+
+    #[test]
+    fn window_wraps_around_the_ends() {
+        assert_eq!(window(0, 2, 10).collect::<Vec<_>>(), [8, 9, 0, 1, 2]);
+        assert_eq!(window(9, 1, 10).collect::<Vec<_>>(), [8, 9, 0]);
+    }
+
+    #[test]
+    fn window_never_repeats_an_index() {
+        assert_eq!(window(1, 5, 4).collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(window(0, 3, 5).collect::<Vec<_>>(), [3, 4, 0, 1, 2]);
+        assert_eq!(window(0, 1, 1).collect::<Vec<_>>(), [0]);
+        assert_eq!(window(0, 1, 0).count(), 0);
+    }
+}
@@ -0,0 +1,98 @@
+//! DXF export of the grown curves, for laser cutters and the CAD tools fabricators drive them
+//! with.
+
+use crate::{ParticleSystem, Polyline};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// The drawing units DXF coordinates are written in, which have to match those the cutter's
+/// software reads them in, since R12 files can't declare them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DxfUnits {
+    /// Left in millimeters, for software that reads the coordinates without units.
+    Unitless,
+    Inches,
+    #[default]
+    Millimeters,
+    Centimeters,
+    Meters,
+}
+
+impl DxfUnits {
+    /// How many millimeters one of these units is.
+    fn millimeters(self) -> f32 {
+        match self {
+            DxfUnits::Unitless | DxfUnits::Millimeters => 1.0,
+            DxfUnits::Inches => 25.4,
+            DxfUnits::Centimeters => 10.0,
+            DxfUnits::Meters => 1000.0,
+        }
+    }
+}
+
+/// How the curves are sized in DXF exports.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DxfOptions {
+    pub units: DxfUnits,
+    /// How many millimeters a simulation unit becomes, whichever `units` they are written in.
+    pub scale: f32,
+}
+
+impl Default for DxfOptions {
+    fn default() -> Self {
+        DxfOptions {
+            units: DxfUnits::Millimeters,
+            scale: 0.1,
+        }
+    }
+}
+
+impl ParticleSystem {
+    /// Serializes every curve as a polyline of an R12 DXF file, closed ones flagged as such. The
+    /// drawing is scaled as set by `options` and moved so that its lower left corner is at the
+    /// origin, where cutters start from.
+    pub fn to_dxf(&self, options: &DxfOptions) -> String {
+        let origin = self.bounding_box().map_or(Vec2::ZERO, |(min, _)| min);
+        let scale = options.scale / options.units.millimeters();
+        let mut dxf = String::new();
+
+        // Group codes and values alternate on their own lines.
+        let mut pair = |code: u16, value: &dyn std::fmt::Display| {
+            writeln!(dxf, "{}\n{}", code, value).unwrap();
+        };
+        pair(0, &"SECTION");
+        pair(2, &"HEADER");
+        pair(9, &"$ACADVER");
+        pair(1, &"AC1009");
+        pair(0, &"ENDSEC");
+
+        pair(0, &"SECTION");
+        pair(2, &"ENTITIES");
+        for Polyline { points, closed } in self.polylines() {
+            pair(0, &"POLYLINE");
+            pair(8, &"curves");
+            // Vertices follow as their own entities, so the polyline's own point is unused.
+            pair(66, &1);
+            pair(10, &0.0);
+            pair(20, &0.0);
+            pair(30, &0.0);
+            pair(70, &u8::from(closed));
+            for p in points {
+                let p = (p - origin) * scale;
+                pair(0, &"VERTEX");
+                pair(8, &"curves");
+                pair(10, &p.x);
+                pair(20, &p.y);
+            }
+            pair(0, &"SEQEND");
+            pair(8, &"curves");
+        }
+        pair(0, &"ENDSEC");
+        pair(0, &"EOF");
+
+        dxf
+    }
+}
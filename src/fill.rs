@@ -0,0 +1,61 @@
+//! Filled rendering of closed curves.
+
+use crate::Color;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// How the inside of closed curves is colored.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fill {
+    Solid(Color),
+    /// Blends from `bottom` to `top` across the height of the curves' bounding box.
+    Vertical {
+        bottom: Color,
+        top: Color,
+    },
+    /// Blends from `inner` at the center of the curves' bounding box to `outer` at its corners.
+    Radial {
+        inner: Color,
+        outer: Color,
+    },
+}
+
+impl Fill {
+    /// The fill color at `p`, for curves spanning the `(min, max)` bounding box.
+    pub fn color_at(&self, p: Vec2, (min, max): (Vec2, Vec2)) -> Color {
+        match *self {
+            Fill::Solid(color) => color,
+            Fill::Vertical { bottom, top } => {
+                let t = (p.y - min.y) / (max.y - min.y).max(f32::EPSILON);
+                mix(bottom, top, t)
+            }
+            Fill::Radial { inner, outer } => {
+                let center = (min + max) / 2.0;
+                let t = (p - center).length() / (max - center).length().max(f32::EPSILON);
+                mix(inner, outer, t)
+            }
+        }
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let mut color = a;
+    for c in 0..4 {
+        color[c] += (b[c] - a[c]) * t;
+    }
+    color
+}
+
+/// Splits the polygon outlined by `points` into triangles by ear clipping, returned as triples of
+/// indices into `points`. Works for concave polygons, unlike a triangle fan.
+pub fn triangulate(points: &[Vec2]) -> Vec<[usize; 3]> {
+    let coords: Vec<f32> = points.iter().flat_map(|p| [p.x, p.y]).collect();
+    let indices = earcutr::earcut(&coords, &[], 2).unwrap_or_default();
+
+    indices
+        .chunks_exact(3)
+        .map(|tri| [tri[0], tri[1], tri[2]])
+        .collect()
+}
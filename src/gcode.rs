@@ -0,0 +1,102 @@
+//! G-code export of the grown curves, for pen plotters and CNC machines driven directly by it
+//! rather than through SVG.
+
+use crate::{ParticleSystem, Polyline};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// How the toolpath is sized and how the machine raises and lowers its pen or tool.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GcodeOptions {
+    /// How many millimeters a simulation unit becomes.
+    pub scale: f32,
+    /// How fast the curves are drawn, in millimeters per minute.
+    pub feed_rate: f32,
+    /// The height the tool moves between curves at, when `pen_up` is empty.
+    pub travel_height: f32,
+    /// The height the tool draws at, when `pen_down` is empty.
+    pub draw_height: f32,
+    /// The commands raising the pen, e.g. `M3 S30` for servo plotters. Moves to `travel_height`
+    /// when empty.
+    pub pen_up: String,
+    /// The commands lowering the pen. Moves to `draw_height` when empty.
+    pub pen_down: String,
+}
+
+impl Default for GcodeOptions {
+    fn default() -> Self {
+        GcodeOptions {
+            scale: 0.1,
+            feed_rate: 1500.0,
+            travel_height: 5.0,
+            draw_height: 0.0,
+            pen_up: String::new(),
+            pen_down: String::new(),
+        }
+    }
+}
+
+impl ParticleSystem {
+    /// Serializes every curve as a toolpath in millimeters, lowering the pen at its start and
+    /// raising it once back around or at its end. The drawing is moved so that its lower left
+    /// corner is at the origin, and the curves are visited nearest first to keep travel short.
+    pub fn to_gcode(&self, options: &GcodeOptions) -> String {
+        let origin = self.bounding_box().map_or(Vec2::ZERO, |(min, _)| min);
+        let to_machine = |p: Vec2| (p - origin) * options.scale;
+
+        let mut gcode = String::from("; Differential line growth\nG21 ; millimeters\nG90\n");
+        let pen_up = |gcode: &mut String| match options.pen_up.as_str() {
+            "" => writeln!(gcode, "G0 Z{:.3}", options.travel_height).unwrap(),
+            commands => writeln!(gcode, "{}", commands).unwrap(),
+        };
+        let pen_down = |gcode: &mut String| match options.pen_down.as_str() {
+            "" => writeln!(
+                gcode,
+                "G1 Z{:.3} F{:.0}",
+                options.draw_height, options.feed_rate
+            )
+            .unwrap(),
+            commands => writeln!(gcode, "{}", commands).unwrap(),
+        };
+        pen_up(&mut gcode);
+
+        let mut polylines = self.polylines();
+        let mut position = Vec2::ZERO;
+        while !polylines.is_empty() {
+            let nearest = (0..polylines.len())
+                .min_by(|&a, &b| {
+                    let distance =
+                        |i: usize| (to_machine(polylines[i].points[0]) - position).length();
+                    distance(a).total_cmp(&distance(b))
+                })
+                .unwrap();
+            let Polyline { points, closed } = polylines.swap_remove(nearest);
+
+            let start = to_machine(points[0]);
+            writeln!(gcode, "G0 X{:.3} Y{:.3}", start.x, start.y).unwrap();
+            position = start;
+            pen_down(&mut gcode);
+            let back_around = if closed { Some(points[0]) } else { None };
+            for (i, p) in points[1..].iter().copied().chain(back_around).enumerate() {
+                let p = to_machine(p);
+                if i == 0 {
+                    writeln!(
+                        gcode,
+                        "G1 X{:.3} Y{:.3} F{:.0}",
+                        p.x, p.y, options.feed_rate
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(gcode, "G1 X{:.3} Y{:.3}", p.x, p.y).unwrap();
+                }
+                position = p;
+            }
+            pen_up(&mut gcode);
+        }
+
+        gcode.push_str("G0 X0 Y0\nM2\n");
+        gcode
+    }
+}
@@ -0,0 +1,54 @@
+//! Animated GIF export, for sharing a growth without going through ffmpeg.
+
+use crate::Canvas;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageResult};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Trades color quality for encoding speed, from 1 to 30. GIFs only have 256 colors per frame, so
+/// a fast palette search barely shows.
+const QUANTIZATION_SPEED: i32 = 10;
+
+/// Collects canvas snapshots in memory and encodes them into a looping GIF once done.
+pub struct GifRecorder {
+    frames: Vec<Frame>,
+    delay: Delay,
+}
+
+impl GifRecorder {
+    /// Creates a recorder showing each frame for `frame_ms` milliseconds.
+    pub fn new(frame_ms: u32) -> Self {
+        GifRecorder {
+            frames: Vec::new(),
+            delay: Delay::from_numer_denom_ms(frame_ms, 1),
+        }
+    }
+
+    pub fn add_frame(&mut self, canvas: &Canvas) {
+        self.frames
+            .push(Frame::from_parts(canvas.to_image(), 0, 0, self.delay));
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Encodes the frames collected so far to `path`, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = GifEncoder::new_with_speed(file, QUANTIZATION_SPEED);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(self.frames.iter().cloned())
+    }
+}
@@ -0,0 +1,395 @@
+//! Computes the forces between particles in wgpu compute shaders.
+//!
+//! Finding the neighbors of every particle dominates the cost of a step, and for a hundred
+//! thousand particles or more the GPU does it much faster, even with the round trip of uploading
+//! positions and reading the forces back every step. Everything else stays on the CPU.
+
+use crate::Links;
+use bytemuck::{Pod, Zeroable};
+use glam::{vec2, Vec2};
+use std::fmt;
+use std::sync::mpsc;
+
+/// Cells in the grid the shaders bin particles into. Beyond this, cells grow larger than the
+/// influence radius so far flung particles can't blow up the memory used.
+const MAX_CELLS: usize = 1 << 22;
+/// Matches `@workgroup_size` of the per-particle shaders.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Why the GPU couldn't be used.
+#[derive(Debug)]
+pub enum GpuError {
+    /// No graphics adapter was found.
+    NoAdapter,
+    Device(wgpu::RequestDeviceError),
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter => write!(f, "no GPU adapter found"),
+            GpuError::Device(err) => write!(f, "{}", err),
+            GpuError::Readback(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+impl From<wgpu::RequestDeviceError> for GpuError {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        GpuError::Device(err)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for GpuError {
+    fn from(err: wgpu::BufferAsyncError) -> Self {
+        GpuError::Readback(err)
+    }
+}
+
+/// The forces on one particle computed by the GPU.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+pub(crate) struct GpuForces {
+    pub attraction: [f32; 2],
+    pub pressure: [f32; 2],
+    pub num_neighbors: u32,
+    _padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Params {
+    num_particles: u32,
+    grid_width: u32,
+    grid_height: u32,
+    num_cells: u32,
+    origin: [f32; 2],
+    cell_size: f32,
+    influence_radius: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Link {
+    prev: i32,
+    next: i32,
+    alive: u32,
+    _padding: u32,
+}
+
+/// A GPU device with the force shaders loaded.
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    count: wgpu::ComputePipeline,
+    scan: wgpu::ComputePipeline,
+    scatter: wgpu::ComputePipeline,
+    forces: wgpu::ComputePipeline,
+    buffers: Option<Buffers>,
+}
+
+/// Buffers sized for up to `particles` particles and `cells` grid cells, grown as needed.
+struct Buffers {
+    particles: usize,
+    cells: usize,
+    params: wgpu::Buffer,
+    positions: wgpu::Buffer,
+    links: wgpu::Buffer,
+    cell_cursors: wgpu::Buffer,
+    outputs: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Gpu {
+    /// Picks the default adapter and compiles the shaders, blocking until done.
+    pub fn new() -> Result<Self, GpuError> {
+        let (device, queue) = open_device(wgpu::Limits {
+            max_storage_buffers_per_shader_stage: 6,
+            ..wgpu::Limits::downlevel_defaults()
+        })?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("forces"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+                storage(4, false),
+                storage(5, false),
+                storage(6, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point,
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Ok(Gpu {
+            count: pipeline("count"),
+            scan: pipeline("scan"),
+            scatter: pipeline("scatter"),
+            forces: pipeline("forces"),
+            device,
+            queue,
+            layout,
+            buffers: None,
+        })
+    }
+
+    /// Computes the attraction, pressure and number of neighbors of every particle, like
+    /// `ParticleSystem::compute_forces()` does. Dead particles get no forces.
+    pub(crate) fn compute(
+        &mut self,
+        positions: &[Vec2],
+        links: &Links,
+        alive: &[bool],
+        influence_radius: f32,
+    ) -> Result<Vec<GpuForces>, GpuError> {
+        let num_particles = positions.len();
+        if num_particles == 0 {
+            return Ok(Vec::new());
+        }
+
+        let (min, max) = positions
+            .iter()
+            .zip(alive)
+            .filter(|&(_, &alive)| alive)
+            .fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), (&p, _)| (min.min(p), max.max(p)),
+            );
+        let (min, max) = if min.x <= max.x {
+            (min, max)
+        } else {
+            (Vec2::ZERO, Vec2::ZERO)
+        };
+        let extent = max - min;
+        let cell_size = influence_radius
+            .max((extent.x * extent.y / MAX_CELLS as f32).sqrt())
+            .max(f32::EPSILON);
+        let grid_width = (extent.x / cell_size) as u32 + 1;
+        let grid_height = (extent.y / cell_size) as u32 + 1;
+        let num_cells = (grid_width * grid_height) as usize;
+
+        let params = Params {
+            num_particles: num_particles as u32,
+            grid_width,
+            grid_height,
+            num_cells: num_cells as u32,
+            origin: min.into(),
+            cell_size,
+            influence_radius,
+        };
+        let links: Vec<Link> = links
+            .iter()
+            .zip(alive)
+            .map(|(link, &alive)| Link {
+                prev: link.prev.map_or(-1, |prev| prev as i32),
+                next: link.next.map_or(-1, |next| next as i32),
+                alive: alive as u32,
+                _padding: 0,
+            })
+            .collect();
+        let positions: Vec<[f32; 2]> = positions.iter().map(|&p| p.into()).collect();
+
+        self.reserve(num_particles, num_cells);
+        let buffers = self.buffers.as_ref().unwrap();
+        self.queue
+            .write_buffer(&buffers.params, 0, bytemuck::bytes_of(&params));
+        self.queue
+            .write_buffer(&buffers.positions, 0, bytemuck::cast_slice(&positions));
+        self.queue
+            .write_buffer(&buffers.links, 0, bytemuck::cast_slice(&links));
+
+        let output_size = (num_particles * std::mem::size_of::<GpuForces>()) as u64;
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.clear_buffer(&buffers.cell_cursors, 0, None);
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_bind_group(0, &buffers.bind_group, &[]);
+            let particle_groups = (num_particles as u32).div_ceil(WORKGROUP_SIZE);
+            for (pipeline, groups) in [
+                (&self.count, particle_groups),
+                (&self.scan, 1),
+                (&self.scatter, particle_groups),
+                (&self.forces, particle_groups),
+            ] {
+                pass.set_pipeline(pipeline);
+                pass.dispatch_workgroups(groups, 1, 1);
+            }
+        }
+        encoder.copy_buffer_to_buffer(&buffers.outputs, 0, &buffers.readback, 0, output_size);
+        self.queue.submit(Some(encoder.finish()));
+        read_back(&self.device, &buffers.readback, output_size)
+    }
+
+    /// Makes sure the buffers are large enough for the given sizes, reallocating them if needed.
+    fn reserve(&mut self, particles: usize, cells: usize) {
+        let fits = self
+            .buffers
+            .as_ref()
+            .is_some_and(|b| b.particles >= particles && b.cells >= cells);
+        if !fits {
+            let particles = particles.next_power_of_two();
+            let cells = cells.next_power_of_two();
+            self.buffers = Some(self.create_buffers(particles, cells));
+        }
+    }
+
+    fn create_buffers(&self, particles: usize, cells: usize) -> Buffers {
+        let buffer = |label, size: usize, usage| {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: size as u64,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let storage = wgpu::BufferUsages::STORAGE;
+        let copy_dst = wgpu::BufferUsages::COPY_DST;
+        let output_size = particles * std::mem::size_of::<GpuForces>();
+
+        let params = buffer(
+            "params",
+            std::mem::size_of::<Params>(),
+            wgpu::BufferUsages::UNIFORM | copy_dst,
+        );
+        let positions = buffer("positions", particles * 8, storage | copy_dst);
+        let links = buffer("links", particles * 16, storage | copy_dst);
+        let cell_cursors = buffer("cell_cursors", cells * 4, storage | copy_dst);
+        // Only used by the shaders, the bind group keeps these alive.
+        let cell_starts = buffer("cell_starts", cells * 4, storage);
+        let sorted = buffer("sorted", particles * 4, storage);
+        let outputs = buffer(
+            "outputs",
+            output_size,
+            storage | wgpu::BufferUsages::COPY_SRC,
+        );
+        let readback = buffer(
+            "readback",
+            output_size,
+            wgpu::BufferUsages::MAP_READ | copy_dst,
+        );
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.layout,
+            entries: &[
+                &params,
+                &positions,
+                &links,
+                &cell_cursors,
+                &cell_starts,
+                &sorted,
+                &outputs,
+            ]
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect::<Vec<_>>(),
+        });
+
+        Buffers {
+            particles,
+            cells,
+            params,
+            positions,
+            links,
+            cell_cursors,
+            outputs,
+            readback,
+            bind_group,
+        }
+    }
+}
+
+/// Opens a device on the default adapter with `limits`, blocking until done.
+pub(crate) fn open_device(limits: wgpu::Limits) -> Result<(wgpu::Device, wgpu::Queue), GpuError> {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))
+        .ok_or(GpuError::NoAdapter)?;
+    Ok(pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: limits,
+            memory_hints: Default::default(),
+        },
+        None,
+    ))?)
+}
+
+/// Waits for the commands submitted so far and copies the first `size` bytes of `readback` out.
+pub(crate) fn read_back<T: Pod>(
+    device: &wgpu::Device,
+    readback: &wgpu::Buffer,
+    size: u64,
+) -> Result<Vec<T>, GpuError> {
+    let slice = readback.slice(..size);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("the map callback runs while polling")?;
+
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    readback.unmap();
+    Ok(data)
+}
+
+impl GpuForces {
+    pub fn attraction(&self) -> Vec2 {
+        vec2(self.attraction[0], self.attraction[1])
+    }
+
+    pub fn pressure(&self) -> Vec2 {
+        vec2(self.pressure[0], self.pressure[1])
+    }
+}
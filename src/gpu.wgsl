@@ -0,0 +1,157 @@
+// Neighbor search and the forces between particles, see gpu.rs.
+//
+// Particles are binned into a grid by counting them per cell, scanning the counts into the start
+// of each cell's range and scattering the particle indices into those ranges. Each particle then
+// only has to look at the 3x3 cells around it.
+
+struct Params {
+    num_particles: u32,
+    grid_width: u32,
+    grid_height: u32,
+    num_cells: u32,
+    origin: vec2<f32>,
+    cell_size: f32,
+    influence_radius: f32,
+}
+
+// The previous and next particle along the curve, or -1, and whether the particle is alive.
+struct Link {
+    prev: i32,
+    next: i32,
+    alive: u32,
+    _padding: u32,
+}
+
+struct Output {
+    attraction: vec2<f32>,
+    pressure: vec2<f32>,
+    num_neighbors: u32,
+    _padding: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> positions: array<vec2<f32>>;
+@group(0) @binding(2) var<storage, read> links: array<Link>;
+// The number of particles in each cell while counting, then where the next one goes while
+// scattering, which leaves the end of each cell's range.
+@group(0) @binding(3) var<storage, read_write> cell_cursors: array<atomic<u32>>;
+@group(0) @binding(4) var<storage, read_write> cell_starts: array<u32>;
+// Particle indices sorted by cell.
+@group(0) @binding(5) var<storage, read_write> sorted: array<u32>;
+@group(0) @binding(6) var<storage, read_write> outputs: array<Output>;
+
+const SCAN_THREADS: u32 = 256u;
+var<workgroup> chunk_sums: array<u32, SCAN_THREADS>;
+
+fn cell_coords(position: vec2<f32>) -> vec2<i32> {
+    let cell = vec2<i32>(floor((position - params.origin) / params.cell_size));
+    let size = vec2<i32>(i32(params.grid_width), i32(params.grid_height));
+    return clamp(cell, vec2<i32>(0), size - 1);
+}
+
+fn cell_index(cell: vec2<i32>) -> u32 {
+    return u32(cell.y) * params.grid_width + u32(cell.x);
+}
+
+@compute @workgroup_size(64)
+fn count(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.num_particles || links[i].alive == 0u {
+        return;
+    }
+    atomicAdd(&cell_cursors[cell_index(cell_coords(positions[i]))], 1u);
+}
+
+// Turns the counts into exclusive prefix sums. Each thread sums up a chunk of the cells, the
+// chunk totals are scanned by the first thread, and then each thread fills in its chunk.
+@compute @workgroup_size(256)
+fn scan(@builtin(local_invocation_index) thread: u32) {
+    let chunk = (params.num_cells + SCAN_THREADS - 1u) / SCAN_THREADS;
+    let begin = min(thread * chunk, params.num_cells);
+    let end = min(begin + chunk, params.num_cells);
+
+    var sum = 0u;
+    for (var c = begin; c < end; c++) {
+        sum += atomicLoad(&cell_cursors[c]);
+    }
+    chunk_sums[thread] = sum;
+    workgroupBarrier();
+
+    if thread == 0u {
+        var total = 0u;
+        for (var t = 0u; t < SCAN_THREADS; t++) {
+            let chunk_sum = chunk_sums[t];
+            chunk_sums[t] = total;
+            total += chunk_sum;
+        }
+    }
+    workgroupBarrier();
+
+    var start = chunk_sums[thread];
+    for (var c = begin; c < end; c++) {
+        let count = atomicLoad(&cell_cursors[c]);
+        cell_starts[c] = start;
+        atomicStore(&cell_cursors[c], start);
+        start += count;
+    }
+}
+
+@compute @workgroup_size(64)
+fn scatter(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.num_particles || links[i].alive == 0u {
+        return;
+    }
+    let slot = atomicAdd(&cell_cursors[cell_index(cell_coords(positions[i]))], 1u);
+    sorted[slot] = i;
+}
+
+@compute @workgroup_size(64)
+fn forces(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.num_particles {
+        return;
+    }
+
+    var output: Output;
+    let link = links[i];
+    if link.alive == 0u {
+        outputs[i] = output;
+        return;
+    }
+    let position = positions[i];
+
+    if link.prev >= 0 && link.next >= 0 {
+        output.attraction = (positions[link.prev] + positions[link.next]) / 2.0 - position;
+    } else if link.prev >= 0 {
+        output.attraction = (positions[link.prev] - position) / 2.0;
+    } else if link.next >= 0 {
+        output.attraction = (positions[link.next] - position) / 2.0;
+    }
+
+    let radius = params.influence_radius;
+    let center = cell_coords(position);
+    let size = vec2<i32>(i32(params.grid_width), i32(params.grid_height));
+    let min_cell = max(center - 1, vec2<i32>(0));
+    let max_cell = min(center + 1, size - 1);
+    for (var y = min_cell.y; y <= max_cell.y; y++) {
+        for (var x = min_cell.x; x <= max_cell.x; x++) {
+            let cell = cell_index(vec2<i32>(x, y));
+            let end = atomicLoad(&cell_cursors[cell]);
+            for (var k = cell_starts[cell]; k < end; k++) {
+                let j = sorted[k];
+                let offset = position - positions[j];
+                if j != i && length(offset) <= radius {
+                    output.pressure += offset / (radius * 0.5);
+                    output.num_neighbors += 1u;
+                }
+            }
+        }
+    }
+
+    let pressure = length(output.pressure);
+    if pressure > 2.0 {
+        output.pressure *= 2.0 / pressure;
+    }
+    outputs[i] = output;
+}
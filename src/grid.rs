@@ -0,0 +1,214 @@
+//! Uniform grid spatial hash used to accelerate neighbor queries.
+
+use glam::{Vec2, Vec3};
+use std::collections::HashMap;
+
+type Cell = (i32, i32);
+type SpaceCell = (i32, i32, i32);
+
+/// Buckets particle indices into square cells so that radius queries only have to look at the
+/// few cells overlapping the query circle instead of every particle.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<usize>>,
+}
+
+impl Default for SpatialGrid {
+    /// An empty grid with unit cells.
+    fn default() -> Self {
+        SpatialGrid::new(1.0)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn cell_of(&self, position: Vec2) -> Cell {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Clears the grid and inserts every position, using `cell_size` for the new layout.
+    ///
+    /// Cell buckets are kept around between rebuilds so their allocations can be reused.
+    pub fn rebuild(&mut self, cell_size: f32, positions: &[Vec2]) {
+        self.cell_size = cell_size;
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+
+        for (i, &position) in positions.iter().enumerate() {
+            let cell = self.cell_of(position);
+            self.cells.entry(cell).or_default().push(i);
+        }
+    }
+
+    /// The lower left corner of every cell holding any index, with how many it holds.
+    pub fn occupied_cells(&self) -> impl Iterator<Item = (Vec2, usize)> + '_ {
+        self.cells
+            .iter()
+            .filter(|(_, bucket)| !bucket.is_empty())
+            .map(move |(&(x, y), bucket)| {
+                (Vec2::new(x as f32, y as f32) * self.cell_size, bucket.len())
+            })
+    }
+
+    /// Calls `f` with every index whose cell overlaps the square bounding the circle at `center`
+    /// with the given `radius`. Callers still need to do an exact distance check.
+    pub fn for_each_candidate<F>(&self, center: Vec2, radius: f32, mut f: F)
+    where
+        F: FnMut(usize),
+    {
+        let (min_x, min_y) = self.cell_of(center - Vec2::splat(radius));
+        let (max_x, max_y) = self.cell_of(center + Vec2::splat(radius));
+
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                if let Some(bucket) = self.cells.get(&(x, y)) {
+                    bucket.iter().for_each(|&i| f(i));
+                }
+            }
+        }
+    }
+}
+
+/// Like `SpatialGrid`, but bucketing positions in space into cubic cells.
+pub struct SpaceGrid {
+    cell_size: f32,
+    cells: HashMap<SpaceCell, Vec<usize>>,
+}
+
+impl Default for SpaceGrid {
+    /// An empty grid with unit cells.
+    fn default() -> Self {
+        SpaceGrid::new(1.0)
+    }
+}
+
+impl SpaceGrid {
+    pub fn new(cell_size: f32) -> Self {
+        SpaceGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_of(&self, position: Vec3) -> SpaceCell {
+        let cell = (position / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32, cell.z as i32)
+    }
+
+    /// Clears the grid and inserts every position, see `SpatialGrid::rebuild()`.
+    pub fn rebuild(&mut self, cell_size: f32, positions: &[Vec3]) {
+        self.cell_size = cell_size;
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+
+        for (i, &position) in positions.iter().enumerate() {
+            let cell = self.cell_of(position);
+            self.cells.entry(cell).or_default().push(i);
+        }
+    }
+
+    /// Calls `f` with every index whose cell overlaps the cube bounding the sphere at `center`
+    /// with the given `radius`. Callers still need to do an exact distance check.
+    pub fn for_each_candidate<F>(&self, center: Vec3, radius: f32, mut f: F)
+    where
+        F: FnMut(usize),
+    {
+        let (min_x, min_y, min_z) = self.cell_of(center - Vec3::splat(radius));
+        let (max_x, max_y, max_z) = self.cell_of(center + Vec3::splat(radius));
+
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                for z in min_z..=max_z {
+                    if let Some(bucket) = self.cells.get(&(x, y, z)) {
+                        bucket.iter().for_each(|&i| f(i));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighbor_index::{BruteForce, NeighborIndex};
+    use glam::vec2;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    /// The indices of `positions` within `radius` of `center` among the candidates given by
+    /// `for_each_candidate`, sorted, so that repeated candidates show up too.
+    fn neighbors<F>(
+        positions: &[Vec2],
+        center: Vec2,
+        radius: f32,
+        for_each_candidate: F,
+    ) -> Vec<usize>
+    where
+        F: FnOnce(&mut dyn FnMut(usize)),
+    {
+        let mut found = Vec::new();
+        for_each_candidate(&mut |i| {
+            if positions[i].distance(center) <= radius {
+                found.push(i);
+            }
+        });
+        found.sort_unstable();
+        found
+    }
+
+    #[test]
+    fn finds_the_same_neighbors_as_brute_force() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(2);
+        let mut positions: Vec<Vec2> = (0..500)
+            .map(|_| vec2(rng.gen_range(-80.0..40.0), rng.gen_range(-80.0..40.0)))
+            .collect();
+        // On the borders of cells of both sizes below, on either side of zero.
+        for i in -12..=6 {
+            let along = i as f32 * 6.0;
+            positions.extend([vec2(along, -0.0), vec2(-along, along), vec2(along, along)]);
+        }
+
+        let mut grid = SpatialGrid::default();
+        let mut brute_force = BruteForce::default();
+        // Rebuilding with another influence radius keeps the buckets of the old cells around.
+        for radius in [6.0, 12.0, 3.0] {
+            grid.rebuild(radius, &positions);
+            brute_force.rebuild(radius, &positions);
+
+            let queries = positions
+                .iter()
+                .map(|&p| (p, radius))
+                .chain((0..200).map(|_| {
+                    let center = vec2(rng.gen_range(-90.0..50.0), rng.gen_range(-90.0..50.0));
+                    (center, rng.gen_range(0.0..2.0 * radius))
+                }));
+            for (center, radius) in queries {
+                assert_eq!(
+                    neighbors(&positions, center, radius, |f| grid
+                        .for_each_candidate(center, radius, f)),
+                    neighbors(&positions, center, radius, |f| brute_force
+                        .for_each_candidate(center, radius, f)),
+                    "around {} within {}",
+                    center,
+                    radius
+                );
+            }
+        }
+    }
+}
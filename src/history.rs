@@ -0,0 +1,70 @@
+//! Past outlines of the curves, kept to draw the growth rings they leave behind.
+
+use crate::{ParticleSystem, Polyline};
+use std::collections::VecDeque;
+
+/// The curves as they were at some step, copied to outlive later steps unlike the borrowed
+/// `crate::Snapshot`.
+pub struct Snapshot {
+    pub step: u64,
+    pub polylines: Vec<Polyline>,
+}
+
+/// A ring buffer of snapshots of the curves, taken every `every` steps. Once `depth` snapshots are
+/// kept, each new one replaces the oldest.
+pub struct History {
+    pub every: u64,
+    pub depth: usize,
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl History {
+    pub fn new(every: u64, depth: usize) -> Self {
+        History {
+            every,
+            depth,
+            snapshots: VecDeque::with_capacity(depth),
+        }
+    }
+
+    /// Takes a snapshot of `ps` if its step is a multiple of `every`. Call it after every step.
+    pub fn record(&mut self, ps: &ParticleSystem) {
+        if ps.steps().is_multiple_of(self.every.max(1)) {
+            self.push(ps);
+        }
+    }
+
+    /// Takes a snapshot of `ps` whatever its step.
+    pub fn push(&mut self, ps: &ParticleSystem) {
+        while self.snapshots.len() >= self.depth.max(1) {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot {
+            step: ps.steps(),
+            polylines: ps.polylines(),
+        });
+    }
+
+    /// The snapshots from oldest to newest.
+    pub fn snapshots(&self) -> impl Iterator<Item = &Snapshot> + '_ {
+        self.snapshots.iter()
+    }
+
+    /// How opaque the `i`th snapshot from the oldest is drawn, fading from nearly transparent for
+    /// the oldest to fully opaque for the newest.
+    pub fn opacity(&self, i: usize) -> f32 {
+        (i + 1) as f32 / self.snapshots.len() as f32
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
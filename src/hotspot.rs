@@ -0,0 +1,100 @@
+//! Regions where the curves grow faster, to direct where folds and density develop.
+
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// A disc or polygon multiplying the probability of splitting the edges inside it by `factor`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hotspot {
+    /// The center of the disc, or where the corners of `polygon` are relative to, at time 0.
+    #[serde(default)]
+    pub position: Vec2,
+    /// The corners of the region, relative to `position`. A disc of `radius` when empty.
+    #[serde(default)]
+    pub polygon: Vec<Vec2>,
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// Above 1 the curves grow faster inside, and below 1 slower.
+    #[serde(default = "default_factor")]
+    pub factor: f32,
+    #[serde(default)]
+    pub motion: Motion,
+}
+
+/// How a `Hotspot` moves over simulated time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Motion {
+    #[default]
+    Still,
+    /// Moves in a straight line, by `velocity` per unit of time.
+    Drift { velocity: Vec2 },
+    /// Circles counterclockwise around `center`, once every `period` units of time.
+    Orbit { center: Vec2, period: f32 },
+}
+
+fn default_radius() -> f32 {
+    50.0
+}
+
+fn default_factor() -> f32 {
+    4.0
+}
+
+impl Hotspot {
+    /// A disc of `radius` around `position`.
+    pub fn new(position: Vec2, radius: f32, factor: f32) -> Self {
+        Hotspot {
+            position,
+            polygon: Vec::new(),
+            radius,
+            factor,
+            motion: Motion::Still,
+        }
+    }
+
+    /// Where `position` has moved to at `time`.
+    pub fn position_at(&self, time: f32) -> Vec2 {
+        match self.motion {
+            Motion::Still => self.position,
+            Motion::Drift { velocity } => self.position + velocity * time,
+            Motion::Orbit { center, period } => {
+                let angle = TAU * time / period.max(f32::EPSILON);
+                let (sin, cos) = angle.sin_cos();
+                let offset = self.position - center;
+                center
+                    + vec2(
+                        offset.x * cos - offset.y * sin,
+                        offset.x * sin + offset.y * cos,
+                    )
+            }
+        }
+    }
+
+    /// The factor the split probability is scaled by at `p` and `time`: `factor` inside the
+    /// region, and 1 outside of it.
+    pub fn scale_at(&self, p: Vec2, time: f32) -> f32 {
+        let p = p - self.position_at(time);
+        let inside = if self.polygon.is_empty() {
+            p.length() <= self.radius
+        } else {
+            let points = &self.polygon;
+            let mut inside = false;
+            for i in 0..points.len() {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x)
+                {
+                    inside = !inside;
+                }
+            }
+            inside
+        };
+        if inside {
+            self.factor
+        } else {
+            1.0
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! The particles as instances of one shape, each with its own position, size and color, so that
+//! all of them are drawn at once instead of one shape at a time, which is what slows down drawing
+//! tens of thousands of them.
+
+use crate::{Color, ParticleSystem};
+use glam::{vec2, Vec2};
+use std::f32::consts::TAU;
+
+/// One shape to draw, `size` across.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Instance {
+    pub position: Vec2,
+    pub size: f32,
+    pub color: Color,
+    /// The particle it is drawn for, to look up more of it.
+    pub index: usize,
+}
+
+/// The particles of `ps` at `positions`, and their copies under the symmetry, as wide as their
+/// radius in their own colors.
+pub fn particles(ps: &ParticleSystem, positions: &[Vec2]) -> Vec<Instance> {
+    let colors = ps.colors();
+    ps.symmetry_transforms()
+        .into_iter()
+        .flat_map(|transform| {
+            ps.particle_indices().map(move |i| Instance {
+                position: transform * positions[i],
+                size: ps.particle_radius,
+                color: colors[i],
+                index: i,
+            })
+        })
+        .collect()
+}
+
+/// The outline of a regular polygon with `sides` around the origin, 1 across, to draw the
+/// instances as discs with.
+pub fn disc(sides: usize) -> Vec<Vec2> {
+    (0..sides.max(3))
+        .map(|k| {
+            let angle = TAU * k as f32 / sides.max(3) as f32;
+            vec2(angle.cos(), angle.sin()) / 2.0
+        })
+        .collect()
+}
+
+/// Every instance as the convex `outline` scaled to its size, in one mesh of colored vertices.
+pub fn mesh(instances: &[Instance], outline: &[Vec2]) -> (Vec<(Vec2, Color)>, Vec<[usize; 3]>) {
+    let n = outline.len();
+    let mut vertices = Vec::with_capacity(instances.len() * n);
+    let mut triangles = Vec::with_capacity(instances.len() * n.saturating_sub(2));
+    for instance in instances {
+        let first = vertices.len();
+        vertices.extend(
+            outline
+                .iter()
+                .map(|&corner| (instance.position + corner * instance.size, instance.color)),
+        );
+        triangles.extend((1..n.saturating_sub(1)).map(|k| [first, first + k, first + k + 1]));
+    }
+    (vertices, triangles)
+}
@@ -0,0 +1,25 @@
+//! JSON export of the grown curves, for tools that want the raw geometry.
+
+use crate::{ParticleSystem, Polyline};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Document {
+    seed: u64,
+    steps: u64,
+    curves: Vec<Polyline>,
+}
+
+impl ParticleSystem {
+    /// Serializes every curve as `{ "points": [[x, y], ...], "closed": bool }`, along with the
+    /// seed and step count needed to grow it again.
+    pub fn to_json(&self) -> String {
+        let document = Document {
+            seed: self.seed(),
+            steps: self.steps(),
+            curves: self.polylines(),
+        };
+
+        serde_json::to_string(&document).unwrap()
+    }
+}
@@ -0,0 +1,117 @@
+//! Differential line growth.
+//!
+//! The simulation lives here, independent of any windowing or rendering stack, so it can be
+//! driven from the nannou app, headless tools or tests alike.
+
+pub mod accumulation;
+pub mod area;
+pub mod attractor;
+pub mod audio;
+pub mod backdrop;
+pub mod bloom;
+pub mod boundary;
+pub mod cli;
+pub mod config;
+pub mod cyclic;
+pub mod direction_field;
+pub mod dxf;
+pub mod fill;
+pub mod gcode;
+pub mod gif;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod grid;
+pub mod history;
+pub mod hotspot;
+pub mod instances;
+pub mod json;
+pub mod links;
+pub mod mesh;
+pub mod metrics_log;
+pub mod midi;
+pub mod ndi;
+pub mod neighbor_index;
+pub mod noise_field;
+pub mod obstacle;
+pub mod osc;
+pub mod parameter_map;
+mod particle_system;
+pub mod post;
+pub mod quadtree;
+pub mod raster;
+pub mod reaction_diffusion;
+pub mod replay;
+pub mod schedule;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod shader;
+pub mod snapshot;
+pub mod space_curve;
+pub mod spawn;
+pub mod spline;
+pub mod spout;
+pub mod state;
+pub mod stats;
+pub mod stroke;
+pub mod svg;
+pub mod sweep;
+pub mod symmetry;
+pub mod text;
+pub mod timestep;
+pub mod torus;
+pub mod undo;
+pub mod video;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+pub use accumulation::Accumulation;
+pub use area::AreaPreservation;
+pub use attractor::Attractor;
+pub use audio::{AudioInput, AudioLevels};
+pub use backdrop::{Backdrop, Fit};
+pub use bloom::{Bloom, BloomPass};
+pub use boundary::{Boundary, BoundaryResponse, Floor, ImageMask};
+pub use config::Config;
+pub use direction_field::{DirectionField, DirectionSource};
+pub use dxf::{DxfOptions, DxfUnits};
+pub use fill::Fill;
+pub use gcode::GcodeOptions;
+pub use gif::GifRecorder;
+pub use glam::{vec2, Vec2};
+pub use grid::{SpaceGrid, SpatialGrid};
+pub use history::History;
+pub use hotspot::{Hotspot, Motion};
+pub use instances::Instance;
+pub use links::{Link, Links};
+pub use mesh::Mesh;
+pub use metrics_log::MetricsLog;
+pub use midi::{MidiControls, MidiInput};
+pub use ndi::NdiSender;
+pub use neighbor_index::{NeighborIndex, NeighborIndexKind};
+pub use noise_field::{NoiseField, NoiseMode};
+pub use obstacle::Obstacle;
+pub use osc::{OscCommand, OscServer};
+pub use parameter_map::{MappedParameter, ParameterMap};
+pub use particle_system::{
+    AdaptiveRadius, Color, Coloring, Curve, Expiry, GrowthBudget, Integration, Jitter,
+    ParticleSystem, Polyline, Separation, SplitCandidate, SplitCondition, SplitPolicy,
+    SplitThrottle, Springs,
+};
+pub use post::PostEffects;
+pub use quadtree::Quadtree;
+pub use raster::{BlendMode, Canvas};
+pub use reaction_diffusion::{GrayScott, ReactionDiffusion};
+pub use replay::{ReplayReader, ReplayWriter};
+pub use schedule::{Easing, Schedule};
+pub use shader::ShaderPass;
+pub use snapshot::Snapshot;
+pub use space_curve::{OrbitCamera, SpaceCurve};
+pub use spawn::SpawnShape;
+pub use spout::SpoutSender;
+pub use stats::{Stats, Summary};
+pub use stroke::{Cap, Join, Thickness};
+pub use symmetry::Symmetry;
+pub use timestep::FixedTimestep;
+pub use torus::Torus;
+pub use undo::Undo;
+pub use video::VideoRecorder;
@@ -0,0 +1,352 @@
+//! The order of the particles along their curves, as doubly-linked lists threaded through their
+//! indices.
+
+use serde::{Deserialize, Serialize};
+
+/// The neighbors of a particle along its curve. Endpoints of open curves lack one of them, and
+/// particles on no curve lack both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(
+    from = "(Option<usize>, Option<usize>)",
+    into = "(Option<usize>, Option<usize>)"
+)]
+pub struct Link {
+    pub prev: Option<usize>,
+    pub next: Option<usize>,
+}
+
+// Saved as `(prev, next)` pairs, like states written before links had names.
+impl From<(Option<usize>, Option<usize>)> for Link {
+    fn from((prev, next): (Option<usize>, Option<usize>)) -> Self {
+        Link { prev, next }
+    }
+}
+
+impl From<Link> for (Option<usize>, Option<usize>) {
+    fn from(link: Link) -> Self {
+        (link.prev, link.next)
+    }
+}
+
+/// The links of every particle, indexed like the particles.
+///
+/// Links are always mutual: `b` follows `a` exactly when `a` precedes `b`. Every curve is then
+/// either a ring, where following `next` from any particle comes back around to it, or a chain
+/// from an endpoint without a `prev` to one without a `next`. The methods here keep it that way,
+/// which `validate()` checks.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Links(Vec<Link>);
+
+impl Links {
+    pub fn new() -> Self {
+        Links(Vec::new())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The links of every particle, indexed like the particles.
+    pub fn as_slice(&self) -> &[Link] {
+        &self.0
+    }
+
+    pub fn get(&self, i: usize) -> Link {
+        self.0[i]
+    }
+
+    pub fn prev(&self, i: usize) -> Option<usize> {
+        self.0[i].prev
+    }
+
+    pub fn next(&self, i: usize) -> Option<usize> {
+        self.0[i].next
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Link> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Every edge as a particle and its successor.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, link)| link.next.map(|next| (i, next)))
+    }
+
+    /// The edges touching particle `i`, as a particle and its successor.
+    pub fn edges_of(&self, i: usize) -> impl Iterator<Item = (usize, usize)> {
+        let Link { prev, next } = self.0[i];
+        prev.map(|prev| (prev, i))
+            .into_iter()
+            .chain(next.map(|next| (i, next)))
+    }
+
+    /// Adds an unlinked particle, returning its index.
+    pub fn push(&mut self) -> usize {
+        self.0.push(Link::default());
+        self.0.len() - 1
+    }
+
+    /// Makes `b` follow `a`, neither of which may be linked on that side yet.
+    pub fn link(&mut self, a: usize, b: usize) {
+        debug_assert!(
+            self.0[a].next.is_none() && self.0[b].prev.is_none(),
+            "linking {} to {} would break other links",
+            a,
+            b
+        );
+        self.0[a].next = Some(b);
+        self.0[b].prev = Some(a);
+    }
+
+    /// Inserts the unlinked particle `i` right after `a`, between it and its successor if any.
+    pub fn insert_after(&mut self, a: usize, i: usize) {
+        debug_assert_eq!(self.0[i], Link::default(), "{} is already linked", i);
+        let next = self.0[a].next;
+        self.0[i] = Link {
+            prev: Some(a),
+            next,
+        };
+        self.0[a].next = Some(i);
+        if let Some(next) = next {
+            self.0[next].prev = Some(i);
+        }
+    }
+
+    /// Takes particle `i` out of its curve, linking its neighbors to each other instead, and
+    /// leaves it unlinked.
+    pub fn remove(&mut self, i: usize) {
+        let Link { prev, next } = self.0[i];
+        if let Some(prev) = prev {
+            self.0[prev].next = next;
+        }
+        if let Some(next) = next {
+            self.0[next].prev = prev;
+        }
+        self.0[i] = Link::default();
+    }
+
+    /// Swaps the successors of `a` and `b`. A ring they are both on splits in two, one through `a`
+    /// and one through `b`, while the rings they are on join into one if they are different.
+    pub fn swap_next(&mut self, a: usize, b: usize) {
+        let (a_next, b_next) = (self.0[a].next, self.0[b].next);
+        self.0[a].next = b_next;
+        self.0[b].next = a_next;
+        if let Some(b_next) = b_next {
+            self.0[b_next].prev = Some(a);
+        }
+        if let Some(a_next) = a_next {
+            self.0[a_next].prev = Some(b);
+        }
+    }
+
+    /// Whether `b` is at most `steps` links away from `a` along their curve, in either direction.
+    pub fn is_within(&self, a: usize, b: usize, steps: usize) -> bool {
+        let (mut forward, mut backward) = (Some(a), Some(a));
+        for _ in 0..=steps {
+            if forward == Some(b) || backward == Some(b) {
+                return true;
+            }
+            forward = forward.and_then(|i| self.0[i].next);
+            backward = backward.and_then(|i| self.0[i].prev);
+        }
+        false
+    }
+
+    /// The particles of the curve through `i` in order, and whether the curve is a ring. Rings
+    /// start at `i` and chains at their first endpoint.
+    pub fn walk(&self, i: usize) -> (Vec<usize>, bool) {
+        let mut start = i;
+        while let Some(prev) = self.0[start].prev {
+            if prev == i {
+                start = i;
+                break;
+            }
+            start = prev;
+        }
+
+        let mut indices = vec![start];
+        let mut current = self.0[start].next;
+        while let Some(c) = current {
+            if c == start {
+                return (indices, true);
+            }
+            indices.push(c);
+            current = self.0[c].next;
+        }
+        (indices, false)
+    }
+
+    /// Checks that every link points to a particle that links back, describing the first one
+    /// that doesn't.
+    pub fn validate(&self) -> Result<(), String> {
+        let linked_back = |j: usize, back: fn(&Link) -> Option<usize>, i: usize| {
+            self.0.get(j).is_some_and(|link| back(link) == Some(i))
+        };
+
+        for (i, link) in self.0.iter().enumerate() {
+            if let Some(next) = link.next {
+                if !linked_back(next, |link| link.prev, i) {
+                    return Err(format!(
+                        "{} is followed by {}, which doesn't precede it",
+                        i, next
+                    ));
+                }
+            }
+            if let Some(prev) = link.prev {
+                if !linked_back(prev, |link| link.next, i) {
+                    return Err(format!(
+                        "{} is preceded by {}, which doesn't follow it",
+                        i, prev
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(n: usize) -> Links {
+        let mut links = Links::new();
+        for _ in 0..n {
+            links.push();
+        }
+        for i in 0..n {
+            links.link(i, (i + 1) % n);
+        }
+        links
+    }
+
+    fn chain(n: usize) -> Links {
+        let mut links = Links::new();
+        for _ in 0..n {
+            links.push();
+        }
+        for i in 1..n {
+            links.link(i - 1, i);
+        }
+        links
+    }
+
+    #[test]
+    fn ring_is_a_single_cycle() {
+        let links = ring(5);
+        assert_eq!(links.validate(), Ok(()));
+        for i in 0..5 {
+            let (indices, is_ring) = links.walk(i);
+            assert!(is_ring);
+            assert_eq!(indices.len(), 5);
+        }
+    }
+
+    #[test]
+    fn inserting_into_a_ring_keeps_a_single_cycle() {
+        let mut links = ring(4);
+        for a in [0, 2, 4, 4, 1, 7] {
+            let i = links.push();
+            links.insert_after(a, i);
+            assert_eq!(links.validate(), Ok(()));
+            assert_eq!(links.next(a), Some(i));
+
+            let (mut indices, is_ring) = links.walk(0);
+            assert!(is_ring);
+            indices.sort_unstable();
+            assert_eq!(indices, (0..links.len()).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn inserting_into_a_chain_keeps_its_endpoints() {
+        let mut links = chain(3);
+        let middle = links.push();
+        links.insert_after(0, middle);
+        let end = links.push();
+        links.insert_after(2, end);
+
+        assert_eq!(links.validate(), Ok(()));
+        assert_eq!(links.walk(2), (vec![0, middle, 1, 2, end], false));
+    }
+
+    #[test]
+    fn removing_from_a_ring_closes_the_gap() {
+        let mut links = ring(5);
+        links.remove(2);
+        links.remove(0);
+
+        assert_eq!(links.validate(), Ok(()));
+        assert_eq!(links.get(2), Link::default());
+        assert_eq!(links.get(0), Link::default());
+        assert_eq!(links.walk(1), (vec![1, 3, 4], true));
+    }
+
+    #[test]
+    fn removing_an_endpoint_shortens_the_chain() {
+        let mut links = chain(4);
+        links.remove(0);
+
+        assert_eq!(links.validate(), Ok(()));
+        assert_eq!(links.walk(3), (vec![1, 2, 3], false));
+    }
+
+    #[test]
+    fn swapping_successors_on_a_ring_splits_it() {
+        let mut links = ring(6);
+        links.swap_next(0, 3);
+
+        assert_eq!(links.validate(), Ok(()));
+        assert_eq!(links.walk(0), (vec![0, 4, 5], true));
+        assert_eq!(links.walk(3), (vec![3, 1, 2], true));
+    }
+
+    #[test]
+    fn swapping_successors_across_rings_joins_them() {
+        let mut links = ring(6);
+        links.swap_next(0, 3);
+        links.swap_next(0, 3);
+
+        assert_eq!(links.validate(), Ok(()));
+        assert_eq!(links.walk(0), (vec![0, 1, 2, 3, 4, 5], true));
+    }
+
+    #[test]
+    fn nearby_particles_are_within_a_few_steps() {
+        let links = ring(10);
+        assert!(links.is_within(0, 2, 2));
+        assert!(links.is_within(0, 8, 2));
+        assert!(!links.is_within(0, 5, 4));
+        assert!(chain(10).is_within(9, 6, 3));
+        assert!(!chain(10).is_within(0, 9, 5));
+    }
+
+    #[test]
+    fn one_sided_links_are_invalid() {
+        let mut links = ring(3);
+        links.0[0].next = Some(2);
+        assert!(links.validate().is_err());
+
+        let mut links = chain(3);
+        links.0[2].prev = None;
+        assert!(links.validate().is_err());
+    }
+
+    #[test]
+    fn links_are_saved_as_pairs() {
+        let links = chain(2);
+        let json = serde_json::to_string(&links).unwrap();
+        assert_eq!(json, "[[null,1],[0,null]]");
+
+        let loaded: Links = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.walk(0), (vec![0, 1], false));
+    }
+}
@@ -1,283 +1,1879 @@
+use differential_lines::cli::{self, Args};
+use differential_lines::stroke::{self, UNIFORM_THICKNESS};
+use differential_lines::{fill, instances, raster};
+use differential_lines::{
+    vec2, Accumulation, Attractor, AudioInput, AudioLevels, Backdrop, BloomPass, Canvas, Config,
+    FixedTimestep, GifRecorder, History, Instance, MetricsLog, MidiInput, NdiSender, Obstacle,
+    OscCommand, OscServer, ParticleSystem, ShaderPass, SpatialGrid, SpoutSender, Stats, Undo, Vec2,
+    VideoRecorder,
+};
 use nannou::prelude::*;
-use std::f32::consts::PI;
+use nannou::ui::{widget, Colorable, Positionable, Ui, Widget};
+use nannou::window::Window;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::borrow::Cow;
+use std::cell::Ref;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+mod space_app;
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse(APP_NAME);
+    if args.headless || args.sweep.is_some() || args.play.is_some() {
+        let result = match (&args.sweep, &args.play) {
+            (Some(sweep), _) => cli::run_sweep(&args, sweep),
+            (None, Some(play)) => cli::run_play(&args, play),
+            (None, None) => cli::run_headless(args),
+        };
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.space {
+        nannou::app(space_app::model)
+            .update(space_app::update)
+            .run();
+        return;
+    }
+
+    nannou::app(model).update(update).exit(exit).run();
 }
 
-fn wrap(num: i32, max: i32) -> usize {
-    let wrapped = if num < 0 {
-        max - 1
-    } else if num == max {
-        0
-    } else {
-        num
-    };
+const APP_NAME: &str = "differential-lines";
 
-    wrapped as usize
-}
+const ATTRACTOR_STRENGTH: f32 = 0.5;
+const ATTRACTOR_FALLOFF: f32 = 100.0;
+/// How far the mouse has to move, in pixels, for a click to become a drag.
+const DRAG_THRESHOLD: f32 = 3.0;
+/// How close to the cursor, in pixels, particles get pinned or released.
+const PIN_RADIUS: f32 = 10.0;
+/// How close to the cursor, in pixels, an edge has to be to get particles injected into it.
+const INJECT_RADIUS: f32 = 20.0;
+/// How far the mouse moves, in pixels, between injections while dragging.
+const INJECT_SPACING: f32 = 4.0;
+/// How far per step the repulsion brush pushes particles right under the cursor.
+const BRUSH_STRENGTH: f32 = 1.5;
+/// How often the rates shown in the statistics overlay are updated.
+const RATE_INTERVAL: Duration = Duration::from_millis(500);
+/// How many quads across images are drawn with in the window, which has no textures to draw them
+/// at full resolution.
+const IMAGE_CELLS: usize = 512;
+/// How many sides the discs of particles are drawn with, plenty for dots a few pixels across.
+const DISC_SIDES: usize = 12;
+/// The width and height of the debug window, in pixels.
+const DEBUG_WINDOW_SIZE: u32 = 600;
+/// How long, in pixels, the pressure drawn in the debug window is at its 95th percentile.
+const DEBUG_VECTOR_LENGTH: f32 = 12.0;
 
 struct Model {
+    config: Config,
+    config_path: PathBuf,
+    config_watcher: Option<ConfigWatcher>,
     ps: ParticleSystem,
+    seed: u64,
+    paused: bool,
+    timestep: FixedTimestep,
+    /// Steps requested with `.` while paused, run on the next update.
+    pending_steps: usize,
+    recording: Option<Recording>,
+    gif: Option<GifCapture>,
+    video: Option<VideoCapture>,
+    ndi: Option<NdiCapture>,
+    spout: Option<SpoutCapture>,
+    /// The window the simulation is shown in, which captures and resizes go by rather than
+    /// whichever window has focus.
+    window: WindowId,
+    /// Opened by `--debug-window`.
+    debug: Option<DebugWindow>,
+    next_frame_index: usize,
+    camera: Camera,
+    drag: Option<Drag>,
+    /// Where the last particle was injected, in window coordinates, while shift dragging.
+    injection: Option<Vec2>,
+    /// Whether the repulsion brush is held down.
+    brushing: bool,
+    /// How far the repulsion brush reaches, in pixels, set with the scroll wheel while brushing.
+    brush_radius: f32,
+    /// The corners placed so far while drawing an obstacle.
+    obstacle_draft: Option<Vec<Vec2>>,
+    render_mode: RenderMode,
+    /// Whether the forces on every particle are drawn over it, toggled with `A`.
+    overlay: bool,
+    /// Past outlines drawn as growth rings, while enabled with `H`.
+    history: Option<History>,
+    /// Earlier states stepped back to with `Z`.
+    undo: Undo,
+    /// Drawn behind the curves while `[backdrop]` has an image.
+    backdrop: Option<Backdrop>,
+    /// The trails the curves leave, toggled with `B` and drawn instead of them.
+    accumulation: Option<Accumulation>,
+    /// The trails with the `[accumulation]` bloom added, shown and saved in their place.
+    bloomed: Option<Canvas>,
+    bloom: BloomPass,
+    /// Draws the curves while `[render]` has a shader that compiled.
+    shader: ShaderPass,
+    /// Watches the shader so that edits to it apply while the app is running.
+    shader_watcher: Option<ConfigWatcher>,
+    /// Given `--metrics`, until writing to it fails.
+    metrics: Option<MetricsLog>,
+    ui: Ui,
+    hud_text: widget::Id,
+    /// Whether the statistics overlay is shown, toggled with `I`.
+    hud: bool,
+    frame_rate: RateMeter,
+    step_rate: RateMeter,
+    /// Listening while `[audio]` has mappings, and the levels heard, smoothed every frame.
+    audio: Option<AudioInput>,
+    audio_levels: AudioLevels,
+    /// Connected while `[midi]` has mappings.
+    midi: Option<MidiInput>,
+    /// Listening while `[osc]` has a port.
+    osc: Option<OscServer>,
+    /// The parameters set over OSC, by dotted path, kept until `[simulation]` is reloaded.
+    osc_parameters: Vec<(String, f64)>,
 }
 
-struct ParticleSystem {
-    particle_radius: f32,
-    influence_radius: f32,
-    max_pressure_index: usize,
-    max_attraction_index: usize,
-    max_neighbors_index: usize,
-    num_particles: usize,
-    positions: Vec<Point2>,
-    colors: Vec<Rgba<f32>>,
-    edges: Vec<(usize, usize)>,
-    pressures: Vec<Vector2>,
-    attractions: Vec<Vector2>,
-    num_neighbors: Vec<usize>,
-}
-
-impl ParticleSystem {
-    fn new() -> Self {
-        let positions = Vec::new();
-        let colors = Vec::new();
-        let edges = Vec::new();
-        let pressures = Vec::new();
-        let attractions = Vec::new();
-        let num_neighbors = Vec::new();
-
-        ParticleSystem {
-            particle_radius: 4.0,
-            influence_radius: 12.0,
-            max_pressure_index: 0,
-            max_attraction_index: 0,
-            max_neighbors_index: 0,
-            num_particles: 0,
-            positions,
-            colors,
-            edges,
-            pressures,
-            attractions,
-            num_neighbors,
-        }
-    }
-
-    fn add_particle(
-        &mut self,
-        position: Point2,
-        color: Rgba<f32>,
-        edges: (usize, usize),
-        pressure: Vector2,
-        attraction: Vector2,
-    ) {
-        self.positions.push(position);
-        self.colors.push(color);
-        self.edges.push(edges);
-        self.pressures.push(pressure);
-        self.attractions.push(attraction);
-        self.num_neighbors.push(0);
-        self.num_particles += 1;
-    }
-
-
-    fn spawn_particles(&mut self, num_particles: usize, spawn_radius: f32) {
-        let delta_phi = (2.0 * PI) / num_particles as f32;
-        let mut phi = 0.0;
-
-        for i in 0..num_particles {
-            let direction = vec2(phi.cos(), phi.sin());
-            let offset = (phi * 6.2).sin() * 50.0;
-            let position = direction * (spawn_radius + offset);
-
-            let l = random_f32() * 0.8 + 0.1;
-            let color = Rgba::new(l, l - random_f32() * 0.2, l - random_f32() * 0.1, 1.0);
-
-            let prev_particle = wrap(i as i32 - 1, num_particles as i32);
-            let next_particle = wrap(i as i32 + 1, num_particles as i32);
-
-            let edges = (prev_particle, next_particle);
-            let pressure = vec2(0.0, 0.0);
-            let attraction = vec2(0.0, 0.0);
-
-            self.add_particle(position, color, edges, pressure, attraction);
-
-            phi += delta_phi;
-        }
-
-        self.num_particles = num_particles;
-    }
-
-    fn update(&mut self) {
-        let old_positions = self.positions.clone();
-        for i in 0..self.num_particles {
-            let neighbors = self.get_neighbors_of_particle(i);
-            self.num_neighbors[i] = neighbors.len();
-
-            if self.num_neighbors[self.max_neighbors_index] < neighbors.len() {
-                self.max_neighbors_index = i;
-            }
-
-            let attraction = {
-                let (b0, b1) = self.edges[i];
-                (old_positions[b0] + old_positions[b1]) / 2.0 - old_positions[i]
-            };
-            self.attractions[i] = attraction;
-            self.positions[i] += attraction * 0.6;
-            if self.attractions[self.max_attraction_index].magnitude() < attraction.magnitude() {
-                self.max_attraction_index = i;
-            }
+/// Counts events over time, e.g. frames or steps, averaging their rate per second over
+/// `RATE_INTERVAL` so that it can be read while it changes.
+#[derive(Default)]
+struct RateMeter {
+    count: u64,
+    elapsed: Duration,
+    rate: f64,
+}
 
-            let pressure = {
-                let mut pressure = vec2(0.0, 0.0);
-                for j in neighbors {
-                    pressure +=
-                        (self.positions[i] - self.positions[j]) / (self.influence_radius * 0.5);
-                }
+impl RateMeter {
+    fn record(&mut self, count: u64, elapsed: Duration) {
+        self.count += count;
+        self.elapsed += elapsed;
+        if self.elapsed >= RATE_INTERVAL {
+            self.rate = self.count as f64 / self.elapsed.as_secs_f64();
+            self.count = 0;
+            self.elapsed = Duration::ZERO;
+        }
+    }
+}
 
-                pressure.limit_magnitude(2.0)
-            };
-            self.pressures[i] = pressure;
-            self.positions[i] += (pressure) * 0.2;
-            if self.pressures[self.max_pressure_index].magnitude() < pressure.magnitude() {
-                self.max_pressure_index = i;
-            }
-        }
-
-        for i in 0..self.num_particles {
-            let p =
-                self.pressures[i].magnitude() / self.pressures[self.max_pressure_index].magnitude();
-            let a = self.attractions[i].magnitude()
-                / self.attractions[self.max_attraction_index].magnitude();
-            let n = 1.0
-                - self.num_neighbors[i] as f32
-                    / self.num_neighbors[self.max_neighbors_index] as f32;
-            self.colors[i] = Rgba::new(p, a, p * a + 0.1, 1.0);
-        }
-
-        for e in 0..self.edges.len() {
-            let (p0, p1) = (e, self.edges[e].1);
-            let avg_pressure = (self.pressures[p0] + self.pressures[p1]) / 2.0;
-            let relative_magnitude =
-                avg_pressure.magnitude() / self.pressures[self.max_pressure_index].magnitude();
-
-            let tolerance = 0.05;
-            if self.num_neighbors[p0] + self.num_neighbors[p1] < 16 && random_f32() < 0.05 {
-                // self.colors[b0] = Rgba::new(0.2, 0.3, 1.0, 1.0);
-                // self.colors[i] = Rgba::new(0.2, 0.3, 1.0, 1.0);
-                self.split_at(p0, p1);
-            } else {
-                // self.colors[b0] = Rgba::new(1.0, 0.3, 0.2, 1.0);
-                // self.colors[i] = Rgba::new(1.0, 0.3, 0.2, 1.0);
-            }
+/// The second window opened by `--debug-window`, so that the main one stays clean for projection.
+struct DebugWindow {
+    id: WindowId,
+    view: DebugView,
+}
+
+/// What the debug window shows, switched with `Tab` in it.
+#[derive(Clone, Copy, Debug)]
+enum DebugView {
+    /// The pressure acting on each particle, as a line.
+    Pressure,
+    /// Every particle colored by how many neighbors it has.
+    Neighbors,
+    /// How many particles each cell of a grid the size of the influence radius holds.
+    Grid,
+}
+
+impl DebugView {
+    fn next(self) -> Self {
+        match self {
+            DebugView::Pressure => DebugView::Neighbors,
+            DebugView::Neighbors => DebugView::Grid,
+            DebugView::Grid => DebugView::Pressure,
         }
     }
+}
 
+#[derive(Clone, Copy, PartialEq)]
+enum RenderMode {
+    /// The particles as colored dots, linked by faint lines.
+    Lines,
+    /// Closed curves as filled polygons, colored by the configured fill.
+    Filled,
+    /// Edges and particles colored by how crowded they are, see `crowding()`.
+    Density,
+}
 
-    fn split_at(&mut self, p0: usize, p1: usize) {
-        let new_index = self.positions.len();
+/// Maps simulation coordinates to window coordinates, which have their origin in the middle of
+/// the window.
+struct Camera {
+    /// The simulation position shown in the middle of the window.
+    position: Vec2,
+    /// Window pixels per simulation unit.
+    zoom: f32,
+}
 
-        let position = (self.positions[p0] + self.positions[p1]) / 2.0
-            + self.pressures[p0]
-            + self.pressures[p1];
-        let color = (self.colors[p0] + self.colors[p1]) / 2.0;
-        let edges = (p0, p1);
-        let pressure = vec2(0.0, 0.0);
-        let attraction = vec2(0.0, 0.0);
+impl Camera {
+    fn to_screen(&self, p: Vec2) -> Point2 {
+        let p = (p - self.position) * self.zoom;
+        pt2(p.x, p.y)
+    }
+
+    fn to_world(&self, screen: Vec2) -> Vec2 {
+        self.position + screen / self.zoom
+    }
+
+    /// Zooms by `factor` while keeping the point under `screen` in place.
+    fn zoom_at(&mut self, screen: Vec2, factor: f32) {
+        let before = self.to_world(screen);
+        self.zoom *= factor;
+        self.position += before - self.to_world(screen);
+    }
+
+    /// Centers the box between `min` and `max`, zoomed to fit in a window of the given size.
+    fn fit(&mut self, (min, max): (Vec2, Vec2), window_size: Vec2) {
+        let margin = 20.0;
+        let size = (max - min).max(Vec2::splat(1.0));
+        let zoom = (window_size - Vec2::splat(2.0 * margin)) / size;
 
-        self.edges[p0].1 = new_index;
-        self.edges[p1].0 = new_index;
-        self.add_particle(position, color, edges, pressure, attraction);
+        self.position = (min + max) / 2.0;
+        self.zoom = zoom.x.min(zoom.y).max(0.01);
     }
+}
+
+/// A left mouse button press, which pans the camera if dragged or places an attractor otherwise.
+struct Drag {
+    start: Vec2,
+    last: Vec2,
+}
+
+impl Drag {
+    fn is_click(&self) -> bool {
+        (self.last - self.start).length() <= DRAG_THRESHOLD
+    }
+}
 
-    fn get_neighbors_of_particle(&self, index: usize) -> Vec<usize> {
-        let mut neighbors = Vec::<usize>::new();
+/// Watches the configuration file, or another file it names, so that edits apply while the app is
+/// running.
+struct ConfigWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<notify::Event>>,
+    // Watching stops when this is dropped.
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    fn new(path: &Path) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+
+        // Editors often save by replacing the file, which would end a watch on the file itself.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
 
-        for j in 0..self.num_particles {
-            if index == j {
-                continue;
+        Ok(ConfigWatcher {
+            path: path.to_path_buf(),
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Whether the file was written to since the last call.
+    fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter().flatten() {
+            if (event.kind.is_modify() || event.kind.is_create())
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == self.path.file_name())
+            {
+                changed = true;
             }
+        }
+        changed
+    }
+}
+
+/// Frames are rasterized on the CPU while recording, since nannou doesn't let us read back the
+/// window's swapchain images.
+struct Recording {
+    canvas: Canvas,
+}
 
-            let distance = (self.positions[index] - self.positions[j]).magnitude();
+/// Like `Recording`, but into a GIF kept in memory until the capture is stopped.
+struct GifCapture {
+    canvas: Canvas,
+    recorder: GifRecorder,
+}
 
-            if distance <= self.influence_radius {
-                neighbors.push(j);
+/// Like `Recording`, but streamed into ffmpeg.
+struct VideoCapture {
+    canvas: Canvas,
+    recorder: VideoRecorder,
+    path: PathBuf,
+}
+
+/// Like `Recording`, but sent over NDI every frame rather than every step.
+struct NdiCapture {
+    canvas: Canvas,
+    sender: NdiSender,
+}
+
+/// Like `NdiCapture`, but shared with other apps on the machine through Spout.
+struct SpoutCapture {
+    canvas: Canvas,
+    sender: SpoutSender,
+}
+
+/// Draws the pressure, attraction and net force acting on every particle as lines from it, in
+/// the colors of `[overlay]`.
+fn draw_forces(
+    ps: &ParticleSystem,
+    positions: &[Vec2],
+    config: &Config,
+    camera: &Camera,
+    draw: &app::Draw,
+) {
+    let overlay = &config.overlay;
+    let (pressures, attractions) = (ps.pressures(), ps.attractions());
+    for i in ps.particle_indices() {
+        let forces = [
+            (overlay.pressure, pressures[i]),
+            (overlay.attraction, attractions[i]),
+            (overlay.net, pressures[i] + attractions[i]),
+        ];
+        for (color, force) in forces {
+            if let Some([r, g, b, a]) = color {
+                draw.line()
+                    .start(camera.to_screen(positions[i]))
+                    .end(camera.to_screen(positions[i] + force * overlay.scale))
+                    .thickness(overlay.thickness)
+                    .rgba(r, g, b, a);
             }
         }
+    }
+}
 
-        neighbors
+fn draw_density(ps: &ParticleSystem, positions: &[Vec2], camera: &Camera, draw: &app::Draw) {
+    let segments = ps.symmetry_transforms().into_iter().flat_map(|transform| {
+        ps.links().edges().map(move |(i, next)| {
+            let (r, g, b) = heat(crowding(ps, i, next));
+            let (start, end) = ps.edge_at(positions, i, next);
+            (transform * start, transform * end, Rgba::new(r, g, b, 1.0))
+        })
+    });
+    draw_segments(segments, UNIFORM_THICKNESS * 2.0, camera, draw);
+    let mut particles = instances::particles(ps, positions);
+    for particle in &mut particles {
+        let (r, g, b) = heat(crowding(ps, particle.index, particle.index));
+        particle.color = [r, g, b, 1.0];
     }
+    draw_instances(&particles, camera, draw);
+}
 
-    fn draw(&self, draw: &app::Draw) {
-        let thickness = 0.1;
+/// Draws every segment as a straight band `width` across in its own color, all in one mesh,
+/// which is much faster than a line each.
+fn draw_segments(
+    segments: impl Iterator<Item = (Vec2, Vec2, Rgba)>,
+    width: f32,
+    camera: &Camera,
+    draw: &app::Draw,
+) {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    for (start, end, color) in segments {
+        let (band, band_triangles) = stroke::stroke(&[start, end], &[width, width], false);
+        let first = vertices.len();
+        vertices.extend(band.into_iter().map(|p| (camera.to_screen(p), color)));
+        triangles.extend(
+            band_triangles
+                .into_iter()
+                .map(|[a, b, c]| [first + a, first + b, first + c]),
+        );
+    }
+    draw.mesh().indexed(vertices, triangles);
+}
 
-        for i in 0..self.edges.len() {
-            let (_, next) = self.edges[i];
+/// Draws every instance as a disc, all in one mesh, which is much faster than an ellipse each.
+fn draw_instances(particles: &[Instance], camera: &Camera, draw: &app::Draw) {
+    let (vertices, triangles) = instances::mesh(particles, &instances::disc(DISC_SIDES));
+    let vertices = vertices
+        .into_iter()
+        .map(|(p, [r, g, b, a])| (camera.to_screen(p), Rgba::new(r, g, b, a)));
+    draw.mesh().indexed(vertices, triangles);
+}
 
-            draw.line()
-                .start(self.positions[i])
-                .end(self.positions[next])
-                .thickness(thickness)
-                .rgba(0.8, 0.8, 0.8, 0.1);
+/// How close the edge from `i` to `next` is to having too many neighbors to split, from 0
+/// without any to 1 at `max_split_neighbors` and above. Passing a particle as both ends gives
+/// how crowded edges around it would be if its neighbors had as many neighbors as it does.
+fn crowding(ps: &ParticleSystem, i: usize, next: usize) -> f32 {
+    let num_neighbors = ps.num_neighbors();
+    let total = num_neighbors[i] + num_neighbors[next];
+    (total as f32 / ps.max_split_neighbors.max(1) as f32).min(1.0)
+}
+
+fn draw_particle_system(
+    ps: &ParticleSystem,
+    positions: &[Vec2],
+    config: &Config,
+    camera: &Camera,
+    draw: &app::Draw,
+) {
+    let [lr, lg, lb, la] = config.colors.line;
+    let line = Rgba::new(lr, lg, lb, la);
+
+    // Every curve in one mesh, rather than a mesh or a line per edge, which dominated drawing once
+    // there are tens of thousands of edges.
+    let render = &config.render;
+    let paths = stroke::stroke_paths_at(ps, positions, &render.thickness, render.subdivisions);
+    let (vertices, triangles) = stroke::mesh(&paths, render.join, render.cap);
+    let vertices = vertices.into_iter().map(|p| (camera.to_screen(p), line));
+    draw.mesh().indexed(vertices, triangles);
+
+    draw_instances(&instances::particles(ps, positions), camera, draw);
+
+    let pinned = ps.particle_indices().filter(|&i| ps.pinned()[i]);
+    for i in pinned {
+        let size = ps.particle_radius * camera.zoom * 1.5;
+        draw.rect()
+            .xy(camera.to_screen(positions[i]))
+            .w_h(size, size)
+            .rgba(1.0, 1.0, 1.0, 0.6);
+    }
+
+    for attractor in &ps.attractors {
+        let (r, g) = if attractor.strength > 0.0 {
+            (0.3, 1.0)
+        } else {
+            (1.0, 0.3)
+        };
+
+        draw.ellipse()
+            .xy(camera.to_screen(attractor.position))
+            .w_h(8.0, 8.0)
+            .rgba(r, g, 0.3, 0.8);
+    }
+}
+
+/// Draws the past outlines, fading out the older ones.
+fn draw_history(history: &History, config: &Config, camera: &Camera, draw: &app::Draw) {
+    let [r, g, b, a] = config.colors.history;
+    let half_thickness = config.history.thickness * camera.zoom / 2.0;
+
+    for (i, snapshot) in history.snapshots().enumerate() {
+        let color = Rgba::new(r, g, b, a * history.opacity(i));
+        for polyline in &snapshot.polylines {
+            let mut points = polyline.points.clone();
+            if polyline.closed {
+                points.extend(polyline.points.first());
+            }
+            let vertices = points.iter().map(|&p| (camera.to_screen(p), color));
+            draw.polyline().vertices(half_thickness, vertices);
         }
+    }
+}
 
-        for i in 0..self.num_particles {
-            let size = self.particle_radius;
+/// The window the simulation is shown in. `App::main_window()` is whichever window has focus,
+/// which is the debug window while it is clicked.
+fn main_window<'a>(app: &'a App, m: &Model) -> Ref<'a, Window> {
+    app.window(m.window).expect("the main window is open")
+}
 
-            draw.ellipse().xy(self.positions[i]).w_h(size, size).rgba(
-                self.colors[i].red,
-                self.colors[i].green,
-                self.colors[i].blue,
-                self.colors[i].alpha,
-            );
+/// Draws `backdrop` fitted into the main window, whatever the camera shows.
+fn draw_backdrop(backdrop: &Backdrop, app: &App, m: &Model, draw: &app::Draw) {
+    let (w, h) = main_window(app, m).rect().w_h();
+    let half = vec2(w, h) / 2.0;
+    let (vertices, triangles) = backdrop.grid((-half, half), IMAGE_CELLS);
+    let vertices = vertices
+        .into_iter()
+        .map(|(p, [r, g, b, a])| (pt2(p.x, p.y), Rgba::new(r, g, b, a)));
+    draw.mesh().indexed(vertices, triangles);
+}
+
+/// The trails as shown, with the bloom if there is one.
+fn shown_accumulation(m: &Model) -> Option<&Canvas> {
+    let accumulation = m.accumulation.as_ref()?;
+    Some(m.bloomed.as_ref().unwrap_or_else(|| accumulation.canvas()))
+}
+
+/// Draws the trails `canvas` where they were accumulated, moving along with the camera.
+fn draw_accumulation(canvas: &Canvas, camera: &Camera, draw: &app::Draw) {
+    let size = (canvas.width(), canvas.height());
+    let (vertices, triangles) = raster::image_grid(size, canvas.view_bounds(), IMAGE_CELLS, |uv| {
+        canvas.sample(uv)
+    });
+    let vertices = vertices
+        .into_iter()
+        .map(|(p, [r, g, b, a])| (camera.to_screen(p), Rgba::new(r, g, b, a)));
+    draw.mesh().indexed(vertices, triangles);
+}
+
+/// Draws the curves with the `[render]` shader into an image the size of the window, shown like
+/// the trails. Returns whether the shader drew them.
+fn draw_shaded(app: &App, m: &Model, positions: &[Vec2], draw: &app::Draw) -> bool {
+    if !m.shader.is_loaded() {
+        return false;
+    }
+    let (w, h) = main_window(app, m).rect().w_h();
+    let mut canvas = Canvas::new(w as usize, h as usize);
+    canvas.set_view(m.camera.position, m.camera.zoom);
+    canvas.pixels_mut().fill([0.0; 4]);
+    let (line, thickness) = (m.config.colors.line, &m.config.render.thickness);
+    if !m
+        .shader
+        .draw(&mut canvas, &m.ps, positions, line, thickness)
+    {
+        return false;
+    }
+
+    // Over transparent black, the colors are left multiplied by their alpha.
+    let size = (canvas.width(), canvas.height());
+    let (vertices, triangles) = raster::image_grid(size, canvas.view_bounds(), IMAGE_CELLS, |uv| {
+        let [r, g, b, a] = canvas.sample(uv);
+        let alpha = a.max(f32::EPSILON);
+        [r / alpha, g / alpha, b / alpha, a]
+    });
+    let vertices = vertices
+        .into_iter()
+        .map(|(p, [r, g, b, a])| (m.camera.to_screen(p), Rgba::new(r, g, b, a)));
+    draw.mesh().indexed(vertices, triangles);
+    true
+}
 
-            // draw.line()
-            //     .start(self.positions[i])
-            //     .end(self.positions[i] + self.pressures[i] * 2.0)
-            //     .thickness(thickness * 10.0)
-            //     .rgba(1.0, 0.3, 0.3, 1.0);
+/// Draws the whole scene with the `[post]` effects, which work on pixels, so it is rendered on
+/// the CPU at the size of the window like a screenshot and shown like the trails.
+fn draw_post_processed(app: &App, m: &Model, positions: &[Vec2], draw: &app::Draw) {
+    let (w, h) = main_window(app, m).rect().w_h();
+    let mut canvas = Canvas::new(w as usize, h as usize);
+    let shown = render(&mut canvas, m, positions, 1.0);
+    let size = (shown.width(), shown.height());
+    let (vertices, triangles) = raster::image_grid(size, shown.view_bounds(), IMAGE_CELLS, |uv| {
+        shown.sample(uv)
+    });
+    let vertices = vertices
+        .into_iter()
+        .map(|(p, [r, g, b, a])| (m.camera.to_screen(p), Rgba::new(r, g, b, a)));
+    draw.mesh().indexed(vertices, triangles);
+}
 
-            // draw.line()
-            //     .start(self.positions[i])
-            //     .end(self.positions[i] + self.attractions[i] * 2.0)
-            //     .thickness(thickness * 10.0)
-            //     .rgba(0.3, 1.0, 0.3, 1.0);
+fn draw_obstacles(m: &Model, draw: &app::Draw) {
+    for obstacle in &m.ps.obstacles {
+        let vertices = obstacle
+            .polygon
+            .iter()
+            .map(|&p| (m.camera.to_screen(p), Rgba::new(0.3, 0.3, 0.35, 1.0)));
+        draw.mesh()
+            .indexed(vertices, fill::triangulate(&obstacle.polygon));
+    }
 
-            // draw.line()
-            //     .start(self.positions[i])
-            //     .end(self.positions[i] + (self.attractions[i] + self.pressures[i]) * 2.0)
-            //     .thickness(thickness * 30.0)
-            //     .rgba(1.0, 1.0, 1.0, 1.0);
+    if let Some(corners) = &m.obstacle_draft {
+        for pair in corners.windows(2) {
+            draw.line()
+                .start(m.camera.to_screen(pair[0]))
+                .end(m.camera.to_screen(pair[1]))
+                .thickness(1.0)
+                .rgba(0.6, 0.6, 0.7, 1.0);
         }
+        for &corner in corners {
+            draw.ellipse()
+                .xy(m.camera.to_screen(corner))
+                .w_h(6.0, 6.0)
+                .rgba(0.6, 0.6, 0.7, 1.0);
+        }
+    }
+}
+
+fn fill_particle_system(
+    ps: &ParticleSystem,
+    positions: &[Vec2],
+    config: &Config,
+    camera: &Camera,
+    draw: &app::Draw,
+) {
+    let bounds = match ps.bounding_box() {
+        Some(bounds) => bounds,
+        None => return,
+    };
+
+    for polyline in ps
+        .polylines_at(positions)
+        .iter()
+        .filter(|polyline| polyline.closed)
+    {
+        let vertices = polyline.points.iter().map(|&p| {
+            let [r, g, b, a] = config.colors.fill.color_at(p, bounds);
+            (camera.to_screen(p), Rgba::new(r, g, b, a))
+        });
+        draw.mesh()
+            .indexed(vertices, fill::triangulate(&polyline.points));
     }
 }
 
+/// Loads the file given with `--config`, or `config.toml` if there is one, with the spawn
+/// settings overridden by the other arguments.
+fn load_config(args: &Args) -> (PathBuf, Config) {
+    let path = args.config.clone();
+    let mut config = match Config::load_or_default(&path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Failed to load {}, using defaults: {}", path.display(), err);
+            Config::default()
+        }
+    };
+    args.apply(&mut config);
+
+    (path, config)
+}
+
+/// Spawns the configured curves, falling back to the configured shape without any parameter maps
+/// or direction field if the SVG, font or images can't be loaded.
+fn new_particle_system(config: &Config, seed: u64) -> ParticleSystem {
+    config.new_particle_system(seed).unwrap_or_else(|err| {
+        eprintln!("Failed to set up the simulation: {}", err);
+        let mut config = config.clone();
+        config.spawn.svg = None;
+        config.spawn.text = None;
+        config.simulation.parameter_maps.clear();
+        config.simulation.direction_field = None;
+        config.new_particle_system(seed).unwrap()
+    })
+}
+
 fn model(app: &App) -> Model {
-    app.new_window()
-        .with_dimensions(800, 600)
+    let args = Args::parse(APP_NAME);
+    let (config_path, config) = load_config(&args);
+    let config_watcher = match ConfigWatcher::new(&config_path) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            eprintln!(
+                "Not watching {} for changes: {}",
+                config_path.display(),
+                err
+            );
+            None
+        }
+    };
+
+    let window = app
+        .new_window()
+        .with_dimensions(config.window.width, config.window.height)
         .view(view)
+        .key_pressed(key_pressed)
+        .mouse_pressed(mouse_pressed)
+        .mouse_released(mouse_released)
+        .mouse_moved(mouse_moved)
+        .mouse_wheel(mouse_wheel)
         .build()
         .unwrap();
 
-    // let (_w, h) = app.window_rect().w_h();
-    let mut ps = ParticleSystem::new();
-    let num_particles = 100;
-    let spawn_radius = 100.0;
-    ps.spawn_particles(num_particles, spawn_radius);
+    let debug = if args.debug_window {
+        let id = app
+            .new_window()
+            .with_dimensions(DEBUG_WINDOW_SIZE, DEBUG_WINDOW_SIZE)
+            .with_title("differential-lines debug")
+            .view(view_debug)
+            .key_pressed(debug_key_pressed)
+            .build()
+            .unwrap();
+        Some(DebugWindow {
+            id,
+            view: DebugView::Pressure,
+        })
+    } else {
+        None
+    };
+
+    let resumed = args
+        .resume
+        .as_ref()
+        .and_then(|path| match ParticleSystem::load_state(path) {
+            Ok(ps) => Some(ps),
+            Err(err) => {
+                eprintln!("Failed to resume from {}: {}", path.display(), err);
+                None
+            }
+        });
+    let mut ps =
+        resumed.unwrap_or_else(|| new_particle_system(&config, args.seed.unwrap_or_else(random)));
+    if args.gpu {
+        if let Err(err) = cli::enable_gpu(&mut ps) {
+            eprintln!("Computing on the CPU instead of the GPU: {}", err);
+        }
+    }
+    let seed = ps.seed();
+    println!("Seed: {}", seed);
+    let mut undo = Undo::new(config.undo.every, config.undo.depth);
+    if let Err(err) = undo.push(&ps) {
+        eprintln!("Not keeping states to undo: {}", err);
+        undo.depth = 0;
+    }
+    let timestep = FixedTimestep::new(config.simulation.steps_per_second);
+    let backdrop = load_backdrop(&config);
+    let mut shader = ShaderPass::default();
+    let shader_watcher = load_shader(&config, &mut shader);
+    let mut bloom = BloomPass::default();
+    if args.gpu {
+        if let Err(err) = cli::enable_gpu_bloom(&mut bloom) {
+            eprintln!("Blooming on the CPU instead of the GPU: {}", err);
+        }
+    }
+
+    let mut ui = match &config.window.font {
+        Some(font) => app.new_ui().default_font_path(font.clone()),
+        None => app.new_ui(),
+    }
+    .window(window)
+    .build()
+    .unwrap();
+    let hud_text = ui.generate_widget_id();
+
+    let metrics = args.metrics.as_ref().and_then(|path| {
+        match MetricsLog::create(path, config.metrics.every) {
+            Ok(metrics) => Some(metrics),
+            Err(err) => {
+                eprintln!("Failed to open {}: {}", path.display(), err);
+                None
+            }
+        }
+    });
+
+    let audio = start_audio(&config);
+    let midi = start_midi(&config);
+    let osc = start_osc(&config);
+
+    Model {
+        ps,
+        config,
+        config_path,
+        config_watcher,
+        seed,
+        paused: false,
+        timestep,
+        pending_steps: 0,
+        recording: None,
+        gif: None,
+        video: None,
+        ndi: None,
+        spout: None,
+        window,
+        debug,
+        next_frame_index: 0,
+        camera: Camera {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+        },
+        drag: None,
+        injection: None,
+        brushing: false,
+        brush_radius: 50.0,
+        obstacle_draft: None,
+        render_mode: RenderMode::Lines,
+        overlay: false,
+        history: None,
+        undo,
+        backdrop,
+        accumulation: None,
+        bloomed: None,
+        bloom,
+        shader,
+        shader_watcher,
+        metrics,
+        ui,
+        hud_text,
+        hud: false,
+        frame_rate: RateMeter::default(),
+        step_rate: RateMeter::default(),
+        audio,
+        audio_levels: AudioLevels::default(),
+        midi,
+        osc,
+        osc_parameters: Vec::new(),
+    }
+}
+
+fn key_pressed(app: &App, m: &mut Model, key: Key) {
+    match key {
+        Key::Space => m.paused = !m.paused,
+        Key::A => m.overlay = !m.overlay,
+        Key::Period if m.paused => m.pending_steps += 1,
+        Key::Back => restart(m),
+        Key::Z => {
+            let result = if app.keys.mods.shift {
+                m.undo.redo(&mut m.ps)
+            } else {
+                m.undo.undo(&mut m.ps)
+            };
+            match result {
+                Ok(true) => println!("Back at step {}", m.ps.steps()),
+                Ok(false) => println!("No state to go back to"),
+                Err(err) => eprintln!("Failed to restore the state: {}", err),
+            }
+        }
+        Key::RBracket | Key::LBracket => {
+            let factor = if key == Key::RBracket { 2.0 } else { 0.5 };
+            m.timestep.speed = (m.timestep.speed * factor).clamp(1.0 / 64.0, 64.0);
+            println!("Running at {}x speed", m.timestep.speed);
+        }
+        Key::K => {
+            m.seed = random();
+            m.ps.reseed(m.seed);
+            println!("Continuing with seed {}", m.seed);
+        }
+        Key::L => match &m.history {
+            Some(history) => {
+                let path = Path::new("output").join(format!("rings-{}.svg", m.ps.steps()));
+                let result =
+                    fs::create_dir_all("output").and_then(|()| fs::write(&path, history.to_svg()));
+                match result {
+                    Ok(()) => println!("Saved {}", path.display()),
+                    Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+                }
+            }
+            None => println!("Press H to start keeping outlines first"),
+        },
+        Key::B if app.keys.mods.shift => match shown_accumulation(m) {
+            Some(canvas) => {
+                let name = format!("accumulation-{}.png", m.ps.steps());
+                let path = Path::new("output").join(name);
+                match save_png(&m.config.post.applied(canvas, m.ps.steps()), &path) {
+                    Ok(()) => println!("Saved {}", path.display()),
+                    Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+                }
+            }
+            None => println!("Press B to start accumulating trails first"),
+        },
+        Key::B => {
+            m.bloomed = None;
+            m.accumulation = match m.accumulation.take() {
+                Some(_) => None,
+                None => {
+                    let (w, h) = main_window(app, m).rect().w_h();
+                    let config = &m.config.accumulation;
+                    let mut accumulation = Accumulation::new(
+                        (w * config.resolution) as usize,
+                        (h * config.resolution) as usize,
+                        m.camera.position,
+                        m.camera.zoom * config.resolution,
+                        config.decay,
+                        m.config.colors.background,
+                    );
+                    accumulation.blend_mode = config.blend;
+                    Some(accumulation)
+                }
+            }
+        }
+        Key::H => {
+            m.history = match m.history.take() {
+                Some(_) => None,
+                None => {
+                    let mut history = History::new(m.config.history.every, m.config.history.depth);
+                    history.push(&m.ps);
+                    Some(history)
+                }
+            }
+        }
+        Key::E if app.keys.mods.shift => {
+            let path = Path::new("output").join(format!("diff-lines-{}.obj", m.ps.steps()));
+            let mesh = cli::mesh_of(&m.ps, &m.config, m.history.as_ref());
+            let result =
+                fs::create_dir_all("output").and_then(|()| fs::write(&path, mesh.to_obj()));
+            match result {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+            }
+        }
+        Key::D if app.keys.mods.shift => {
+            let path = Path::new("output").join(format!("diff-lines-{}.gcode", m.ps.steps()));
+            let result = fs::create_dir_all("output")
+                .and_then(|()| fs::write(&path, m.ps.to_gcode(&m.config.gcode)));
+            match result {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+            }
+        }
+        Key::D => {
+            let path = Path::new("output").join(format!("diff-lines-{}.dxf", m.ps.steps()));
+            let result = fs::create_dir_all("output")
+                .and_then(|()| fs::write(&path, m.ps.to_dxf(&m.config.dxf)));
+            match result {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+            }
+        }
+        Key::E => {
+            let path = Path::new("output").join(format!("diff-lines-{}.svg", m.ps.steps()));
+            match export_svg(&m.ps, &path) {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+            }
+        }
+        Key::S if !app.keys.mods.shift => screenshot(app, m),
+        Key::P => export_image(m),
+        Key::S => {
+            let path = Path::new("output").join(format!("state-{}.msgpack", m.ps.steps()));
+            match m.ps.save_state(&path) {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+            }
+        }
+        Key::C => {
+            m.ps.attractors.clear();
+            m.ps.obstacles.clear();
+        }
+        Key::O => match m.obstacle_draft.take() {
+            Some(corners) if corners.len() >= 3 => m.ps.obstacles.push(Obstacle::new(corners)),
+            Some(_) => println!("Obstacles need at least three corners"),
+            None => {
+                m.obstacle_draft = Some(Vec::new());
+                println!("Click to place the corners of an obstacle, press O again to finish");
+            }
+        },
+        Key::X => m.ps.prevent_crossings = !m.ps.prevent_crossings,
+        Key::I => m.hud = !m.hud,
+        Key::M => {
+            m.render_mode = match m.render_mode {
+                RenderMode::Lines => RenderMode::Filled,
+                RenderMode::Filled => RenderMode::Density,
+                RenderMode::Density => RenderMode::Lines,
+            }
+        }
+        Key::F => {
+            if let Some(bounds) = m.ps.bounding_box() {
+                let (w, h) = main_window(app, m).rect().w_h();
+                m.camera.fit(bounds, vec2(w, h));
+            }
+        }
+        Key::R => {
+            if m.recording.take().is_some() {
+                println!("Stopped recording");
+            } else {
+                let (w, h) = main_window(app, m).inner_size_points();
+                let canvas = Canvas::new(w as usize, h as usize);
+                m.recording = Some(Recording { canvas });
+                println!("Recording frames to frames/");
+            }
+        }
+        Key::G => match m.gif.take() {
+            Some(gif) => save_gif(&gif, m.ps.steps()),
+            None => {
+                let (w, h) = main_window(app, m).inner_size_points();
+                let scale = m.config.gif.scale;
+                let canvas = Canvas::new((w * scale) as usize, (h * scale) as usize);
+                let recorder = GifRecorder::new(m.config.gif.frame_ms);
+                m.gif = Some(GifCapture { canvas, recorder });
+                println!("Capturing a GIF, press G again to save it");
+            }
+        },
+        Key::V => match m.video.take() {
+            Some(video) => finish_video(video),
+            None => {
+                // Encoders working on 4:2:0 chroma need even dimensions.
+                let (w, h) = main_window(app, m).inner_size_points();
+                let (w, h) = (w as usize & !1, h as usize & !1);
+                let path = Path::new("output").join(format!("diff-lines-{}.mp4", m.ps.steps()));
+                match VideoRecorder::start(&path, w, h, &m.config.video) {
+                    Ok(recorder) => {
+                        let canvas = Canvas::new(w, h);
+                        m.video = Some(VideoCapture {
+                            canvas,
+                            recorder,
+                            path,
+                        });
+                        println!("Recording a video, press V again to stop");
+                    }
+                    Err(err) => eprintln!("Failed to start {}: {}", m.config.video.ffmpeg, err),
+                }
+            }
+        },
+        Key::N if app.keys.mods.shift => match m.spout.take() {
+            Some(_) => println!("Stopped sharing through Spout"),
+            None => {
+                let spout = &m.config.spout;
+                match SpoutSender::start(&spout.name, spout.library.as_deref()) {
+                    Ok(sender) => {
+                        let (w, h) = main_window(app, m).inner_size_points();
+                        let canvas = Canvas::new(w as usize, h as usize);
+                        m.spout = Some(SpoutCapture { canvas, sender });
+                        println!(
+                            "Sharing through Spout as {}, press Shift+N again to stop",
+                            spout.name
+                        );
+                    }
+                    Err(err) => eprintln!("Failed to share through Spout: {}", err),
+                }
+            }
+        },
+        Key::N => match m.ndi.take() {
+            Some(_) => println!("Stopped sending over NDI"),
+            None => {
+                let ndi = &m.config.ndi;
+                match NdiSender::start(&ndi.name, ndi.library.as_deref(), ndi.fps) {
+                    Ok(sender) => {
+                        let (w, h) = main_window(app, m).inner_size_points();
+                        let canvas = Canvas::new(w as usize, h as usize);
+                        m.ndi = Some(NdiCapture { canvas, sender });
+                        println!("Sending over NDI as {}, press N again to stop", ndi.name);
+                    }
+                    Err(err) => eprintln!("Failed to send over NDI: {}", err),
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+fn debug_key_pressed(_app: &App, m: &mut Model, key: Key) {
+    if let (Key::Tab, Some(debug)) = (key, &mut m.debug) {
+        debug.view = debug.view.next();
+        println!("Debug view: {:?}", debug.view);
+    }
+}
+
+fn mouse_pressed(app: &App, m: &mut Model, button: MouseButton) {
+    let mouse = vec2(app.mouse.x, app.mouse.y);
+    match button {
+        MouseButton::Left if app.keys.mods.shift => {
+            m.ps.inject_at(m.camera.to_world(mouse), INJECT_RADIUS / m.camera.zoom);
+            m.injection = Some(mouse);
+        }
+        MouseButton::Left => {
+            m.drag = Some(Drag {
+                start: mouse,
+                last: mouse,
+            })
+        }
+        MouseButton::Right if !app.keys.mods.shift => m.brushing = true,
+        MouseButton::Right => {
+            let position = m.camera.to_world(mouse);
+            m.ps.attractors.push(Attractor::new(
+                position,
+                -ATTRACTOR_STRENGTH,
+                ATTRACTOR_FALLOFF,
+            ));
+        }
+        MouseButton::Middle => {
+            let position = m.camera.to_world(mouse);
+            let pinned = !app.keys.mods.shift;
+            m.ps.set_pinned_near(position, PIN_RADIUS / m.camera.zoom, pinned);
+        }
+        _ => {}
+    }
+}
+
+fn mouse_released(_app: &App, m: &mut Model, button: MouseButton) {
+    if button == MouseButton::Right {
+        m.brushing = false;
+    }
+    if button != MouseButton::Left {
+        return;
+    }
+
+    m.injection = None;
+    if let Some(drag) = m.drag.take() {
+        if drag.is_click() {
+            let position = m.camera.to_world(drag.start);
+            match &mut m.obstacle_draft {
+                Some(corners) => corners.push(position),
+                None => m.ps.attractors.push(Attractor::new(
+                    position,
+                    ATTRACTOR_STRENGTH,
+                    ATTRACTOR_FALLOFF,
+                )),
+            }
+        }
+    }
+}
+
+fn mouse_moved(_app: &App, m: &mut Model, position: Point2) {
+    let position = vec2(position.x, position.y);
+
+    if let Some(drag) = &mut m.drag {
+        let delta = position - drag.last;
+        drag.last = position;
+        if !drag.is_click() {
+            m.camera.position -= delta / m.camera.zoom;
+        }
+    }
+
+    if let Some(last) = m.injection {
+        if (position - last).length() >= INJECT_SPACING {
+            m.ps.inject_at(m.camera.to_world(position), INJECT_RADIUS / m.camera.zoom);
+            m.injection = Some(position);
+        }
+    }
+}
+
+fn mouse_wheel(app: &App, m: &mut Model, delta: MouseScrollDelta, _phase: TouchPhase) {
+    let lines = match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(p) => p.y as f32 / 20.0,
+    };
+
+    if m.brushing {
+        m.brush_radius = (m.brush_radius * 1.1f32.powf(lines)).clamp(5.0, 1000.0);
+        return;
+    }
+
+    let mouse = vec2(app.mouse.x, app.mouse.y);
+    m.camera.zoom_at(mouse, 1.1f32.powf(lines));
+}
+
+fn export_svg(ps: &ParticleSystem, path: &Path) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, ps.to_svg())
+}
+
+/// Renders the current frame at the window's full resolution to
+/// `output/diff-lines-<timestamp>-seed<seed>.png`.
+fn screenshot(app: &App, m: &Model) {
+    let window = main_window(app, m);
+    let (w, h) = window.inner_size_pixels();
+    let (points, _) = window.inner_size_points();
+    let mut canvas = Canvas::new(w as usize, h as usize);
+    let shown = render(&mut canvas, m, &shown_positions(m), w as f32 / points);
+
+    let name = format!("diff-lines-{}-seed{}.png", timestamp(), m.seed);
+    let path = Path::new("output").join(name);
+    match save_png(&shown, &path) {
+        Ok(()) => println!("Saved {}", path.display()),
+        Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+    }
+}
+
+/// Re-renders the whole growth at the `[export]` size, whatever the window size, or a seamless
+/// tile of the torus as wide as that, to `output/diff-lines-<steps>-<width>x<height>.png`.
+fn export_image(m: &Model) {
+    let (width, height) = (m.config.export.width, m.config.export.height);
+    let image = match &m.ps.torus {
+        Some(torus) => {
+            let background = m.config.colors.background;
+            raster::render_seamless(&m.ps, torus, width, background, |canvas| {
+                draw_growth(canvas, m, m.ps.positions())
+            })
+        }
+        None => {
+            let bounds = m.ps.bounding_box().unwrap_or((Vec2::ZERO, Vec2::ZERO));
+            let (center, scale) = raster::fit_view(bounds, width, height, 20.0);
+            let frame = raster::view_bounds(center, scale, width, height);
+            let steps = m.ps.steps();
+            cli::render_tiled_post(&m.config, width, height, center, scale, steps, |canvas| {
+                canvas.fill(m.config.colors.background);
+                if m.config.backdrop.export {
+                    if let Some(backdrop) = &m.backdrop {
+                        canvas.draw_backdrop(backdrop, frame);
+                    }
+                }
+                draw_growth(canvas, m, m.ps.positions())
+            })
+        }
+    };
+
+    let (width, height) = image.dimensions();
+    let name = format!("diff-lines-{}-{}x{}.png", m.ps.steps(), width, height);
+    let path = Path::new("output").join(name);
+    let saved = fs::create_dir_all("output")
+        .map_err(Box::<dyn Error>::from)
+        .and_then(|()| Ok(image.save(&path)?));
+    match saved {
+        Ok(()) => println!("Saved {}", path.display()),
+        Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+    }
+}
+
+/// The current UTC time as `YYYYMMDD-HHMMSS`, which sorts chronologically.
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // Converts days since 1970-01-01 to a date in the proleptic Gregorian calendar, working in
+    // 400 year eras that start on March 1st so leap days fall at the end of a year.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn save_png(canvas: &Canvas, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    canvas.save_png(path)?;
+    Ok(())
+}
+
+/// Re-reads the configuration file, applying new parameters to the running simulation. The
+/// geometry is only reset when the spawn settings changed.
+fn reload_config(app: &App, m: &mut Model) {
+    let config = match Config::load(&m.config_path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Failed to reload {}: {}", m.config_path.display(), err);
+            return;
+        }
+    };
+
+    if config.simulation != m.config.simulation {
+        m.osc_parameters.clear();
+        config.simulation.apply(&mut m.ps);
+        m.timestep
+            .set_steps_per_second(config.simulation.steps_per_second);
+    }
+    if config.simulation.parameter_maps != m.config.simulation.parameter_maps {
+        match config.simulation.load_parameter_maps() {
+            Ok(maps) => m.ps.parameter_maps = maps,
+            Err(err) => eprintln!("Failed to load the parameter maps: {}", err),
+        }
+    }
+    if config.simulation.script != m.config.simulation.script {
+        if let Err(err) = config.simulation.load_script(&mut m.ps) {
+            eprintln!("Failed to load the script: {}", err);
+        }
+    }
+    if config.simulation.direction_field != m.config.simulation.direction_field {
+        match config.simulation.load_direction_field() {
+            Ok(field) => m.ps.direction_field = field,
+            Err(err) => eprintln!("Failed to load the direction field: {}", err),
+        }
+    }
+    if config.spawn != m.config.spawn {
+        m.ps = new_particle_system(&config, m.seed);
+        if let Some(history) = &mut m.history {
+            history.clear();
+        }
+        m.undo.clear();
+        record_undo(m);
+        if let Some(accumulation) = &mut m.accumulation {
+            accumulation.clear();
+        }
+        m.bloomed = None;
+    }
+    if let Some(history) = &mut m.history {
+        history.every = config.history.every;
+        history.depth = config.history.depth;
+    }
+    m.undo.every = config.undo.every;
+    m.undo.depth = config.undo.depth;
+    if let Some(accumulation) = &mut m.accumulation {
+        accumulation.decay = config.accumulation.decay;
+        accumulation.blend_mode = config.accumulation.blend;
+    }
+    if config.accumulation.bloom.is_none() {
+        m.bloomed = None;
+    }
+    if config.backdrop != m.config.backdrop {
+        m.backdrop = load_backdrop(&config);
+    }
+    if config.render.shader != m.config.render.shader {
+        m.shader_watcher = load_shader(&config, &mut m.shader);
+    }
+    let audio = (&config.audio.device, config.audio.window);
+    if audio != (&m.config.audio.device, m.config.audio.window)
+        || config.audio.mappings.is_empty() != m.config.audio.mappings.is_empty()
+    {
+        // Dropped first, so that the device is free to open again.
+        m.audio = None;
+        m.audio = start_audio(&config);
+    }
+    if config.midi.port != m.config.midi.port
+        || config.midi.mappings.is_empty() != m.config.midi.mappings.is_empty()
+    {
+        m.midi = None;
+        m.midi = start_midi(&config);
+    }
+    if config.osc != m.config.osc {
+        m.osc = None;
+        m.osc = start_osc(&config);
+    }
+    if config.window != m.config.window {
+        let (w, h) = (config.window.width, config.window.height);
+        main_window(app, m).set_inner_size_points(w as f32, h as f32);
+    }
+
+    m.config = config;
+    println!("Reloaded {}", m.config_path.display());
+}
+
+fn update(app: &App, m: &mut Model, update: Update) {
+    let config_changed = m
+        .config_watcher
+        .as_ref()
+        .map_or(false, |watcher| watcher.changed());
+    if config_changed {
+        reload_config(app, m);
+    }
+    let shader_changed = m
+        .shader_watcher
+        .as_ref()
+        .map_or(false, |watcher| watcher.changed());
+    if let (true, Some(path)) = (shader_changed, &m.config.render.shader) {
+        reload_shader(&mut m.shader, path);
+    }
+
+    let commands = m.osc.as_ref().map(OscServer::receive).unwrap_or_default();
+    for command in commands {
+        run_osc_command(m, command);
+    }
+    if let Some(audio) = &m.audio {
+        m.audio_levels = m
+            .audio_levels
+            .smoothed(audio.levels(), m.config.audio.smoothing);
+    }
+
+    let steps = if m.paused {
+        let steps = m.pending_steps.min(1);
+        m.pending_steps -= steps;
+        steps
+    } else if m.config.simulation.steps_per_frame > 0 {
+        m.timestep
+            .advance_frame(m.config.simulation.steps_per_frame)
+    } else {
+        m.timestep.advance(update.since_last)
+    };
+
+    // The brush is a repulsor following the cursor, only there for the steps it is held during.
+    if m.brushing {
+        let position = m.camera.to_world(vec2(app.mouse.x, app.mouse.y));
+        let falloff = m.brush_radius / m.camera.zoom;
+        m.ps.attractors
+            .push(Attractor::new(position, -BRUSH_STRENGTH, falloff));
+    }
+    for _ in 0..steps {
+        step(m);
+    }
+    if m.brushing {
+        m.ps.attractors.pop();
+    }
+    if steps > 0 {
+        if let Some(mut accumulation) = m.accumulation.take() {
+            accumulation.fade(steps);
+            let render = &m.config.render;
+            accumulation.draw(|canvas| {
+                canvas.draw_edges_at(
+                    &m.ps,
+                    m.ps.positions(),
+                    m.config.colors.line,
+                    &render.thickness,
+                    render.subdivisions,
+                )
+            });
+            m.bloomed = m.config.accumulation.bloom.map(|bloom| {
+                let mut canvas = accumulation.canvas().clone();
+                m.bloom.apply(&bloom, &mut canvas);
+                canvas
+            });
+            m.accumulation = Some(accumulation);
+        }
+    }
+
+    if let Some(mut ndi) = m.ndi.take() {
+        ndi.sender
+            .send(&render(&mut ndi.canvas, m, &shown_positions(m), 1.0));
+        m.ndi = Some(ndi);
+    }
+    if let Some(mut spout) = m.spout.take() {
+        let sent = spout
+            .sender
+            .send(&render(&mut spout.canvas, m, &shown_positions(m), 1.0));
+        match sent {
+            Ok(()) => m.spout = Some(spout),
+            Err(err) => eprintln!("Stopped sharing through Spout: {}", err),
+        }
+    }
+
+    m.frame_rate.record(1, update.since_last);
+    m.step_rate.record(steps as u64, update.since_last);
+    update_hud(m);
+}
+
+/// Lays out the statistics overlay, which is only drawn when set during the latest update.
+fn update_hud(m: &mut Model) {
+    let ui = &mut m.ui.set_widgets();
+    if !m.hud {
+        return;
+    }
+
+    let stats = Stats::of(&m.ps);
+    let text = format!(
+        "particles     {}\nedges         {}\nfps           {:.1}\nsteps/s       {:.1}\n\
+         edge length   {:.3}\npressure      {:.3} mean, {:.3} p90, {:.3} max\nseed          {}",
+        stats.particles,
+        stats.edges,
+        m.frame_rate.rate,
+        m.step_rate.rate,
+        stats.mean_edge_length,
+        stats.pressure.mean,
+        stats.pressure.p90,
+        stats.pressure.max,
+        m.seed
+    );
+    widget::Text::new(&text)
+        .top_left_with_margin(10.0)
+        .font_size(14)
+        .left_justify()
+        .rgba(1.0, 1.0, 1.0, 0.8)
+        .set(m.hud_text, ui);
+}
+
+fn save_gif(gif: &GifCapture, steps: u64) {
+    let path = Path::new("output").join(format!("diff-lines-{}.gif", steps));
+    println!("Encoding {} frames...", gif.recorder.len());
+    match gif.recorder.save(&path) {
+        Ok(()) => println!("Saved {}", path.display()),
+        Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+    }
+}
+
+fn finish_video(video: VideoCapture) {
+    match video.recorder.finish() {
+        Ok(status) if status.success() => println!("Saved {}", video.path.display()),
+        Ok(status) => eprintln!("Failed to save {}: ffmpeg {}", video.path.display(), status),
+        Err(err) => eprintln!("Failed to save {}: {}", video.path.display(), err),
+    }
+}
+
+/// Draws the simulation onto a CPU canvas the way `view()` draws it to the window, with the
+/// canvas `scale` times the size of the window and the particles at `positions`, and returns it
+/// with the `[post]` effects.
+fn render<'a>(
+    canvas: &'a mut Canvas,
+    m: &Model,
+    positions: &[Vec2],
+    scale: f32,
+) -> Cow<'a, Canvas> {
+    canvas.set_view(m.camera.position, m.camera.zoom * scale);
+    draw_scene(canvas, m, positions);
+    m.config.post.applied(canvas, m.ps.steps())
+}
+
+/// Draws the simulation onto a CPU canvas through whatever view it already has.
+fn draw_scene(canvas: &mut Canvas, m: &Model, positions: &[Vec2]) {
+    canvas.fill(m.config.colors.background);
+    if let Some(backdrop) = &m.backdrop {
+        canvas.draw_backdrop(backdrop, canvas.view_bounds());
+    }
+    draw_growth(canvas, m, positions);
+}
+
+/// Draws everything `draw_scene()` does over the background.
+fn draw_growth(canvas: &mut Canvas, m: &Model, positions: &[Vec2]) {
+    if let Some(history) = &m.history {
+        canvas.draw_history(history, m.config.colors.history, m.config.history.thickness);
+    }
+    for obstacle in &m.ps.obstacles {
+        canvas.fill_polygon(&obstacle.polygon, [0.3, 0.3, 0.35, 1.0]);
+    }
+    match m.render_mode {
+        RenderMode::Lines => {
+            let render = &m.config.render;
+            let line = m.config.colors.line;
+            if !m
+                .shader
+                .draw(canvas, &m.ps, positions, line, &render.thickness)
+            {
+                canvas.draw_particle_system_at(
+                    &m.ps,
+                    positions,
+                    line,
+                    &render.thickness,
+                    render.subdivisions,
+                );
+            }
+        }
+        RenderMode::Filled => {
+            canvas.fill_particle_system_at(&m.ps, positions, &m.config.colors.fill)
+        }
+        RenderMode::Density => {
+            for transform in m.ps.symmetry_transforms() {
+                for (i, next) in m.ps.links().edges() {
+                    let (r, g, b) = heat(crowding(&m.ps, i, next));
+                    let (start, end) = m.ps.edge_at(positions, i, next);
+                    let color = [r, g, b, 1.0];
+                    canvas.line(
+                        transform * start,
+                        transform * end,
+                        UNIFORM_THICKNESS * 2.0,
+                        color,
+                    );
+                }
+                for i in m.ps.particle_indices() {
+                    let (r, g, b) = heat(crowding(&m.ps, i, i));
+                    let p = transform * positions[i];
+                    canvas.disc(p, m.ps.particle_radius / 2.0, [r, g, b, 1.0]);
+                }
+            }
+        }
+    }
+}
 
-    Model { ps }
+/// Compiles the `[render]` shader, if there is one, and watches it for edits.
+fn load_shader(config: &Config, shader: &mut ShaderPass) -> Option<ConfigWatcher> {
+    shader.unload();
+    let path = config.render.shader.as_ref()?;
+    reload_shader(shader, path);
+    match ConfigWatcher::new(path) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            eprintln!("Not watching {} for changes: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Compiles the shader at `path` again, drawing with the one so far if it fails.
+fn reload_shader(shader: &mut ShaderPass, path: &Path) {
+    match cli::load_shader(shader, path) {
+        Ok(()) => println!("Loaded {}", path.display()),
+        Err(err) => eprintln!("Failed to load {}: {}", path.display(), err),
+    }
 }
 
-fn update(_app: &App, m: &mut Model, _update: Update) {
+/// Loads the `[backdrop]` image, if there is one.
+fn load_backdrop(config: &Config) -> Option<Backdrop> {
+    config.backdrop.load().unwrap_or_else(|err| {
+        eprintln!("Failed to load the backdrop: {}", err);
+        None
+    })
+}
+
+/// Starts listening for the `[audio]` mappings, if there are any.
+fn start_audio(config: &Config) -> Option<AudioInput> {
+    if config.audio.mappings.is_empty() {
+        return None;
+    }
+    match AudioInput::start(config.audio.device.as_deref(), config.audio.window) {
+        Ok(audio) => Some(audio),
+        Err(err) => {
+            eprintln!("Not listening to audio: {}", err);
+            None
+        }
+    }
+}
+
+/// Connects to the MIDI controller for the `[midi]` mappings, if there are any.
+fn start_midi(config: &Config) -> Option<MidiInput> {
+    if config.midi.mappings.is_empty() {
+        return None;
+    }
+    match MidiInput::start(config.midi.port.as_deref()) {
+        Ok(midi) => Some(midi),
+        Err(err) => {
+            eprintln!("Not reading MIDI: {}", err);
+            None
+        }
+    }
+}
+
+/// Listens for OSC messages on the `[osc]` port, if there is one.
+fn start_osc(config: &Config) -> Option<OscServer> {
+    let port = config.osc.port?;
+    match OscServer::bind(port, &config.osc.prefix) {
+        Ok(osc) => {
+            println!("Listening for OSC on port {}", port);
+            Some(osc)
+        }
+        Err(err) => {
+            eprintln!("Not listening for OSC: {}", err);
+            None
+        }
+    }
+}
+
+fn run_osc_command(m: &mut Model, command: OscCommand) {
+    match command {
+        OscCommand::Set { parameter, value } => {
+            // Checked on its own, so that a typo doesn't stop the other overrides.
+            let check = [(parameter.as_str(), value)];
+            if let Err(err) = m.config.simulation.scheduled(m.ps.time(), &check) {
+                eprintln!("Ignoring OSC for {}: {}", parameter, err);
+                return;
+            }
+            m.osc_parameters.retain(|(p, _)| *p != parameter);
+            m.osc_parameters.push((parameter, value));
+        }
+        OscCommand::Reset => restart(m),
+        OscCommand::Pause(Some(paused)) => m.paused = paused,
+        OscCommand::Pause(None) => m.paused = !m.paused,
+    }
+}
+
+/// Starts the simulation over from the spawn shape, with the same seed.
+fn restart(m: &mut Model) {
+    m.ps = new_particle_system(&m.config, m.seed);
+    if let Some(history) = &mut m.history {
+        history.clear();
+    }
+    m.undo.clear();
+    record_undo(m);
+    if let Some(accumulation) = &mut m.accumulation {
+        accumulation.clear();
+    }
+    m.bloomed = None;
+}
+
+/// Keeps the current state to step back to, giving up on undoing if encoding it fails.
+fn record_undo(m: &mut Model) {
+    if let Err(err) = m.undo.push(&m.ps) {
+        eprintln!("Stopped keeping states to undo: {}", err);
+        m.undo.depth = 0;
+    }
+}
+
+/// Advances the simulation by one step, recording the result if needed.
+fn step(m: &mut Model) {
+    let mut overrides = m.config.audio.overrides(&m.audio_levels);
+    if let Some(midi) = &m.midi {
+        overrides.extend(m.config.midi.overrides(&midi.controls()));
+    }
+    overrides.extend(
+        m.osc_parameters
+            .iter()
+            .map(|(parameter, value)| (parameter.as_str(), *value)),
+    );
+    if let Err(err) = m.config.simulation.apply_schedules(&mut m.ps, &overrides) {
+        // Reported once, until the config is fixed and reloaded.
+        eprintln!(
+            "Stopped following the schedules, audio, MIDI and OSC: {}",
+            err
+        );
+        m.config.simulation.schedules.clear();
+        m.config.audio.mappings.clear();
+        m.config.midi.mappings.clear();
+        m.osc_parameters.clear();
+        m.audio = None;
+        m.midi = None;
+    }
+    let start = Instant::now();
     m.ps.update();
+    if let Err(err) = cli::check_script(&mut m.ps) {
+        eprintln!("{}", err);
+    }
+    if let Some(metrics) = &mut m.metrics {
+        if let Err(err) = metrics.record(&m.ps, start.elapsed()) {
+            eprintln!("Stopped logging metrics: {}", err);
+            m.metrics = None;
+        }
+    }
+    if let Some(history) = &mut m.history {
+        history.record(&m.ps);
+    }
+    if let Err(err) = m.undo.record(&m.ps) {
+        eprintln!("Stopped keeping states to undo: {}", err);
+        m.undo.depth = 0;
+    }
+
+    if let Some(mut recording) = m.recording.take() {
+        let path = Path::new("frames").join(format!("{:04}.png", m.next_frame_index));
+        let saved = save_png(
+            &render(&mut recording.canvas, m, m.ps.positions(), 1.0),
+            &path,
+        );
+        match saved {
+            Ok(()) => {
+                m.next_frame_index += 1;
+                m.recording = Some(recording);
+            }
+            Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+        }
+    }
+
+    if m.ps.steps() % m.config.gif.every.max(1) == 0 {
+        if let Some(mut gif) = m.gif.take() {
+            let scale = m.config.gif.scale;
+            gif.recorder
+                .add_frame(&render(&mut gif.canvas, m, m.ps.positions(), scale));
+            m.gif = Some(gif);
+        }
+    }
+
+    if let Some(mut video) = m.video.take() {
+        let added = video
+            .recorder
+            .add_frame(&render(&mut video.canvas, m, m.ps.positions(), 1.0));
+        match added {
+            Ok(()) => m.video = Some(video),
+            Err(err) => {
+                eprintln!("Stopped recording {}: {}", video.path.display(), err);
+                finish_video(video);
+            }
+        }
+    }
+}
+
+/// Saves a GIF or video that is still being captured when the window is closed.
+fn exit(_app: &App, m: Model) {
+    if let Some(gif) = &m.gif {
+        save_gif(gif, m.ps.steps());
+    }
+    if let Some(video) = m.video {
+        finish_video(video);
+    }
 }
 
 fn view(app: &App, m: &Model, frame: Frame) -> Frame {
-    let draw = app.draw();
-    draw.background().color(Rgba::new(0.01, 0.01, 0.01, 0.2));
-    // draw.rect().w_h(1280.0, 720.0).rgba(0.01, 0.01, 0.01, 0.09);
+    let draw = app
+        .draw_for_window(frame.window_id())
+        .expect("the window being drawn is open");
+    let [r, g, b, a] = m.config.colors.background;
+    draw.background().color(Rgba::new(r, g, b, a));
+    let positions = shown_positions(m);
+    if !m.config.post.is_empty() && shown_accumulation(m).is_none() {
+        draw_post_processed(app, m, &positions, &draw);
+    } else {
+        if let Some(backdrop) = &m.backdrop {
+            draw_backdrop(backdrop, app, m, &draw);
+        }
+        // draw.rect().w_h(1280.0, 720.0).rgba(0.01, 0.01, 0.01, 0.09);
+
+        draw_obstacles(m, &draw);
+        if let Some(history) = &m.history {
+            draw_history(history, &m.config, &m.camera, &draw);
+        }
+        match (shown_accumulation(m), m.render_mode) {
+            (Some(canvas), _) => {
+                let shown = m.config.post.applied(canvas, m.ps.steps());
+                draw_accumulation(&shown, &m.camera, &draw)
+            }
+            (None, RenderMode::Lines) => {
+                if !draw_shaded(app, m, &positions, &draw) {
+                    draw_particle_system(&m.ps, &positions, &m.config, &m.camera, &draw)
+                }
+            }
+            (None, RenderMode::Filled) => {
+                fill_particle_system(&m.ps, &positions, &m.config, &m.camera, &draw)
+            }
+            (None, RenderMode::Density) => draw_density(&m.ps, &positions, &m.camera, &draw),
+        }
+    }
+    if m.overlay {
+        draw_forces(&m.ps, &positions, &m.config, &m.camera, &draw);
+    }
 
-    m.ps.draw(&draw);
+    if m.brushing {
+        let diameter = m.brush_radius * 2.0;
+        draw.ellipse()
+            .x_y(app.mouse.x, app.mouse.y)
+            .w_h(diameter, diameter)
+            .rgba(1.0, 0.3, 0.3, 0.15);
+    }
 
     draw.to_frame(app, &frame).unwrap();
+    m.ui.draw_to_frame(app, &frame).unwrap();
+
+    frame
+}
+
+/// Where the particles are drawn in the window: part of the way from where they were before the
+/// latest step to where they are now when interpolating, which lags a step behind but moves
+/// smoothly when there are fewer steps than frames.
+fn shown_positions(m: &Model) -> Cow<'_, [Vec2]> {
+    if !m.config.simulation.interpolate || m.paused {
+        return Cow::Borrowed(m.ps.positions());
+    }
+    Cow::Owned(m.ps.interpolated_positions(m.timestep.fraction()))
+}
+
+/// Draws the current debug view, always fitted to the whole growth.
+fn view_debug(app: &App, m: &Model, frame: Frame) -> Frame {
+    let debug = match &m.debug {
+        Some(debug) => debug,
+        None => return frame,
+    };
+    let (w, h) = match app.window(debug.id) {
+        Some(window) => window.inner_size_points(),
+        None => return frame,
+    };
+    let mut camera = Camera {
+        position: Vec2::ZERO,
+        zoom: 1.0,
+    };
+    if let Some(bounds) = m.ps.bounding_box() {
+        camera.fit(bounds, vec2(w, h));
+    }
+
+    let draw = app
+        .draw_for_window(frame.window_id())
+        .expect("the window being drawn is open");
+    draw.background().rgb(0.05, 0.05, 0.05);
+    let positions = m.ps.positions();
+    let segments = m.ps.links().edges().map(|(i, next)| {
+        let (start, end) = m.ps.edge(i, next);
+        (start, end, Rgba::new(1.0, 1.0, 1.0, 0.2))
+    });
+    draw_segments(segments, 1.0 / camera.zoom, &camera, &draw);
+
+    match debug.view {
+        DebugView::Pressure => {
+            let pressures = m.ps.pressures();
+            let scale = DEBUG_VECTOR_LENGTH / m.ps.pressure_summary().p95.max(f32::EPSILON);
+            for i in m.ps.particle_indices() {
+                let end = positions[i] + pressures[i] * scale / camera.zoom;
+                draw.line()
+                    .start(camera.to_screen(positions[i]))
+                    .end(camera.to_screen(end))
+                    .thickness(1.0)
+                    .rgba(1.0, 0.3, 0.3, 0.8);
+            }
+        }
+        DebugView::Neighbors => {
+            let num_neighbors = m.ps.num_neighbors();
+            let most = m.ps.particle_indices().map(|i| num_neighbors[i]).max();
+            let most = most.unwrap_or(1).max(1) as f32;
+            let size = (m.ps.particle_radius * camera.zoom).max(3.0) / camera.zoom;
+            let particles: Vec<Instance> =
+                m.ps.particle_indices()
+                    .map(|i| {
+                        let (r, g, b) = heat(num_neighbors[i] as f32 / most);
+                        Instance {
+                            position: positions[i],
+                            size,
+                            color: [r, g, b, 1.0],
+                            index: i,
+                        }
+                    })
+                    .collect();
+            draw_instances(&particles, &camera, &draw);
+        }
+        DebugView::Grid => {
+            let alive: Vec<Vec2> = m.ps.particle_indices().map(|i| positions[i]).collect();
+            let mut grid = SpatialGrid::default();
+            grid.rebuild(m.ps.influence_radius, &alive);
+            let cell_size = grid.cell_size();
+            let cells: Vec<(Vec2, usize)> = grid.occupied_cells().collect();
+            let most = cells.iter().map(|&(_, count)| count).max().unwrap_or(1);
+            for (corner, count) in cells {
+                let (r, g, b) = heat(count as f32 / most as f32);
+                draw.rect()
+                    .xy(camera.to_screen(corner + Vec2::splat(cell_size / 2.0)))
+                    .w_h(cell_size * camera.zoom, cell_size * camera.zoom)
+                    .rgba(r, g, b, 0.6);
+            }
+        }
+    }
 
+    draw.to_frame(app, &frame).unwrap();
     frame
 }
+
+/// A color ramp from blue through green to red for `t` from 0 to 1, for the debug views.
+fn heat(t: f32) -> (f32, f32, f32) {
+    let t = t.clamp(0.0, 1.0);
+    let band = |center: f32| (1.5 - (4.0 * t - center).abs()).clamp(0.0, 1.0);
+    (band(3.0), band(2.0), band(1.0))
+}
@@ -0,0 +1,111 @@
+//! Solid triangle meshes extruded from the curves, written as OBJ or PLY to 3D print the growth
+//! or import it into a modelling tool.
+
+use crate::stroke;
+use crate::{History, ParticleSystem, Polyline};
+use glam::{vec3, Vec3};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Triangles between vertices, wound counterclockwise seen from outside the solid.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Vec3>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    pub fn new() -> Self {
+        Mesh::default()
+    }
+
+    /// Every curve of `ps` as a wall `thickness` wide standing `height` tall on the plane.
+    pub fn extrude(ps: &ParticleSystem, thickness: f32, height: f32) -> Self {
+        let mut mesh = Mesh::new();
+        for polyline in ps.polylines() {
+            mesh.add_wall(&polyline, thickness, 0.0, height);
+        }
+        mesh
+    }
+
+    /// Every outline kept in `history` as a wall `thickness` wide and `height` tall, each stacked
+    /// on top of the one before, so the growth rings rise like the floors of a tower.
+    pub fn stack(history: &History, thickness: f32, height: f32) -> Self {
+        let mut mesh = Mesh::new();
+        for (i, snapshot) in history.snapshots().enumerate() {
+            let bottom = i as f32 * height;
+            for polyline in &snapshot.polylines {
+                mesh.add_wall(polyline, thickness, bottom, bottom + height);
+            }
+        }
+        mesh
+    }
+
+    /// Adds a closed solid following `polyline`: the ribbon drawing it `thickness` wide, at
+    /// heights `bottom` and `top`, joined by walls along its outline.
+    pub fn add_wall(&mut self, polyline: &Polyline, thickness: f32, bottom: f32, top: f32) {
+        let widths = vec![thickness; polyline.points.len()];
+        let (ribbon, triangles) = stroke::stroke(&polyline.points, &widths, polyline.closed);
+        if triangles.is_empty() {
+            return;
+        }
+
+        // The ribbon is wound counterclockwise, facing up, so the floor is flipped to face down.
+        let floor = self.vertices.len();
+        let roof = floor + ribbon.len();
+        self.vertices
+            .extend(ribbon.iter().map(|p| vec3(p.x, p.y, bottom)));
+        self.vertices
+            .extend(ribbon.iter().map(|p| vec3(p.x, p.y, top)));
+        for &[a, b, c] in &triangles {
+            self.triangles.push([floor + a, floor + c, floor + b]);
+            self.triangles.push([roof + a, roof + b, roof + c]);
+        }
+
+        // Edges of the ribbon used by a single triangle are on its outline, going around it
+        // counterclockwise, so the walls raised from them face outward.
+        let edges: HashSet<(usize, usize)> = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| [(a, b), (b, c), (c, a)])
+            .collect();
+        for &[a, b, c] in &triangles {
+            for (a, b) in [(a, b), (b, c), (c, a)] {
+                if !edges.contains(&(b, a)) {
+                    self.triangles.push([floor + a, floor + b, roof + b]);
+                    self.triangles.push([floor + a, roof + b, roof + a]);
+                }
+            }
+        }
+    }
+
+    /// The mesh as a Wavefront OBJ file.
+    pub fn to_obj(&self) -> String {
+        let mut obj = String::from("# Differential line growth\no growth\n");
+        for v in &self.vertices {
+            writeln!(obj, "v {} {} {}", v.x, v.y, v.z).unwrap();
+        }
+        // Indices start at 1.
+        for [a, b, c] in &self.triangles {
+            writeln!(obj, "f {} {} {}", a + 1, b + 1, c + 1).unwrap();
+        }
+        obj
+    }
+
+    /// The mesh as an ASCII PLY file.
+    pub fn to_ply(&self) -> String {
+        let mut ply = format!(
+            "ply\nformat ascii 1.0\ncomment Differential line growth\n\
+             element vertex {}\nproperty float x\nproperty float y\nproperty float z\n\
+             element face {}\nproperty list uchar int vertex_indices\nend_header\n",
+            self.vertices.len(),
+            self.triangles.len()
+        );
+        for v in &self.vertices {
+            writeln!(ply, "{} {} {}", v.x, v.y, v.z).unwrap();
+        }
+        for [a, b, c] in &self.triangles {
+            writeln!(ply, "3 {} {} {}", a, b, c).unwrap();
+        }
+        ply
+    }
+}
@@ -0,0 +1,75 @@
+//! A CSV log of how the curves grow over time, to plot their dynamics.
+
+use crate::sweep::Metrics;
+use crate::ParticleSystem;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+const HEADER: &str = "step,particles,length,area,mean_curvature,step_ms";
+
+/// Appends a row of measurements of the curves to a CSV file every `every` steps.
+pub struct MetricsLog {
+    pub every: u64,
+    writer: BufWriter<File>,
+    /// The time spent stepping since the last row, and over how many steps.
+    elapsed: Duration,
+    steps: u64,
+}
+
+impl MetricsLog {
+    /// Opens `path` to append to, writing the header first if it is new or empty.
+    pub fn create(path: &Path, every: u64) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if is_empty {
+            writeln!(writer, "{}", HEADER)?;
+        }
+        Ok(MetricsLog {
+            every,
+            writer,
+            elapsed: Duration::ZERO,
+            steps: 0,
+        })
+    }
+
+    /// Counts a step of `ps` that took `elapsed`, writing a row if its step is a multiple of
+    /// `every`. Call it after every step. The step time of a row is the mean over the steps since
+    /// the previous one.
+    pub fn record(&mut self, ps: &ParticleSystem, elapsed: Duration) -> io::Result<()> {
+        self.elapsed += elapsed;
+        self.steps += 1;
+        if !ps.steps().is_multiple_of(self.every.max(1)) {
+            return Ok(());
+        }
+
+        let metrics = Metrics::of(ps);
+        let curvatures = ps.curvatures();
+        let mean_curvature = match ps.num_particles() {
+            0 => 0.0,
+            n => ps.particle_indices().map(|i| curvatures[i]).sum::<f32>() / n as f32,
+        };
+        let step_ms = self.elapsed.as_secs_f64() * 1000.0 / self.steps as f64;
+        writeln!(
+            self.writer,
+            "{},{},{:.3},{:.3},{:.5},{:.3}",
+            ps.steps(),
+            metrics.particles,
+            metrics.length,
+            metrics.area,
+            mean_curvature,
+            step_ms
+        )?;
+        // Flushed on every row, so that the log can be plotted while the simulation runs.
+        self.writer.flush()?;
+
+        self.elapsed = Duration::ZERO;
+        self.steps = 0;
+        Ok(())
+    }
+}
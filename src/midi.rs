@@ -0,0 +1,123 @@
+//! Knobs and sliders of a MIDI controller bound to the parameters of the simulation, to play it
+//! live. Connecting to a controller needs the `midi` feature.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The latest values of the control changes received, from 0 to 127.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MidiControls {
+    /// Keyed by channel, from 1 to 16, and controller. Channel 0 holds the latest value of each
+    /// controller on any channel.
+    values: HashMap<(u8, u8), u8>,
+}
+
+impl MidiControls {
+    /// Records a control change message, ignoring any other kind of message.
+    pub fn receive(&mut self, message: &[u8]) {
+        if let [status, controller, value] = *message {
+            if status & 0xf0 == 0xb0 {
+                let channel = (status & 0x0f) + 1;
+                self.values.insert((channel, controller), value);
+                self.values.insert((0, controller), value);
+            }
+        }
+    }
+
+    /// The latest value of `controller` on `channel`, or on any channel, if it was moved yet.
+    pub fn get(&self, channel: Option<u8>, controller: u8) -> Option<u8> {
+        self.values
+            .get(&(channel.unwrap_or(0), controller))
+            .copied()
+    }
+}
+
+/// Sets a parameter from `min` with the control at 0 to `max` with it at 127. Until the control
+/// is first moved, the parameter keeps its configured value.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MidiMapping {
+    /// The channel of the control, from 1 to 16, or any channel when unset.
+    #[serde(default)]
+    pub channel: Option<u8>,
+    /// The control change number of the knob or slider.
+    pub controller: u8,
+    /// The dotted path of the parameter within `[simulation]`, like in schedules.
+    pub parameter: String,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MidiMapping {
+    pub fn value(&self, controls: &MidiControls) -> Option<f64> {
+        let t = controls.get(self.channel, self.controller)? as f64 / 127.0;
+        Some(self.min + (self.max - self.min) * t)
+    }
+}
+
+#[cfg(feature = "midi")]
+pub use input::MidiInput;
+
+/// Control changes received from a MIDI input port.
+#[cfg(feature = "midi")]
+mod input {
+    use super::MidiControls;
+    use midir::{MidiInput as Midir, MidiInputConnection};
+    use std::error::Error;
+    use std::sync::{Arc, Mutex};
+
+    pub struct MidiInput {
+        controls: Arc<Mutex<MidiControls>>,
+        // Receiving stops when the connection is dropped.
+        _connection: MidiInputConnection<()>,
+    }
+
+    impl MidiInput {
+        /// Connects to the first input port whose name contains `port`, or the first one.
+        pub fn start(port: Option<&str>) -> Result<Self, Box<dyn Error>> {
+            let midi = Midir::new("differential-lines")?;
+            let ports = midi.ports();
+            let found = match port {
+                Some(name) => ports
+                    .iter()
+                    .find(|port| midi.port_name(port).is_ok_and(|n| n.contains(name)))
+                    .ok_or_else(|| format!("no MIDI input port named {}", name))?,
+                None => ports.first().ok_or("no MIDI input port")?,
+            };
+
+            let controls = Arc::new(Mutex::new(MidiControls::default()));
+            let received = Arc::clone(&controls);
+            let connection = midi.connect(
+                found,
+                "differential-lines",
+                move |_, message, _| received.lock().unwrap().receive(message),
+                (),
+            )?;
+
+            Ok(MidiInput {
+                controls,
+                _connection: connection,
+            })
+        }
+
+        /// The latest values of the controls.
+        pub fn controls(&self) -> MidiControls {
+            self.controls.lock().unwrap().clone()
+        }
+    }
+}
+
+/// Stands in for the input without the `midi` feature, failing to start.
+#[cfg(not(feature = "midi"))]
+pub enum MidiInput {}
+
+#[cfg(not(feature = "midi"))]
+impl MidiInput {
+    pub fn start(_port: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("reading MIDI controllers needs the midi feature".into())
+    }
+
+    pub fn controls(&self) -> MidiControls {
+        match *self {}
+    }
+}
@@ -0,0 +1,165 @@
+//! Frames sent over the network with NDI, for live streaming from a machine other than the one
+//! rendering. The NDI runtime is loaded when sending starts, which needs the `ndi` feature.
+
+#[cfg(feature = "ndi")]
+pub use sender::NdiSender;
+
+/// Bindings to the few functions of the NDI runtime's C API that sending video needs.
+#[cfg(feature = "ndi")]
+mod sender {
+    use crate::Canvas;
+    use libloading::Library;
+    use std::error::Error;
+    use std::ffi::{c_char, c_float, c_int, c_void, CString};
+    use std::path::Path;
+    use std::ptr;
+
+    /// Where the runtime installed by NDI Tools or the NDI SDK is usually found.
+    #[cfg(target_os = "windows")]
+    const LIBRARY_NAMES: &[&str] = &["Processing.NDI.Lib.x64.dll"];
+    #[cfg(target_os = "macos")]
+    const LIBRARY_NAMES: &[&str] = &["libndi.dylib", "/usr/local/lib/libndi.dylib"];
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    const LIBRARY_NAMES: &[&str] = &["libndi.so.6", "libndi.so.5", "libndi.so"];
+
+    /// `NDIlib_FourCC_video_type_RGBA`, 8 bits per component in memory order.
+    const FOURCC_RGBA: u32 = u32::from_le_bytes(*b"RGBA");
+    /// `NDIlib_frame_format_type_progressive`.
+    const PROGRESSIVE: c_int = 1;
+    /// `NDIlib_send_timecode_synthesize`, letting the runtime time the frames.
+    const SYNTHESIZE_TIMECODE: i64 = i64::MAX;
+
+    #[repr(C)]
+    struct SendCreate {
+        ndi_name: *const c_char,
+        groups: *const c_char,
+        clock_video: bool,
+        clock_audio: bool,
+    }
+
+    #[repr(C)]
+    struct VideoFrame {
+        xres: c_int,
+        yres: c_int,
+        fourcc: u32,
+        frame_rate_n: c_int,
+        frame_rate_d: c_int,
+        picture_aspect_ratio: c_float,
+        frame_format_type: c_int,
+        timecode: i64,
+        data: *const u8,
+        line_stride_in_bytes: c_int,
+        metadata: *const c_char,
+        timestamp: i64,
+    }
+
+    type Instance = *mut c_void;
+
+    /// A source other machines on the network see under its name, sending the frames given to it.
+    pub struct NdiSender {
+        instance: Instance,
+        send_video: unsafe extern "C" fn(Instance, *const VideoFrame),
+        destroy: unsafe extern "C" fn(Instance),
+        fps: u32,
+        // Keeps the functions above loaded, so it has to outlive them.
+        _library: Library,
+    }
+
+    impl NdiSender {
+        /// Loads the NDI runtime from `library`, or from where it is usually installed, and
+        /// announces a source called `name` sending at `fps` frames per second.
+        pub fn start(name: &str, library: Option<&Path>, fps: u32) -> Result<Self, Box<dyn Error>> {
+            let library = match library {
+                Some(path) => unsafe { Library::new(path)? },
+                None => LIBRARY_NAMES
+                    .iter()
+                    .find_map(|name| unsafe { Library::new(name).ok() })
+                    .ok_or("the NDI runtime isn't installed, set ndi.library to its path")?,
+            };
+            let name = CString::new(name)?;
+
+            unsafe {
+                let initialize =
+                    *library.get::<unsafe extern "C" fn() -> bool>(b"NDIlib_initialize")?;
+                let create = *library.get::<unsafe extern "C" fn(*const SendCreate) -> Instance>(
+                    b"NDIlib_send_create",
+                )?;
+                let send_video = *library
+                    .get::<unsafe extern "C" fn(Instance, *const VideoFrame)>(
+                        b"NDIlib_send_send_video_v2",
+                    )?;
+                let destroy =
+                    *library.get::<unsafe extern "C" fn(Instance)>(b"NDIlib_send_destroy")?;
+
+                if !initialize() {
+                    return Err("the CPU isn't supported by NDI".into());
+                }
+                let settings = SendCreate {
+                    ndi_name: name.as_ptr(),
+                    groups: ptr::null(),
+                    // Frames are sent as the app draws them rather than at a steady rate.
+                    clock_video: false,
+                    clock_audio: false,
+                };
+                let instance = create(&settings);
+                if instance.is_null() {
+                    return Err("failed to create an NDI source".into());
+                }
+
+                Ok(NdiSender {
+                    instance,
+                    send_video,
+                    destroy,
+                    fps,
+                    _library: library,
+                })
+            }
+        }
+
+        /// Sends the canvas as the next frame. NDI copies it before returning.
+        pub fn send(&mut self, canvas: &Canvas) {
+            let pixels = canvas.to_rgba8();
+            let (width, height) = (canvas.width(), canvas.height());
+            let frame = VideoFrame {
+                xres: width as c_int,
+                yres: height as c_int,
+                fourcc: FOURCC_RGBA,
+                frame_rate_n: self.fps as c_int,
+                frame_rate_d: 1,
+                picture_aspect_ratio: width as f32 / height.max(1) as f32,
+                frame_format_type: PROGRESSIVE,
+                timecode: SYNTHESIZE_TIMECODE,
+                data: pixels.as_ptr(),
+                line_stride_in_bytes: (width * 4) as c_int,
+                metadata: ptr::null(),
+                timestamp: 0,
+            };
+            unsafe { (self.send_video)(self.instance, &frame) };
+        }
+    }
+
+    impl Drop for NdiSender {
+        fn drop(&mut self) {
+            unsafe { (self.destroy)(self.instance) };
+        }
+    }
+}
+
+/// Stands in for the sender without the `ndi` feature, failing to start.
+#[cfg(not(feature = "ndi"))]
+pub enum NdiSender {}
+
+#[cfg(not(feature = "ndi"))]
+impl NdiSender {
+    pub fn start(
+        _name: &str,
+        _library: Option<&std::path::Path>,
+        _fps: u32,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("sending over NDI needs the ndi feature".into())
+    }
+
+    pub fn send(&mut self, _canvas: &crate::Canvas) {
+        match *self {}
+    }
+}
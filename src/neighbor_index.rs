@@ -0,0 +1,82 @@
+//! Spatial indices for finding the particles near a point, interchangeable at runtime.
+//!
+//! Which one is fastest depends on how the particles are spread out: the grid suits evenly packed
+//! curves, the quadtree adapts to dense clumps next to empty space, and brute force wins for a
+//! handful of particles.
+
+use crate::grid::SpatialGrid;
+use crate::quadtree::Quadtree;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// A spatial index over particle positions answering radius queries.
+pub trait NeighborIndex: Send + Sync {
+    /// The kind of index this is.
+    fn kind(&self) -> NeighborIndexKind;
+
+    /// Replaces the indexed positions. `radius` is the one most queries will be made with, which
+    /// indices may use to size their layout.
+    fn rebuild(&mut self, radius: f32, positions: &[Vec2]);
+
+    /// Calls `f` with every index that may lie within `radius` of `center`, and maybe others.
+    /// Callers still need to do an exact distance check.
+    fn for_each_candidate(&self, center: Vec2, radius: f32, f: &mut dyn FnMut(usize));
+}
+
+/// Selects a `NeighborIndex` implementation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeighborIndexKind {
+    /// A `SpatialGrid` with cells the size of the query radius.
+    #[default]
+    Grid,
+    /// A `Quadtree`.
+    Quadtree,
+    /// Every particle is a candidate for every query.
+    BruteForce,
+}
+
+impl NeighborIndexKind {
+    /// Creates an empty index of this kind.
+    pub fn build(self) -> Box<dyn NeighborIndex> {
+        match self {
+            NeighborIndexKind::Grid => Box::new(SpatialGrid::default()),
+            NeighborIndexKind::Quadtree => Box::new(Quadtree::default()),
+            NeighborIndexKind::BruteForce => Box::new(BruteForce::default()),
+        }
+    }
+}
+
+/// Not an index at all, for comparison and for tiny systems where anything else is overhead.
+#[derive(Default)]
+pub struct BruteForce {
+    len: usize,
+}
+
+impl NeighborIndex for BruteForce {
+    fn kind(&self) -> NeighborIndexKind {
+        NeighborIndexKind::BruteForce
+    }
+
+    fn rebuild(&mut self, _radius: f32, positions: &[Vec2]) {
+        self.len = positions.len();
+    }
+
+    fn for_each_candidate(&self, _center: Vec2, _radius: f32, f: &mut dyn FnMut(usize)) {
+        (0..self.len).for_each(f);
+    }
+}
+
+impl NeighborIndex for SpatialGrid {
+    fn kind(&self) -> NeighborIndexKind {
+        NeighborIndexKind::Grid
+    }
+
+    fn rebuild(&mut self, radius: f32, positions: &[Vec2]) {
+        SpatialGrid::rebuild(self, radius, positions);
+    }
+
+    fn for_each_candidate(&self, center: Vec2, radius: f32, f: &mut dyn FnMut(usize)) {
+        SpatialGrid::for_each_candidate(self, center, radius, f);
+    }
+}
@@ -0,0 +1,133 @@
+//! A time-varying noise field used as a flow force.
+
+use glam::{vec2, Vec2};
+use noise::{NoiseFn, OpenSimplex};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// How the noise value at a particle is turned into a force.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum NoiseMode {
+    /// The noise value encodes an angle the particle is pushed toward.
+    Angle,
+    /// The particle is pushed up the slope of the noise.
+    Gradient,
+}
+
+/// Fractal OpenSimplex noise sampled at each particle's position, evolving over time.
+///
+/// Serialized without the noise itself, which is generated again from the seed.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(from = "NoiseFieldParams", into = "NoiseFieldParams")]
+pub struct NoiseField {
+    /// Frequency of the first octave, in cycles per simulation unit.
+    pub scale: f32,
+    /// Number of layers of noise, each at twice the frequency and half the amplitude of the last.
+    pub octaves: usize,
+    /// Distance travelled per step at full force.
+    pub strength: f32,
+    /// How fast the field changes, in noise units per step.
+    pub speed: f32,
+    pub mode: NoiseMode,
+    seed: u32,
+    noise: OpenSimplex,
+}
+
+#[derive(Deserialize, Serialize)]
+struct NoiseFieldParams {
+    scale: f32,
+    octaves: usize,
+    strength: f32,
+    speed: f32,
+    mode: NoiseMode,
+    seed: u32,
+}
+
+impl From<NoiseFieldParams> for NoiseField {
+    fn from(params: NoiseFieldParams) -> Self {
+        NoiseField {
+            scale: params.scale,
+            octaves: params.octaves,
+            strength: params.strength,
+            speed: params.speed,
+            mode: params.mode,
+            seed: params.seed,
+            noise: OpenSimplex::new(params.seed),
+        }
+    }
+}
+
+impl From<NoiseField> for NoiseFieldParams {
+    fn from(field: NoiseField) -> Self {
+        NoiseFieldParams {
+            scale: field.scale,
+            octaves: field.octaves,
+            strength: field.strength,
+            speed: field.speed,
+            mode: field.mode,
+            seed: field.seed,
+        }
+    }
+}
+
+impl NoiseField {
+    pub fn new(seed: u32) -> Self {
+        NoiseField {
+            scale: 0.005,
+            octaves: 3,
+            strength: 0.2,
+            speed: 0.002,
+            mode: NoiseMode::Angle,
+            seed,
+            noise: OpenSimplex::new(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Fractal noise at `p` and `time`, roughly in `[-1, 1]`.
+    fn sample(&self, p: Vec2, time: f32) -> f32 {
+        let mut value = 0.0;
+        let mut frequency = self.scale as f64;
+        let mut amplitude = 1.0;
+        let mut total_amplitude = 0.0;
+
+        for _ in 0..self.octaves {
+            let point = [
+                p.x as f64 * frequency,
+                p.y as f64 * frequency,
+                (time * self.speed) as f64,
+            ];
+            value += self.noise.get(point) * amplitude;
+            total_amplitude += amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+
+        if total_amplitude > 0.0 {
+            (value / total_amplitude) as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// The force exerted on a particle at `p`, `time` into the simulation, see
+    /// `ParticleSystem::time()`.
+    pub fn force_at(&self, p: Vec2, time: f32) -> Vec2 {
+        match self.mode {
+            NoiseMode::Angle => {
+                let angle = self.sample(p, time) * 2.0 * PI;
+                vec2(angle.cos(), angle.sin()) * self.strength
+            }
+            NoiseMode::Gradient => {
+                // Central differences over a tenth of the noise's feature size.
+                let h = 0.1 / self.scale;
+                let dx = self.sample(p + vec2(h, 0.0), time) - self.sample(p - vec2(h, 0.0), time);
+                let dy = self.sample(p + vec2(0.0, h), time) - self.sample(p - vec2(0.0, h), time);
+                vec2(dx, dy).normalize_or_zero() * self.strength
+            }
+        }
+    }
+}
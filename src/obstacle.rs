@@ -0,0 +1,109 @@
+//! Solid polygons the growth flows around.
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// A polygon that pushes nearby particles away and stops particles from moving into it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Obstacle {
+    /// The corners of the polygon, in simulation units.
+    pub polygon: Vec<Vec2>,
+    /// Distance travelled per step by a particle touching the obstacle.
+    #[serde(default = "default_repulsion")]
+    pub repulsion: f32,
+    /// Distance from the obstacle at which the push has dropped to half its strength.
+    #[serde(default = "default_falloff")]
+    pub falloff: f32,
+}
+
+fn default_repulsion() -> f32 {
+    0.5
+}
+
+fn default_falloff() -> f32 {
+    10.0
+}
+
+impl Obstacle {
+    pub fn new(polygon: Vec<Vec2>) -> Self {
+        Obstacle {
+            polygon,
+            repulsion: default_repulsion(),
+            falloff: default_falloff(),
+        }
+    }
+
+    pub fn contains(&self, p: Vec2) -> bool {
+        let mut inside = false;
+        self.for_each_segment(|a, b| {
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                inside = !inside;
+            }
+        });
+        inside
+    }
+
+    /// The point on the outline of the polygon closest to `p`.
+    fn closest_point(&self, p: Vec2) -> Option<Vec2> {
+        let mut closest: Option<Vec2> = None;
+        self.for_each_segment(|a, b| {
+            let ab = b - a;
+            let t = ((p - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+            let q = a + ab * t;
+            if closest.is_none_or(|c| (p - q).length() < (p - c).length()) {
+                closest = Some(q);
+            }
+        });
+        closest
+    }
+
+    /// The push on a particle at `p`, away from the outline, or out of the polygon when inside.
+    pub fn force_at(&self, p: Vec2) -> Vec2 {
+        let closest = match self.closest_point(p) {
+            Some(closest) => closest,
+            None => return Vec2::ZERO,
+        };
+
+        if self.contains(p) {
+            return (closest - p).normalize_or_zero() * self.repulsion;
+        }
+
+        let offset = p - closest;
+        let falloff2 = self.falloff * self.falloff;
+        let weight = falloff2 / (falloff2 + offset.length_squared());
+        offset.normalize_or_zero() * self.repulsion * weight
+    }
+
+    /// Returns where a particle moving from `from` to `to` stops when it would enter the polygon.
+    ///
+    /// Particles that start inside are left free, so that they can be pushed out of an obstacle
+    /// placed on top of them.
+    pub fn block(&self, from: Vec2, to: Vec2) -> Vec2 {
+        if !self.contains(to) || self.contains(from) {
+            return to;
+        }
+
+        // Bisect the move to find the last point still outside.
+        let (mut outside, mut inside) = (0.0, 1.0);
+        for _ in 0..16 {
+            let t = (outside + inside) / 2.0;
+            if self.contains(from.lerp(to, t)) {
+                inside = t;
+            } else {
+                outside = t;
+            }
+        }
+        from.lerp(to, outside)
+    }
+
+    fn for_each_segment<F>(&self, mut f: F)
+    where
+        F: FnMut(Vec2, Vec2),
+    {
+        let points = &self.polygon;
+        for i in 0..points.len() {
+            f(points[i], points[(i + 1) % points.len()]);
+        }
+    }
+}
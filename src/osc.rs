@@ -0,0 +1,129 @@
+//! A server receiving OSC messages, so that the simulation can be driven remotely from tools like
+//! TouchDesigner, Max/MSP or SuperCollider. Receiving messages needs the `osc` feature.
+
+/// What an OSC message under the configured prefix, like `/diffline`, asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscCommand {
+    /// `<prefix>/<parameter>` with a number sets a parameter of `[simulation]`, with the dots of
+    /// its path as slashes, like `/diffline/throttle/max_particles`.
+    Set { parameter: String, value: f64 },
+    /// `<prefix>/reset` restarts the simulation.
+    Reset,
+    /// `<prefix>/pause` pauses with a non-zero number, resumes with 0, or toggles without one.
+    Pause(Option<bool>),
+}
+
+impl OscCommand {
+    /// The command sent to `address` with the numbers `args`, if it is under `prefix` and makes
+    /// sense.
+    pub fn parse(prefix: &str, address: &str, args: &[f64]) -> Option<Self> {
+        let path = address
+            .strip_prefix(prefix.trim_end_matches('/'))?
+            .strip_prefix('/')?;
+        match (path, args) {
+            ("reset", _) => Some(OscCommand::Reset),
+            ("pause", []) => Some(OscCommand::Pause(None)),
+            ("pause", [value, ..]) => Some(OscCommand::Pause(Some(*value != 0.0))),
+            ("", _) | (_, []) => None,
+            (path, [value, ..]) => Some(OscCommand::Set {
+                parameter: path.replace('/', "."),
+                value: *value,
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "osc")]
+pub use server::OscServer;
+
+/// Messages received over UDP.
+#[cfg(feature = "osc")]
+mod server {
+    use super::OscCommand;
+    use rosc::{OscPacket, OscType};
+    use std::error::Error;
+    use std::io;
+    use std::net::UdpSocket;
+
+    /// The largest packet received, which is plenty for a few numbers.
+    const MAX_PACKET_SIZE: usize = 1536;
+
+    pub struct OscServer {
+        socket: UdpSocket,
+        prefix: String,
+    }
+
+    impl OscServer {
+        /// Listens on `port` of every interface for messages under `prefix`.
+        pub fn bind(port: u16, prefix: &str) -> Result<Self, Box<dyn Error>> {
+            let socket = UdpSocket::bind(("0.0.0.0", port))?;
+            socket.set_nonblocking(true)?;
+            Ok(OscServer {
+                socket,
+                prefix: prefix.to_string(),
+            })
+        }
+
+        /// The commands received since the last call, in order, skipping anything malformed or
+        /// unknown.
+        pub fn receive(&self) -> Vec<OscCommand> {
+            let mut commands = Vec::new();
+            let mut buffer = [0; MAX_PACKET_SIZE];
+            loop {
+                match self.socket.recv_from(&mut buffer) {
+                    Ok((size, _)) => {
+                        if let Ok((_, packet)) = rosc::decoder::decode_udp(&buffer[..size]) {
+                            self.push_commands(packet, &mut commands);
+                        }
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        eprintln!("Failed to receive OSC: {}", err);
+                        break;
+                    }
+                }
+            }
+            commands
+        }
+
+        fn push_commands(&self, packet: OscPacket, commands: &mut Vec<OscCommand>) {
+            match packet {
+                OscPacket::Message(message) => {
+                    let args: Vec<f64> = message.args.iter().filter_map(number).collect();
+                    commands.extend(OscCommand::parse(&self.prefix, &message.addr, &args));
+                }
+                OscPacket::Bundle(bundle) => {
+                    for packet in bundle.content {
+                        self.push_commands(packet, commands);
+                    }
+                }
+            }
+        }
+    }
+
+    fn number(arg: &OscType) -> Option<f64> {
+        match *arg {
+            OscType::Float(x) => Some(x as f64),
+            OscType::Double(x) => Some(x),
+            OscType::Int(x) => Some(x as f64),
+            OscType::Long(x) => Some(x as f64),
+            OscType::Bool(x) => Some(x as u8 as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Stands in for the server without the `osc` feature, failing to start.
+#[cfg(not(feature = "osc"))]
+pub enum OscServer {}
+
+#[cfg(not(feature = "osc"))]
+impl OscServer {
+    pub fn bind(_port: u16, _prefix: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("receiving OSC needs the osc feature".into())
+    }
+
+    pub fn receive(&self) -> Vec<OscCommand> {
+        match *self {}
+    }
+}
@@ -0,0 +1,57 @@
+//! Grayscale images that vary the simulation's parameters across the plane.
+
+use crate::ImageMask;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The parameters a `ParameterMap` can vary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappedParameter {
+    /// How likely long edges are to split, so bright areas fill in more densely.
+    SplitRate,
+    /// How far particles push each other away, so bright areas grow coarser folds.
+    InfluenceRadius,
+    /// How far the forces move particles each step.
+    ForceStrength,
+}
+
+/// Scales a parameter by the brightness of an image under each particle, from `min` on black to
+/// `max` on white. Outside of the image the parameter is scaled by `min`.
+#[derive(Deserialize, Serialize)]
+pub struct ParameterMap {
+    pub parameter: MappedParameter,
+    pub min: f32,
+    pub max: f32,
+    image: ImageMask,
+}
+
+impl ParameterMap {
+    /// Loads an image centered at `center`, scaled so that its width spans `width` simulation
+    /// units, that scales `parameter` by between 0 and 1.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        center: Vec2,
+        width: f32,
+        parameter: MappedParameter,
+    ) -> image::ImageResult<Self> {
+        Ok(ParameterMap {
+            parameter,
+            min: 0.0,
+            max: 1.0,
+            image: ImageMask::open(path, center, width)?,
+        })
+    }
+
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// The factor the parameter is scaled by at `p`.
+    pub fn scale_at(&self, p: Vec2) -> f32 {
+        self.min + (self.max - self.min) * self.image.sample(p)
+    }
+}
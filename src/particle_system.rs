@@ -0,0 +1,2268 @@
+use crate::area::{self, AreaPreservation};
+use crate::attractor::Attractor;
+use crate::boundary::{Boundary, BoundaryResponse, Floor};
+use crate::cyclic;
+use crate::direction_field::DirectionField;
+#[cfg(feature = "gpu")]
+use crate::gpu::{Gpu, GpuError};
+use crate::hotspot::Hotspot;
+use crate::links::{Link, Links};
+use crate::neighbor_index::{NeighborIndex, NeighborIndexKind};
+use crate::noise_field::NoiseField;
+use crate::obstacle::Obstacle;
+use crate::parameter_map::{MappedParameter, ParameterMap};
+use crate::reaction_diffusion::ReactionDiffusion;
+#[cfg(feature = "scripting")]
+use crate::script::{Script, ScriptError};
+use crate::spawn::SpawnShape;
+use crate::stats::Summary;
+use crate::symmetry::Symmetry;
+use crate::torus::Torus;
+use glam::{vec2, Mat2, Vec2};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// An RGBA color with components in `[0, 1]`.
+pub type Color = [f32; 4];
+
+/// Whether the segments `a0`-`a1` and `b0`-`b1` properly cross each other.
+fn segments_intersect(a0: Vec2, a1: Vec2, b0: Vec2, b1: Vec2) -> bool {
+    let side = |p: Vec2, q0: Vec2, q1: Vec2| (q1 - q0).perp_dot(p - q0);
+
+    side(b0, a0, a1) * side(b1, a0, a1) < 0.0 && side(a0, b0, b1) * side(a1, b0, b1) < 0.0
+}
+
+/// A single line made of particles linked through their edges.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Curve {
+    /// Open curves have two endpoints instead of wrapping around into a ring.
+    pub open: bool,
+    /// How many steps the curve grew for before it froze, or so far.
+    #[serde(default)]
+    pub steps: u64,
+    /// Whether the curve reached a limit of `ParticleSystem::budget` and stopped growing.
+    #[serde(default)]
+    pub frozen: bool,
+}
+
+/// The points of one curve in order along its edges.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Polyline {
+    pub points: Vec<Vec2>,
+    /// Whether the last point connects back to the first one.
+    pub closed: bool,
+}
+
+/// How forces move the particles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integration {
+    /// Forces directly displace the particles, which stop as soon as the forces balance out.
+    #[default]
+    Overdamped,
+    /// Forces accelerate the particles, which carry their velocity over into the next step and
+    /// overshoot, growing springier, wavier curves. `damping` is the fraction of the velocity
+    /// lost per unit of time, from 0 to 1.
+    Inertial { damping: f32 },
+}
+
+/// What happens to particles older than `ParticleSystem::max_age`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expiry {
+    /// They stop moving for good, while still pushing younger particles away, and the edges
+    /// between them stop splitting and merging. Over time this leaves layers of fossilized curve
+    /// behind the growing front.
+    #[default]
+    Freeze,
+    /// They are removed, joining their neighbors along the curve, unless that would leave fewer
+    /// than three particles in a closed curve or two in an open one.
+    Kill,
+}
+
+/// Limits on how far each curve grows, so batch runs end at the same point whatever their
+/// length. A curve reaching any of them freezes for good: its particles stop moving and its edges
+/// stop splitting and merging, while it still pushes other curves away. Unset limits don't apply.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrowthBudget {
+    pub max_particles: Option<usize>,
+    /// Only limits closed curves, in square units.
+    pub max_area: Option<f32>,
+    /// The widest or tallest the bounding box of a curve may get.
+    pub max_size: Option<f32>,
+    pub max_steps: Option<u64>,
+}
+
+/// Limits on how fast the whole system grows, so that it slows down gently rather than grinding
+/// to a halt as the particle count rises. Unset limits don't apply.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SplitThrottle {
+    /// The most edges split in a single step. The edges considered first vary from step to step
+    /// so that no part of the curves is favored.
+    pub max_splits_per_step: Option<usize>,
+    /// The particle count splitting stops at.
+    pub max_particles: Option<usize>,
+    /// The fraction of `max_particles` over which splits get gradually less likely as the count
+    /// approaches it, from 0 for an abrupt stop to 1 for slowing down from the start.
+    pub slowdown: f32,
+}
+
+impl Default for SplitThrottle {
+    fn default() -> Self {
+        SplitThrottle {
+            max_splits_per_step: None,
+            max_particles: None,
+            slowdown: 0.2,
+        }
+    }
+}
+
+impl SplitThrottle {
+    /// How much the probability of splitting an edge is scaled by with `num_particles` alive.
+    pub fn scale(&self, num_particles: usize) -> f32 {
+        match self.max_particles {
+            Some(max) => {
+                let remaining = max.saturating_sub(num_particles) as f32;
+                (remaining / (max as f32 * self.slowdown).max(1.0)).min(1.0)
+            }
+            None => 1.0,
+        }
+    }
+}
+
+/// Varies the influence radius from particle to particle with their age and the curvature around
+/// them, on top of any `MappedParameter::InfluenceRadius` maps, for structures of several scales
+/// in one run: e.g. fine folds at the growing tips and coarse ones where the curve has settled.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveRadius {
+    /// The fraction of `influence_radius` the radius grows by per unit of age, or shrinks by
+    /// when negative.
+    pub age: f32,
+    /// The fraction of `influence_radius` the radius grows by where the curve folds back on
+    /// itself, less where it bends less, or shrinks by when negative.
+    pub curvature: f32,
+    /// The smallest and largest the radius gets, as fractions of `influence_radius`.
+    pub min_scale: f32,
+    pub max_scale: f32,
+}
+
+impl Default for AdaptiveRadius {
+    fn default() -> Self {
+        AdaptiveRadius {
+            age: 0.0,
+            curvature: 0.0,
+            min_scale: 0.25,
+            max_scale: 4.0,
+        }
+    }
+}
+
+impl AdaptiveRadius {
+    /// How much the influence radius is scaled by for a particle of `age` where the curve bends
+    /// by `curvature`, from 0 for straight to 1 for folded back.
+    pub fn scale(&self, age: f32, curvature: f32) -> f32 {
+        let scale = (1.0 + self.age * age) * (1.0 + self.curvature * curvature);
+        scale.clamp(self.min_scale, self.max_scale)
+    }
+
+    /// Whether every particle keeps the same radius.
+    pub fn is_uniform(&self) -> bool {
+        self.age == 0.0 && self.curvature == 0.0 && self.scale(0.0, 0.0) == 1.0
+    }
+}
+
+/// A hard minimum distance between particles that aren't linked, enforced by moving them apart
+/// at the end of every step, where the pressure alone only discourages overlaps.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Separation {
+    /// The closest unlinked particles get, in simulation units.
+    pub distance: f32,
+    /// How many passes over the overlaps are made per step. Moving particles apart can push
+    /// them into others, which later passes sort out.
+    pub iterations: usize,
+}
+
+impl Default for Separation {
+    fn default() -> Self {
+        Separation {
+            distance: 2.0,
+            iterations: 4,
+        }
+    }
+}
+
+/// Springs along every edge pulling its ends toward a rest length, the textbook formulation of
+/// differential growth. Unlike the attraction toward the midpoint of the neighbors, which only
+/// ever shortens edges, they also push apart ends closer than the rest length.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Springs {
+    /// The length edges settle at, in simulation units. Below `max_edge_length`, edges only
+    /// split once pressure stretches them.
+    pub rest_length: f32,
+    /// The force per unit the edge is longer or shorter than the rest length.
+    pub stiffness: f32,
+    /// Slows down the ends of an edge moving apart or together, per unit of their relative
+    /// speed. Only with inertial integration, as overdamped particles keep no velocity.
+    pub damping: f32,
+}
+
+impl Default for Springs {
+    fn default() -> Self {
+        Springs {
+            rest_length: 2.0,
+            stiffness: 0.5,
+            damping: 0.0,
+        }
+    }
+}
+
+/// A random push on every particle each step, like the thermal motion of molecules, breaking the
+/// symmetry of the spawn shape and roughening regions that would otherwise grow perfectly smooth.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Jitter {
+    /// The strongest push at the start, in any direction. 0 turns the jitter off.
+    pub amplitude: f32,
+    /// The rate the amplitude decays at per unit of time, so that the growth calms down as it
+    /// ages. 0 keeps it constant.
+    pub decay: f32,
+}
+
+impl Jitter {
+    /// The strongest push after `time` units of time.
+    pub fn amplitude_at(&self, time: f32) -> f32 {
+        self.amplitude * (-self.decay * time).exp()
+    }
+}
+
+/// When edges split beyond being longer than `max_edge_length` with fewer than
+/// `max_split_neighbors` neighbors at their ends: conditions they all have to meet, and how
+/// likely they are to split then.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SplitPolicy {
+    /// The chance each step that an edge meeting every condition splits, before the curvature
+    /// bias, parameter maps, throttle and hotspots scale it.
+    pub probability: f32,
+    pub conditions: Vec<SplitCondition>,
+}
+
+impl Default for SplitPolicy {
+    fn default() -> Self {
+        SplitPolicy {
+            probability: 1.0,
+            conditions: Vec::new(),
+        }
+    }
+}
+
+impl SplitPolicy {
+    pub fn allows(&self, edge: &SplitCandidate) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.allows(edge))
+    }
+}
+
+/// A test an edge has to pass to split, see `SplitPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitCondition {
+    /// Its ends have fewer neighbors than this together.
+    MaxNeighbors(usize),
+    /// It is longer than this.
+    MinLength(f32),
+    /// The mean curvature at its ends is at least this, from 0 where the curve runs straight to 1
+    /// where it turns back on itself.
+    MinCurvature(f32),
+    /// The mean curvature at its ends is at most this.
+    MaxCurvature(f32),
+    /// Both of its ends are at least this old, in simulated time.
+    MinAge(f32),
+}
+
+impl SplitCondition {
+    pub fn allows(&self, edge: &SplitCandidate) -> bool {
+        match *self {
+            SplitCondition::MaxNeighbors(max) => edge.neighbors < max,
+            SplitCondition::MinLength(min) => edge.length > min,
+            SplitCondition::MinCurvature(min) => edge.curvature >= min,
+            SplitCondition::MaxCurvature(max) => edge.curvature <= max,
+            SplitCondition::MinAge(min) => edge.age >= min,
+        }
+    }
+}
+
+/// What a `SplitCondition` knows of the edge it tests.
+#[derive(Clone, Copy, Debug)]
+pub struct SplitCandidate {
+    pub length: f32,
+    /// The neighbors of both ends together.
+    pub neighbors: usize,
+    /// The mean curvature at both ends.
+    pub curvature: f32,
+    /// The age of the younger end.
+    pub age: f32,
+}
+
+impl GrowthBudget {
+    /// Whether a curve through `points`, grown for `steps`, reached one of the limits.
+    pub fn is_spent(&self, points: &[Vec2], closed: bool, steps: u64) -> bool {
+        let size = || {
+            let (min, max) = points.iter().fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), &p| (min.min(p), max.max(p)),
+            );
+            (max - min).max_element()
+        };
+
+        self.max_particles.is_some_and(|max| points.len() >= max)
+            || self.max_steps.is_some_and(|max| steps >= max)
+            || self.max_size.is_some_and(|max| size() >= max)
+            || (closed
+                && self
+                    .max_area
+                    .is_some_and(|max| area::signed_area(points).abs() >= max))
+    }
+}
+
+/// How `ParticleSystem::update()` colors the particles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Coloring {
+    /// By the strength of the pressure and attraction acting on them.
+    #[default]
+    Forces,
+    /// From light for new particles to dark for the oldest ones.
+    Age,
+    /// By the forces like `Forces`, but relative to their 95th percentile rather than their
+    /// maximum, so that a single spiking particle doesn't darken all the others. Steadied over
+    /// time by `color_smoothing`.
+    StableForces,
+}
+
+/// The forces acting on a single particle during one step.
+#[derive(Clone, Copy, Default)]
+struct Forces {
+    num_neighbors: usize,
+    /// Pull toward the particle's neighbors along the curve.
+    attraction: Vec2,
+    /// Push away from particles within the influence radius.
+    pressure: Vec2,
+    /// Everything else acting on the particle, like attractors.
+    external: Vec2,
+}
+
+/// The growing curves. Serializing it captures the whole state, including that of the random
+/// number generator, so a deserialized system continues exactly where the original left off.
+#[derive(Deserialize, Serialize)]
+pub struct ParticleSystem {
+    /// The simulated time a step covers. Every force is scaled by it, so smaller steps move the
+    /// particles less each and take more of them to grow the same amount.
+    #[serde(default = "default_dt")]
+    pub dt: f32,
+    /// How many times a step computes the forces and moves the particles, each over an equal
+    /// share of `dt`. More of them keep strong forces from pushing particles through each other
+    /// or blowing the curve apart, at the cost of computing the forces more often.
+    #[serde(default = "default_sub_steps")]
+    pub sub_steps: usize,
+    #[serde(default)]
+    pub integration: Integration,
+    pub particle_radius: f32,
+    pub influence_radius: f32,
+    /// How strongly particles are pulled toward their neighbors along the curve each step.
+    pub attraction_weight: f32,
+    /// How strongly particles are pushed away from nearby particles each step.
+    pub pressure_weight: f32,
+    /// Scales the push between particles of different curves relative to that within a curve.
+    /// Above 1, separate curves pack against each other without ever merging into one another.
+    #[serde(default = "default_inter_curve_pressure")]
+    pub inter_curve_pressure: f32,
+    /// How strongly sharp folds over a few consecutive edges are straightened out each step,
+    /// trading the crinkly look for smoother, rope-like curves. Useful up to about 0.5, above
+    /// which the curves barely grow and soon start to shake.
+    #[serde(default)]
+    pub bending_stiffness: f32,
+    /// Pulls the ends of every edge toward a rest length when set, on top of the attraction,
+    /// which can be turned off with an `attraction_weight` of 0 to use the springs alone.
+    #[serde(default)]
+    pub springs: Option<Springs>,
+    /// How the influence radius varies between particles.
+    #[serde(default)]
+    pub adaptive_radius: AdaptiveRadius,
+    /// Keeps unlinked particles apart when set.
+    #[serde(default)]
+    pub separation: Option<Separation>,
+    /// Wraps space around at the edges of a rectangle when set, positions as well as distances.
+    #[serde(default)]
+    pub torus: Option<Torus>,
+    /// Replicates the growth under a symmetry group around the origin when set. The particles
+    /// push against their copies under the group as well as against each other.
+    #[serde(default)]
+    pub symmetry: Option<Symmetry>,
+    /// Edges longer than this get split in half by inserting a new particle.
+    pub max_edge_length: f32,
+    /// Edges shorter than this collapse by merging their two particles into one.
+    pub min_edge_length: f32,
+    /// Edges whose particles have this many neighbors between them or more never split, which
+    /// keeps crowded regions from growing.
+    pub max_split_neighbors: usize,
+    /// How much splitting favors tightly curved parts of the curve, from 0 where every long edge
+    /// splits to 1 where straight stretches never do. Higher values grow more fractal shapes.
+    pub curvature_bias: f32,
+    /// Rejects moves that would make the curves cross themselves or each other, leaving those
+    /// particles in place for the step.
+    pub prevent_crossings: bool,
+    /// Closed curves pressing into each other closer than this reconnect where they touch, like
+    /// the surgery of differential meshes: two curves join into one, and two distant parts of the
+    /// same curve pinch it into two, forming holes and handles. Never when unset.
+    #[serde(default)]
+    pub reconnect_distance: Option<f32>,
+    /// The spatial index used to find nearby particles. They all find the same neighbors, but in
+    /// different orders, so rounding makes the curves diverge over time.
+    #[serde(default)]
+    pub neighbor_index: NeighborIndexKind,
+    /// Particles older than this, in simulated time, expire as set by `expiry`.
+    #[serde(default)]
+    pub max_age: Option<f32>,
+    #[serde(default)]
+    pub expiry: Expiry,
+    /// The fraction of their mobility particles lose per unit of time as they age, from 0 to 1.
+    /// Above 0, old parts of the curve stiffen and settle while the young ones keep growing.
+    #[serde(default)]
+    pub stiffening: f32,
+    #[serde(default)]
+    pub coloring: Coloring,
+    /// How much of the previous step's force scale `Coloring::StableForces` keeps, from 0 to
+    /// just below 1. Higher values make the colors change more slowly.
+    #[serde(default)]
+    pub color_smoothing: f32,
+    /// The pressure and attraction the colors of `Coloring::StableForces` are relative to.
+    #[serde(default)]
+    color_scales: [f32; 2],
+    /// Confines the particles to a region when set.
+    pub boundary: Option<Boundary>,
+    pub boundary_response: BoundaryResponse,
+    /// A constant force on every particle, like gravity or wind.
+    #[serde(default)]
+    pub gravity: Vec2,
+    /// Stops the particles from falling below a height when set.
+    #[serde(default)]
+    pub floor: Option<Floor>,
+    pub attractors: Vec<Attractor>,
+    /// Polygons the particles are pushed away from and can't move into.
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    /// Regions the curves grow faster or slower in.
+    #[serde(default)]
+    pub hotspots: Vec<Hotspot>,
+    /// Adds a flow force sampled from noise when set.
+    pub noise_field: Option<NoiseField>,
+    /// Makes the pressure stronger along or across a field of directions when set.
+    #[serde(default)]
+    pub direction_field: Option<DirectionField>,
+    /// Images that vary parameters across the plane. Maps of the same parameter multiply.
+    #[serde(default)]
+    pub parameter_maps: Vec<ParameterMap>,
+    /// Keeps the area of closed curves near a target when set.
+    #[serde(default)]
+    pub area_preservation: Option<AreaPreservation>,
+    /// When each curve stops growing.
+    #[serde(default)]
+    pub budget: GrowthBudget,
+    /// How fast the whole system may grow.
+    #[serde(default)]
+    pub throttle: SplitThrottle,
+    /// Which edges split and how likely.
+    #[serde(default)]
+    pub split: SplitPolicy,
+    /// Random pushes drawn from the system's seed.
+    #[serde(default)]
+    pub jitter: Jitter,
+    /// A reaction-diffusion field the particles feed, steering splitting and forces when set.
+    #[serde(default)]
+    pub reaction_diffusion: Option<ReactionDiffusion>,
+    steps: u64,
+    /// The simulated time so far, the sum of `dt` over every step.
+    #[serde(default)]
+    time: f32,
+    /// Recomputed from the forces of every step.
+    #[serde(skip)]
+    pressure_summary: Summary,
+    #[serde(skip)]
+    attraction_summary: Summary,
+    num_particles: usize,
+    /// Particles removed by merging stay in the arrays as tombstones, since their indices are
+    /// baked into `links`. Their slots are recycled through `free` by later insertions.
+    alive: Vec<bool>,
+    free: Vec<usize>,
+    curves: Vec<Curve>,
+    curve_ids: Vec<usize>,
+    positions: Vec<Vec2>,
+    /// Only used with `Integration::Inertial`.
+    #[serde(default)]
+    velocities: Vec<Vec2>,
+    /// The simulated time since each particle was added.
+    #[serde(default)]
+    ages: Vec<f32>,
+    /// Pinned particles stay in place, see `set_pinned()`.
+    #[serde(default)]
+    pinned: Vec<bool>,
+    colors: Vec<Color>,
+    /// Named `edges` in saved states.
+    #[serde(rename = "edges")]
+    links: Links,
+    pressures: Vec<Vec2>,
+    attractions: Vec<Vec2>,
+    num_neighbors: Vec<usize>,
+    /// Rebuilt from the positions at the start of every step.
+    #[serde(skip, default = "default_index")]
+    index: Box<dyn NeighborIndex>,
+    /// The forces of the current step, kept between steps to reuse its allocation.
+    #[serde(skip)]
+    forces: Vec<Forces>,
+    /// The influence radius of every particle for the current step, and the largest of them.
+    #[serde(skip)]
+    radii: Vec<f32>,
+    #[serde(skip)]
+    max_radius: f32,
+    /// The transformations of the symmetry group other than the identity, for the current step.
+    #[serde(skip)]
+    ghosts: Vec<Mat2>,
+    /// Where the live particles were before the latest step, double-buffering the positions to
+    /// draw in between steps, see `interpolated_positions()`.
+    #[serde(skip)]
+    previous_positions: Vec<Option<Vec2>>,
+    /// Computes the forces between particles instead of the CPU when set.
+    #[cfg(feature = "gpu")]
+    #[serde(skip)]
+    gpu: Option<Gpu>,
+    /// Adds forces and tunes the parameters every step when set.
+    #[cfg(feature = "scripting")]
+    #[serde(skip)]
+    pub script: Option<Script>,
+    /// What stopped `script`, until taken.
+    #[cfg(feature = "scripting")]
+    #[serde(skip)]
+    script_error: Option<ScriptError>,
+    seed: u64,
+    rng: Xoshiro256PlusPlus,
+}
+
+/// Parts of the same curve closer than this many particles apart along it never reconnect, which
+/// would pinch off rings too small to grow.
+const RECONNECT_GAP: usize = 8;
+
+/// How many links away on either side the bending resistance straightens each particle against.
+const BENDING_SPAN: usize = 2;
+
+/// Captures the seed `SeedableRng::seed_from_u64()` derives from a number by default, which
+/// `Xoshiro256PlusPlus` overrides. This is how the `SmallRng` used before seeded itself, so going
+/// through it keeps old seeds growing the same curves.
+struct DefaultSeed([u8; 32]);
+
+impl SeedableRng for DefaultSeed {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        DefaultSeed(seed)
+    }
+}
+
+fn default_dt() -> f32 {
+    1.0
+}
+
+fn default_sub_steps() -> usize {
+    1
+}
+
+fn default_inter_curve_pressure() -> f32 {
+    1.0
+}
+
+fn default_index() -> Box<dyn NeighborIndex> {
+    NeighborIndexKind::default().build()
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParticleSystem {
+    /// Creates an empty system with a random seed, see `with_seed()`.
+    pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    /// Creates an empty system whose randomness is entirely derived from `seed`, so that the same
+    /// seed and calls always grow the same result.
+    pub fn with_seed(seed: u64) -> Self {
+        let alive = Vec::new();
+        let free = Vec::new();
+        let curves = Vec::new();
+        let curve_ids = Vec::new();
+        let positions = Vec::new();
+        let velocities = Vec::new();
+        let ages = Vec::new();
+        let pinned = Vec::new();
+        let colors = Vec::new();
+        let links = Links::new();
+        let pressures = Vec::new();
+        let attractions = Vec::new();
+        let num_neighbors = Vec::new();
+
+        ParticleSystem {
+            dt: default_dt(),
+            sub_steps: default_sub_steps(),
+            integration: Integration::default(),
+            particle_radius: 4.0,
+            influence_radius: 12.0,
+            attraction_weight: 0.6,
+            pressure_weight: 0.2,
+            inter_curve_pressure: default_inter_curve_pressure(),
+            bending_stiffness: 0.0,
+            springs: None,
+            adaptive_radius: AdaptiveRadius::default(),
+            separation: None,
+            torus: None,
+            symmetry: None,
+            max_edge_length: 3.0,
+            min_edge_length: 1.0,
+            max_split_neighbors: 16,
+            curvature_bias: 0.0,
+            prevent_crossings: false,
+            reconnect_distance: None,
+            neighbor_index: NeighborIndexKind::default(),
+            max_age: None,
+            expiry: Expiry::default(),
+            stiffening: 0.0,
+            coloring: Coloring::default(),
+            color_smoothing: 0.0,
+            color_scales: [0.0; 2],
+            boundary: None,
+            boundary_response: BoundaryResponse::Clamp,
+            gravity: Vec2::ZERO,
+            floor: None,
+            attractors: Vec::new(),
+            obstacles: Vec::new(),
+            hotspots: Vec::new(),
+            noise_field: None,
+            direction_field: None,
+            parameter_maps: Vec::new(),
+            area_preservation: None,
+            budget: GrowthBudget::default(),
+            throttle: SplitThrottle::default(),
+            split: SplitPolicy::default(),
+            jitter: Jitter::default(),
+            reaction_diffusion: None,
+            steps: 0,
+            time: 0.0,
+            pressure_summary: Summary::default(),
+            attraction_summary: Summary::default(),
+            num_particles: 0,
+            alive,
+            free,
+            curves,
+            curve_ids,
+            positions,
+            velocities,
+            ages,
+            pinned,
+            colors,
+            links,
+            pressures,
+            attractions,
+            num_neighbors,
+            index: default_index(),
+            forces: Vec::new(),
+            radii: Vec::new(),
+            max_radius: 0.0,
+            ghosts: Vec::new(),
+            previous_positions: Vec::new(),
+            #[cfg(feature = "gpu")]
+            gpu: None,
+            #[cfg(feature = "scripting")]
+            script: None,
+            #[cfg(feature = "scripting")]
+            script_error: None,
+            seed,
+            rng: Xoshiro256PlusPlus::from_seed(DefaultSeed::seed_from_u64(seed).0),
+        }
+    }
+
+    /// Computes the forces between particles on the GPU from now on, which is much faster for
+    /// large systems. Results differ slightly from the CPU's due to rounding.
+    #[cfg(feature = "gpu")]
+    pub fn enable_gpu(&mut self) -> Result<(), GpuError> {
+        self.gpu = Some(Gpu::new()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "gpu")]
+    pub fn disable_gpu(&mut self) {
+        self.gpu = None;
+    }
+
+    /// Whether forces are computed on the GPU. Turns false if the GPU fails during a step, which
+    /// then continues on the CPU.
+    #[cfg(feature = "gpu")]
+    pub fn uses_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Draws random numbers from `seed` from now on, so that growth resumed from the same state
+    /// takes another course.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Xoshiro256PlusPlus::from_seed(DefaultSeed::seed_from_u64(seed).0);
+    }
+
+    /// Takes over what `other` doesn't save with its state, which is the GPU it computes on.
+    pub(crate) fn keep_runtime_of(&mut self, _other: &mut ParticleSystem) {
+        #[cfg(feature = "gpu")]
+        {
+            self.gpu = _other.gpu.take();
+        }
+    }
+
+    /// The number of times `update()` has been called.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The simulated time so far, see `dt`.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// The number of live particles. Per-particle slices may be longer than this, see `alive()`.
+    pub fn num_particles(&self) -> usize {
+        self.num_particles
+    }
+
+    /// Whether each particle slot holds a live particle or a tombstone left behind by a merge.
+    pub fn alive(&self) -> &[bool] {
+        &self.alive
+    }
+
+    /// Iterates over the indices of all live particles.
+    pub fn particle_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|&(_, &alive)| alive)
+            .map(|(i, _)| i)
+    }
+
+    pub fn curves(&self) -> &[Curve] {
+        &self.curves
+    }
+
+    /// The index into `curves()` of the curve each particle belongs to.
+    pub fn curve_ids(&self) -> &[usize] {
+        &self.curve_ids
+    }
+
+    pub fn positions(&self) -> &[Vec2] {
+        &self.positions
+    }
+
+    pub fn velocities(&self) -> &[Vec2] {
+        &self.velocities
+    }
+
+    /// The simulated time since each particle was added.
+    pub fn ages(&self) -> &[f32] {
+        &self.ages
+    }
+
+    pub fn pinned(&self) -> &[bool] {
+        &self.pinned
+    }
+
+    /// Pins particle `i` in place, or releases it. Pinned particles ignore every force but still
+    /// push the particles around them, anchoring the growth.
+    pub fn set_pinned(&mut self, i: usize, pinned: bool) {
+        if self.alive[i] {
+            self.pinned[i] = pinned;
+        }
+    }
+
+    /// Pins or releases every particle within `radius` of `center` and returns how many there
+    /// were.
+    pub fn set_pinned_near(&mut self, center: Vec2, radius: f32, pinned: bool) -> usize {
+        let near: Vec<usize> = self
+            .particle_indices()
+            .filter(|&i| self.offset(center, self.positions[i]).length() <= radius)
+            .collect();
+        for &i in &near {
+            self.set_pinned(i, pinned);
+        }
+        near.len()
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    /// The neighbors of each particle along its curve. Endpoints of open curves
+    /// have `None` on their free side.
+    pub fn links(&self) -> &Links {
+        &self.links
+    }
+
+    pub fn pressures(&self) -> &[Vec2] {
+        &self.pressures
+    }
+
+    pub fn attractions(&self) -> &[Vec2] {
+        &self.attractions
+    }
+
+    /// How strongly particles got pushed by their neighbors in the last step.
+    pub fn pressure_summary(&self) -> Summary {
+        self.pressure_summary
+    }
+
+    /// How strongly particles got pulled along their curves in the last step.
+    pub fn attraction_summary(&self) -> Summary {
+        self.attraction_summary
+    }
+
+    pub fn num_neighbors(&self) -> &[usize] {
+        &self.num_neighbors
+    }
+
+    /// The `(min, max)` corners of the smallest axis aligned box holding every live particle and
+    /// its copies under the symmetry, or `None` if there are none.
+    pub fn bounding_box(&self) -> Option<(Vec2, Vec2)> {
+        let transforms = self.symmetry_transforms();
+        let points = self.particle_indices().flat_map(|i| {
+            transforms
+                .iter()
+                .map(move |&transform| transform * self.positions[i])
+        });
+        points.fold(None, |bounds, p| match bounds {
+            Some((min, max)) => Some((p.min(min), p.max(max))),
+            None => Some((p, p)),
+        })
+    }
+
+    /// How sharply the curve bends at each particle, from 0 where it is straight to 1 where it
+    /// folds back on itself. Endpoints and removed particles have 0.
+    pub fn curvatures(&self) -> Vec<f32> {
+        (0..self.positions.len())
+            .map(|i| self.curvature(i))
+            .collect()
+    }
+
+    /// The edge passing closest to `p`, no further than `max_distance` away, as the particle it
+    /// starts from and its successor. Searches the spatial index as of the last step, so edges
+    /// between particles added since are only found when close to an older particle.
+    pub fn nearest_edge(&self, p: Vec2, max_distance: f32) -> Option<(usize, usize)> {
+        let mut nearest = None;
+        let mut nearest_distance = max_distance;
+
+        // An edge within reach has an endpoint within half its length more.
+        let radius = max_distance + self.max_edge_length;
+        self.for_each_nearby(p, radius, &mut |i| {
+            if !self.alive[i] {
+                return;
+            }
+
+            for (a, b) in self.links.edges_of(i) {
+                let pa = self.position_near(a, p);
+                let pb = self.position_near(b, pa);
+                let ab = pb - pa;
+                let t = ((p - pa).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                let distance = (p - (pa + ab * t)).length();
+                if distance <= nearest_distance {
+                    nearest = Some((a, b));
+                    nearest_distance = distance;
+                }
+            }
+        });
+
+        nearest
+    }
+
+    /// Inserts a particle into the edge closest to `p`, at the point on it closest to `p`, which
+    /// locally kicks off growth. Returns the new particle, or `None` when no edge is within
+    /// `max_distance`.
+    pub fn inject_at(&mut self, p: Vec2, max_distance: f32) -> Option<usize> {
+        self.index.rebuild(self.influence_radius, &self.positions);
+        let (p0, p1) = self.nearest_edge(p, max_distance)?;
+
+        let a = self.position_near(p0, p);
+        let ab = self.position_near(p1, a) - a;
+        let t = ((p - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+        let i = self.insert_between(p0, p1, self.wrap(a + ab * t));
+        self.debug_assert_links();
+        Some(i)
+    }
+
+    /// Walks the edges of every curve, returning their points in order. Open curves start at
+    /// their first endpoint. With a symmetry, the copies of the curves under it follow them.
+    pub fn polylines(&self) -> Vec<Polyline> {
+        self.polylines_at(&self.positions)
+    }
+
+    /// Like `polylines()`, with the particles at `positions` rather than where they are, e.g. part
+    /// of the way between two steps.
+    pub fn polylines_at(&self, positions: &[Vec2]) -> Vec<Polyline> {
+        let polylines: Vec<Polyline> = self
+            .polyline_indices()
+            .into_iter()
+            .map(|(indices, closed)| Polyline {
+                points: self.curve_points_at(positions, &indices),
+                closed,
+            })
+            .collect();
+        if self.symmetry.is_none() {
+            return polylines;
+        }
+        self.symmetry_transforms()
+            .into_iter()
+            .flat_map(|transform| {
+                polylines.iter().map(move |polyline| Polyline {
+                    points: polyline.points.iter().map(|&p| transform * p).collect(),
+                    closed: polyline.closed,
+                })
+            })
+            .collect()
+    }
+
+    /// The transformations of the symmetry group the growth is replicated under, the identity
+    /// first, or just the identity without a symmetry.
+    pub fn symmetry_transforms(&self) -> Vec<Mat2> {
+        match &self.symmetry {
+            Some(symmetry) => symmetry.transforms(),
+            None => vec![Mat2::IDENTITY],
+        }
+    }
+
+    /// The positions of the particles at `indices`, in order along a curve. On a torus, they are
+    /// unwrapped so that the curve stays continuous across the edges, see `Torus::unwrap()`.
+    pub fn curve_points(&self, indices: &[usize]) -> Vec<Vec2> {
+        self.curve_points_at(&self.positions, indices)
+    }
+
+    /// Like `curve_points()`, with the particles at `positions`.
+    pub fn curve_points_at(&self, positions: &[Vec2], indices: &[usize]) -> Vec<Vec2> {
+        let points: Vec<Vec2> = indices.iter().map(|&i| positions[i]).collect();
+        match &self.torus {
+            Some(torus) => torus.unwrap(&points),
+            None => points,
+        }
+    }
+
+    /// The ends of the edge from particle `i` to `next`, the second one next to the first on a
+    /// torus rather than across the edges from it.
+    pub fn edge(&self, i: usize, next: usize) -> (Vec2, Vec2) {
+        self.edge_at(&self.positions, i, next)
+    }
+
+    /// The positions of the particles a fraction `t` of the way from before the latest step to
+    /// now, for drawing the growth in between steps when it runs slower than the display. Those
+    /// added by the step, or whose slot it reused across the curve, are where they are now, as
+    /// are all of them from `t` = 1 on.
+    pub fn interpolated_positions(&self, t: f32) -> Vec<Vec2> {
+        if t >= 1.0 {
+            return self.positions.clone();
+        }
+        let reach = 2.0 * self.max_edge_length;
+        self.positions
+            .iter()
+            .enumerate()
+            .map(
+                |(i, &p)| match self.previous_positions.get(i).copied().flatten() {
+                    Some(from) => {
+                        let offset = self.offset(from, p);
+                        if offset.length() < reach {
+                            from + offset * t
+                        } else {
+                            p
+                        }
+                    }
+                    None => p,
+                },
+            )
+            .collect()
+    }
+
+    /// Like `edge()`, with the particles at `positions`.
+    pub fn edge_at(&self, positions: &[Vec2], i: usize, next: usize) -> (Vec2, Vec2) {
+        let start = positions[i];
+        (start, self.image_near(positions[next], start))
+    }
+
+    /// The shortest move from `from` to `to`, which on a torus may go around the edges.
+    pub fn offset(&self, from: Vec2, to: Vec2) -> Vec2 {
+        self.image_near(to, from) - from
+    }
+
+    /// The copy of `p` closest to `near`, which is `p` itself unless space wraps around.
+    fn image_near(&self, p: Vec2, near: Vec2) -> Vec2 {
+        match &self.torus {
+            Some(torus) => near + torus.offset(near, p),
+            None => p,
+        }
+    }
+
+    /// The copy of the position of particle `i` closest to `near`.
+    fn position_near(&self, i: usize, near: Vec2) -> Vec2 {
+        self.image_near(self.positions[i], near)
+    }
+
+    /// `p` moved into the torus, if any.
+    fn wrap(&self, p: Vec2) -> Vec2 {
+        match &self.torus {
+            Some(torus) => torus.wrap(p),
+            None => p,
+        }
+    }
+
+    /// Calls `f` with every particle that may lie within `radius` of `center`, searching around
+    /// its copies across the edges too on a torus.
+    fn for_each_nearby(&self, center: Vec2, radius: f32, f: &mut dyn FnMut(usize)) {
+        match &self.torus {
+            Some(torus) => {
+                for image in torus.images(center, radius) {
+                    self.index.for_each_candidate(image, radius, f);
+                }
+            }
+            None => self.index.for_each_candidate(center, radius, f),
+        }
+    }
+
+    /// Like `polylines()`, but with the indices of the particles instead of their positions, and
+    /// whether each curve is closed.
+    pub fn polyline_indices(&self) -> Vec<(Vec<usize>, bool)> {
+        let mut visited = vec![false; self.positions.len()];
+        let mut polylines = Vec::new();
+
+        for i in self.particle_indices() {
+            if visited[i] {
+                continue;
+            }
+
+            let (indices, _) = self.links.walk(i);
+            for &j in &indices {
+                visited[j] = true;
+            }
+
+            let closed = !self.curves[self.curve_ids[i]].open;
+            polylines.push((indices, closed));
+        }
+
+        polylines
+    }
+
+    fn add_particle(
+        &mut self,
+        curve_id: usize,
+        position: Vec2,
+        color: Color,
+        pressure: Vec2,
+        attraction: Vec2,
+    ) -> usize {
+        self.num_particles += 1;
+
+        if let Some(index) = self.free.pop() {
+            self.alive[index] = true;
+            self.curve_ids[index] = curve_id;
+            self.positions[index] = position;
+            self.velocities[index] = Vec2::ZERO;
+            self.ages[index] = 0.0;
+            self.pinned[index] = false;
+            self.colors[index] = color;
+            self.pressures[index] = pressure;
+            self.attractions[index] = attraction;
+            self.num_neighbors[index] = 0;
+            return index;
+        }
+
+        self.alive.push(true);
+        self.curve_ids.push(curve_id);
+        self.positions.push(position);
+        self.velocities.push(Vec2::ZERO);
+        self.ages.push(0.0);
+        self.pinned.push(false);
+        self.colors.push(color);
+        self.links.push();
+        self.pressures.push(pressure);
+        self.attractions.push(attraction);
+        self.num_neighbors.push(0);
+        self.positions.len() - 1
+    }
+
+    /// Marks particle `index` as removed, once it's been taken out of its curve.
+    fn remove_particle(&mut self, index: usize) {
+        debug_assert_eq!(self.links.get(index), Link::default());
+        self.alive[index] = false;
+        self.pressures[index] = vec2(0.0, 0.0);
+        self.attractions[index] = vec2(0.0, 0.0);
+        self.num_neighbors[index] = 0;
+        self.free.push(index);
+        self.num_particles -= 1;
+    }
+
+    /// States saved before some per-particle properties were tracked don't have them, so they
+    /// start out at their defaults.
+    pub(crate) fn fill_untracked(&mut self) {
+        let len = self.positions.len();
+        self.velocities.resize(len, Vec2::ZERO);
+        self.ages.resize(len, 0.0);
+        self.pinned.resize(len, false);
+    }
+
+    /// Adds a ring with a wavy radius, see `SpawnShape::Ring`.
+    pub fn spawn_particles(&mut self, num_particles: usize, spawn_radius: f32) {
+        self.spawn(&SpawnShape::Ring, num_particles, spawn_radius);
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Xoshiro256PlusPlus {
+        &mut self.rng
+    }
+
+    /// Adds a new curve going through `points` and returns its index. Closed curves link the
+    /// last point back to the first one.
+    pub fn add_curve(&mut self, points: &[Vec2], open: bool) -> usize {
+        let curve_id = self.curves.len();
+        self.curves.push(Curve {
+            open,
+            steps: 0,
+            frozen: false,
+        });
+
+        let num_points = points.len();
+        let mut indices = Vec::with_capacity(num_points);
+
+        for &position in points {
+            let l = self.rng.gen::<f32>() * 0.8 + 0.1;
+            let color = [
+                l,
+                l - self.rng.gen::<f32>() * 0.2,
+                l - self.rng.gen::<f32>() * 0.1,
+                1.0,
+            ];
+
+            let pressure = vec2(0.0, 0.0);
+            let attraction = vec2(0.0, 0.0);
+
+            indices.push(self.add_particle(curve_id, position, color, pressure, attraction));
+        }
+
+        // Recycled slots mean the new particles aren't necessarily contiguous, so they are only
+        // linked once all of them have an index.
+        for i in 0..num_points {
+            if !(open && i + 1 == num_points) {
+                self.links
+                    .link(indices[i], indices[cyclic::offset(i, 1, num_points)]);
+            }
+        }
+        self.debug_assert_links();
+
+        curve_id
+    }
+
+    /// Computes the forces acting on particle `i`. Only reads the current state, so all particles
+    /// can be processed in parallel.
+    fn compute_forces(&self, i: usize) -> Forces {
+        if !self.alive[i] {
+            return Forces::default();
+        }
+
+        let neighbors = self.get_neighbors_of_particle(i);
+
+        Forces {
+            num_neighbors: neighbors.len(),
+            attraction: self.attraction_of(i),
+            pressure: self.pressure_of(i, &neighbors),
+            external: self.external_force_of(i),
+        }
+    }
+
+    /// The pull on particle `i` toward its neighbors along the curve.
+    fn attraction_of(&self, i: usize) -> Vec2 {
+        // Endpoints of open curves only have one neighbor to be pulled toward. They move
+        // halfway to it, like an interior particle would toward the midpoint of its edges.
+        let p = self.positions[i];
+        match self.links.get(i) {
+            Link {
+                prev: Some(b0),
+                next: Some(b1),
+            } => (self.position_near(b0, p) + self.position_near(b1, p)) / 2.0 - p,
+            Link { prev: Some(b), .. } | Link { next: Some(b), .. } => {
+                (self.position_near(b, p) - p) / 2.0
+            }
+            Link { .. } => vec2(0.0, 0.0),
+        }
+    }
+
+    /// The pull on particle `i` toward the midpoint of the particles `BENDING_SPAN` links away on
+    /// either side. Attraction only evens out each particle against its direct neighbors, which
+    /// leaves the curve free to fold sharply over a few edges, while this straightens it out over
+    /// the wider span.
+    fn bending_of(&self, i: usize) -> Vec2 {
+        let (mut prev, mut next) = (Some(i), Some(i));
+        for _ in 0..BENDING_SPAN {
+            prev = prev.and_then(|j| self.links.get(j).prev);
+            next = next.and_then(|j| self.links.get(j).next);
+        }
+        match (prev, next) {
+            (Some(prev), Some(next)) if prev != i && next != i => {
+                let p = self.positions[i];
+                (self.position_near(prev, p) + self.position_near(next, p)) / 2.0 - p
+            }
+            _ => Vec2::ZERO,
+        }
+    }
+
+    /// The pull of the springs along the edges of particle `i`.
+    fn springs_of(&self, i: usize, springs: &Springs) -> Vec2 {
+        let Link { prev, next } = self.links.get(i);
+        let mut force = Vec2::ZERO;
+        for j in prev.into_iter().chain(next) {
+            let offset = self.offset(self.positions[i], self.positions[j]);
+            let length = offset.length();
+            if length <= f32::EPSILON {
+                continue;
+            }
+            let direction = offset / length;
+            let stretch = length - springs.rest_length;
+            let separation = (self.velocities[j] - self.velocities[i]).dot(direction);
+            force += direction * (springs.stiffness * stretch + springs.damping * separation);
+        }
+        force
+    }
+
+    /// The push on particle `i` away from the given neighbors, at the positions they are seen at.
+    fn pressure_of(&self, i: usize, neighbors: &[(usize, Vec2)]) -> Vec2 {
+        let mut pressure = vec2(0.0, 0.0);
+        for &(j, position) in neighbors {
+            let radius = self.pair_radius(i, j);
+            let push = (self.positions[i] - position) / (radius * 0.5);
+            if self.curve_ids[i] == self.curve_ids[j] {
+                pressure += push;
+            } else {
+                pressure += push * self.inter_curve_pressure;
+            }
+        }
+
+        pressure.clamp_length_max(2.0)
+    }
+
+    /// The forces on particle `i` that don't come from other particles, along with the bending
+    /// resistance and springs, which the GPU doesn't compute either.
+    fn external_force_of(&self, i: usize) -> Vec2 {
+        let mut external = self
+            .attractors
+            .iter()
+            .map(|attractor| attractor.force_at(self.positions[i]))
+            .fold(self.gravity, |sum, force| sum + force);
+        for obstacle in &self.obstacles {
+            external += obstacle.force_at(self.positions[i]);
+        }
+        if let Some(noise_field) = &self.noise_field {
+            external += noise_field.force_at(self.positions[i], self.time);
+        }
+        if self.bending_stiffness != 0.0 {
+            external += self.bending_of(i) * self.bending_stiffness;
+        }
+        if let Some(springs) = &self.springs {
+            external += self.springs_of(i, springs);
+        }
+        external
+    }
+
+    /// How the probability of splitting an edge at `p` is scaled by the hotspots. Long edges
+    /// split whatever their probability above 1, so rather than raising it inside hotspots, it is
+    /// lowered everywhere else by the largest factor they can combine to.
+    fn hotspot_scale(&self, p: Vec2) -> f32 {
+        let (scale, max_scale) =
+            self.hotspots
+                .iter()
+                .fold((1.0, 1.0), |(scale, max_scale), hotspot| {
+                    (
+                        scale * hotspot.scale_at(p, self.time),
+                        max_scale * hotspot.factor.max(1.0),
+                    )
+                });
+        scale / max_scale
+    }
+
+    /// The product of the maps of `parameter` at `p`, or 1 without any.
+    fn parameter_scale(&self, parameter: MappedParameter, p: Vec2) -> f32 {
+        self.parameter_maps
+            .iter()
+            .filter(|map| map.parameter == parameter)
+            .fold(1.0, |scale, map| scale * map.scale_at(p))
+    }
+
+    /// Computes the forces of every particle, on the GPU if enabled. Falls back to the CPU for
+    /// good if the GPU fails. The GPU only handles a uniform influence radius and the same
+    /// pressure between all curves, so the CPU takes over for the steps where they differ.
+    /// Overwrites the buffer of the previous step, which is taken out of `self.forces` and needs
+    /// to be put back.
+    fn compute_all_forces(&mut self) -> Vec<Forces> {
+        let mut all_forces = std::mem::take(&mut self.forces);
+        all_forces.clear();
+
+        #[cfg(feature = "gpu")]
+        let uniform = self.inter_curve_pressure == 1.0
+            && self.adaptive_radius.is_uniform()
+            && self.torus.is_none()
+            && self.symmetry.is_none()
+            && !self
+                .parameter_maps
+                .iter()
+                .any(|map| map.parameter == MappedParameter::InfluenceRadius);
+        #[cfg(feature = "gpu")]
+        if let (Some(gpu), true) = (&mut self.gpu, uniform) {
+            match gpu.compute(
+                &self.positions,
+                &self.links,
+                &self.alive,
+                self.influence_radius,
+            ) {
+                Ok(forces) => {
+                    all_forces.par_extend(forces.into_par_iter().enumerate().map(|(i, forces)| {
+                        Forces {
+                            num_neighbors: forces.num_neighbors as usize,
+                            attraction: forces.attraction(),
+                            pressure: forces.pressure(),
+                            external: self.external_force_of(i),
+                        }
+                    }));
+                    return all_forces;
+                }
+                Err(_) => self.gpu = None,
+            }
+        }
+
+        // Forces are all computed from the same snapshot of the positions before any of them is
+        // applied, so the result doesn't depend on the order particles are visited in.
+        all_forces.par_extend(
+            (0..self.positions.len())
+                .into_par_iter()
+                .map(|i| self.compute_forces(i)),
+        );
+        all_forces
+    }
+
+    /// The random push of the jitter on every particle for this step, or none without it. It is
+    /// drawn once and pushes through every sub-step alike, so that splitting the step into more of
+    /// them doesn't change how far the particles wander.
+    fn jitter_kicks(&mut self) -> Vec<Vec2> {
+        let jitter = self.jitter.amplitude_at(self.time);
+        if jitter <= 0.0 {
+            return Vec::new();
+        }
+        let mut kicks = vec![Vec2::ZERO; self.positions.len()];
+        for (kick, &alive) in kicks.iter_mut().zip(&self.alive) {
+            if alive {
+                let (sin, cos) = (self.rng.gen::<f32>() * 2.0 * PI).sin_cos();
+                // Uniform over the disc rather than bunched up in the middle.
+                let length = jitter * self.rng.gen::<f32>().sqrt();
+                *kick = vec2(cos, sin) * length;
+            }
+        }
+        kicks
+    }
+
+    /// Moves the particles by the forces on them and the jitter's `kicks` over `dt`, the part of
+    /// a step that sub-steps repeat with a fraction of it each.
+    fn move_particles(&mut self, dt: f32, kicks: &[Vec2]) {
+        self.index.rebuild(self.influence_radius, &self.positions);
+        self.fill_untracked();
+        self.update_radii();
+        self.ghosts = self.symmetry_transforms().split_off(1);
+
+        let mut forces = self.compute_all_forces();
+        if let Some(area_preservation) = &self.area_preservation {
+            for (indices, closed) in self.polyline_indices() {
+                if !closed {
+                    continue;
+                }
+                let points = self.curve_points(&indices);
+                for (&i, force) in indices.iter().zip(area_preservation.forces(&points)) {
+                    forces[i].external += force;
+                }
+            }
+        }
+        if let Some(rd) = &self.reaction_diffusion {
+            for i in self.particle_indices() {
+                forces[i].external += rd.force_at(self.positions[i]);
+            }
+        }
+        for (forces, &kick) in forces.iter_mut().zip(kicks) {
+            forces.external += kick;
+        }
+
+        #[cfg(feature = "scripting")]
+        self.add_script_forces(&mut forces);
+
+        for (i, &forces) in forces.iter().enumerate() {
+            if !self.alive[i] {
+                continue;
+            }
+
+            let Forces {
+                num_neighbors,
+                attraction,
+                pressure,
+                external,
+            } = forces;
+            let pressure = match &self.direction_field {
+                Some(field) => field.bias(pressure, self.positions[i], self.time),
+                None => pressure,
+            };
+
+            self.num_neighbors[i] = num_neighbors;
+            self.attractions[i] = attraction;
+            self.pressures[i] = pressure;
+
+            let mobility = self.mobility(i);
+            if mobility <= 0.0 {
+                self.velocities[i] = Vec2::ZERO;
+                continue;
+            }
+
+            let from = self.positions[i];
+            let to = match self.integration {
+                Integration::Overdamped => {
+                    from + attraction * self.attraction_weight * dt
+                        + pressure * self.pressure_weight * dt
+                        + external * dt
+                }
+                Integration::Inertial { damping } => {
+                    let acceleration = attraction * self.attraction_weight
+                        + pressure * self.pressure_weight
+                        + external;
+                    let velocity = (self.velocities[i] + acceleration * dt)
+                        * (1.0 - damping.clamp(0.0, 1.0)).powf(dt);
+                    from + velocity * dt
+                }
+            };
+            let scale = mobility * self.parameter_scale(MappedParameter::ForceStrength, from);
+            let to = if scale != 1.0 {
+                from + (to - from) * scale
+            } else {
+                to
+            };
+            let to = self.confine(from, to);
+            if !(self.prevent_crossings && self.crosses_curve(i, to)) {
+                self.positions[i] = self.wrap(to);
+            }
+
+            // Whatever stopped or deflected the particle took its momentum along.
+            if let Integration::Inertial { .. } = self.integration {
+                if dt > 0.0 {
+                    self.velocities[i] = self.offset(from, self.positions[i]) / dt;
+                }
+            }
+        }
+        self.forces = forces;
+    }
+
+    pub fn update(&mut self) {
+        self.previous_positions.clear();
+        self.previous_positions.extend(
+            self.positions
+                .iter()
+                .zip(&self.alive)
+                .map(|(&p, &alive)| alive.then_some(p)),
+        );
+        #[cfg(feature = "scripting")]
+        self.run_script_step();
+        if let Some(mut rd) = self.reaction_diffusion.take() {
+            rd.step(self.particle_indices().map(|i| self.positions[i]));
+            self.reaction_diffusion = Some(rd);
+        }
+        if self.index.kind() != self.neighbor_index {
+            self.index = self.neighbor_index.build();
+        }
+        let sub_steps = self.sub_steps.max(1);
+        let dt = self.dt / sub_steps as f32;
+        let kicks = self.jitter_kicks();
+        for _ in 0..sub_steps {
+            self.move_particles(dt, &kicks);
+        }
+
+        self.pressure_summary =
+            Summary::of(self.particle_indices().map(|i| self.pressures[i].length()));
+        self.attraction_summary = Summary::of(
+            self.particle_indices()
+                .map(|i| self.attractions[i].length()),
+        );
+        self.update_colors();
+
+        // Splitting adds particles at the end or in the slots of removed ones, which may come
+        // later in this pass. They are only considered from the next step on, once their
+        // neighbors are counted.
+        let len = self.links.len();
+        let mut born = vec![false; len];
+        let first = match self.throttle.max_splits_per_step {
+            Some(_) if len > 0 => self.rng.gen_range(0..len),
+            _ => 0,
+        };
+        let mut splits = 0;
+        for p0 in (first..len).chain(0..first) {
+            if self
+                .throttle
+                .max_splits_per_step
+                .is_some_and(|max| splits >= max)
+            {
+                break;
+            }
+            if born[p0] {
+                continue;
+            }
+            let p1 = match self.links.next(p0) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            if self.is_frozen(p0) && self.is_frozen(p1) {
+                continue;
+            }
+
+            let length = self.offset(self.positions[p0], self.positions[p1]).length();
+            let neighbors = self.num_neighbors[p0] + self.num_neighbors[p1];
+            if neighbors >= self.max_split_neighbors || length <= self.max_edge_length {
+                continue;
+            }
+
+            let curvature = (self.curvature(p0) + self.curvature(p1)) / 2.0;
+            let edge = SplitCandidate {
+                length,
+                neighbors,
+                curvature,
+                age: self.ages[p0].min(self.ages[p1]),
+            };
+            if !self.split.allows(&edge) {
+                continue;
+            }
+            let midpoint = self.midpoint(p0, p1);
+            let probability = self.split.probability
+                * (1.0 - self.curvature_bias * (1.0 - curvature))
+                * self.parameter_scale(MappedParameter::SplitRate, midpoint)
+                * self.throttle.scale(self.num_particles)
+                * self.hotspot_scale(midpoint)
+                * self
+                    .reaction_diffusion
+                    .as_ref()
+                    .map_or(1.0, |rd| rd.split_scale(midpoint));
+            if probability >= 1.0 || self.rng.gen::<f32>() < probability {
+                if let Some(born) = born.get_mut(self.split_at(p0, p1)) {
+                    *born = true;
+                }
+                splits += 1;
+            }
+        }
+
+        for p0 in 0..self.links.len() {
+            let p1 = match self.links.next(p0) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            let length = self.offset(self.positions[p0], self.positions[p1]).length();
+            // Merging moves the remaining particle, so fixed ones are left alone.
+            if length < self.min_edge_length && !self.is_fixed(p0) && !self.is_fixed(p1) {
+                self.merge_at(p0, p1);
+            }
+        }
+
+        self.reconnect();
+        // Last, so that the particles splits add are kept apart too.
+        if let Some(separation) = self.separation {
+            self.separate(&separation);
+        }
+
+        for i in 0..self.ages.len() {
+            if self.alive[i] {
+                self.ages[i] += self.dt;
+            }
+        }
+        if let (Some(max_age), Expiry::Kill) = (self.max_age, self.expiry) {
+            for i in 0..self.ages.len() {
+                if self.alive[i] && !self.pinned[i] && self.ages[i] > max_age {
+                    self.dissolve(i);
+                }
+            }
+        }
+
+        self.spend_budget();
+        self.debug_assert_links();
+        self.steps += 1;
+        self.time += self.dt;
+    }
+
+    /// Lets the script tune the parameters for the coming step. A failing script is removed, and
+    /// its error kept for `take_script_error()`.
+    #[cfg(feature = "scripting")]
+    fn run_script_step(&mut self) {
+        if let Some(script) = self.script.take() {
+            match script.step(self) {
+                Ok(()) => self.script = Some(script),
+                Err(err) => self.script_error = Some(err),
+            }
+        }
+    }
+
+    /// Adds the displacements the script gives each particle to their external forces.
+    #[cfg(feature = "scripting")]
+    fn add_script_forces(&mut self, forces: &mut [Forces]) {
+        let script = match &self.script {
+            Some(script) if script.has_force() => script,
+            _ => return,
+        };
+        let curvatures = self.curvatures();
+        let indices: Vec<usize> = self.particle_indices().collect();
+        let result: Result<Vec<(usize, Vec2)>, ScriptError> = indices
+            .into_par_iter()
+            .map(|i| Ok((i, script.force(self, curvatures[i], i)?)))
+            .collect();
+        match result {
+            Ok(script_forces) => {
+                for (i, force) in script_forces {
+                    forces[i].external += force;
+                }
+            }
+            Err(err) => {
+                self.script = None;
+                self.script_error = Some(err);
+            }
+        }
+    }
+
+    /// Why the script stopped running, once.
+    #[cfg(feature = "scripting")]
+    pub fn take_script_error(&mut self) -> Option<ScriptError> {
+        self.script_error.take()
+    }
+
+    /// Counts the step toward the growth of every curve still growing, freezing those that
+    /// reached a limit of `budget`.
+    fn spend_budget(&mut self) {
+        for curve in self.curves.iter_mut().filter(|curve| !curve.frozen) {
+            curve.steps += 1;
+        }
+        if self.budget == GrowthBudget::default() {
+            return;
+        }
+
+        for (indices, closed) in self.polyline_indices() {
+            let curve_id = self.curve_ids[indices[0]];
+            if self.curves[curve_id].frozen {
+                continue;
+            }
+            let points = self.curve_points(&indices);
+            if self
+                .budget
+                .is_spent(&points, closed, self.curves[curve_id].steps)
+            {
+                self.curves[curve_id].frozen = true;
+            }
+        }
+    }
+
+    /// Whether every curve reached a limit of `budget`, so updating changes nothing anymore.
+    /// Curves left without particles by reconnections don't count.
+    pub fn is_finished(&self) -> bool {
+        self.num_particles > 0
+            && self
+                .particle_indices()
+                .all(|i| self.curves[self.curve_ids[i]].frozen)
+    }
+
+    /// Reconnects the closed curves wherever they press into each other, see
+    /// `reconnect_distance`. Each particle reconnects at most once per step.
+    fn reconnect(&mut self) {
+        let distance = match self.reconnect_distance {
+            Some(distance) => distance,
+            None => return,
+        };
+
+        let mut touched = vec![false; self.positions.len()];
+        for a in 0..self.positions.len() {
+            let a_next = match self.links.next(a) {
+                Some(next) if self.can_reconnect(a, next, &touched) => next,
+                _ => continue,
+            };
+
+            // Only edges running in opposite directions reconnect, as facing sides of two curves
+            // or folds do, so the new edges bridge the gap without crossing each other.
+            let mut partner = None;
+            self.for_each_nearby(self.positions[a], distance, &mut |b| {
+                if partner.is_some() || !self.alive[b] {
+                    return;
+                }
+                let b_next = match self.links.next(b) {
+                    Some(next) if self.can_reconnect(b, next, &touched) => next,
+                    _ => return,
+                };
+                let (pa, pb) = (self.positions[a], self.positions[b]);
+                let facing = self
+                    .offset(pa, self.positions[a_next])
+                    .dot(self.offset(pb, self.positions[b_next]))
+                    < 0.0;
+                let close = self.offset(pa, pb).length() < distance;
+                let same_curve = self.curve_ids[a] == self.curve_ids[b];
+                if facing && close && !(same_curve && self.links.is_within(a, b, RECONNECT_GAP)) {
+                    partner = Some((b, b_next));
+                }
+            });
+
+            if let Some((b, b_next)) = partner {
+                self.reconnect_at(a, b);
+                for i in [a, a_next, b, b_next] {
+                    touched[i] = true;
+                }
+            }
+        }
+    }
+
+    /// Whether the edge from `a` to its successor `next` may reconnect this step.
+    fn can_reconnect(&self, a: usize, next: usize, touched: &[bool]) -> bool {
+        self.alive[a]
+            && !self.curves[self.curve_ids[a]].open
+            && !touched[a]
+            && !touched[next]
+            && !self.is_fixed(a)
+            && !self.is_fixed(next)
+    }
+
+    /// Swaps the successors of `a` and `b`, splitting their curve in two when they're on the same
+    /// one, the part through `b` becoming a new curve, or joining their curves otherwise.
+    fn reconnect_at(&mut self, a: usize, b: usize) {
+        let (curve_a, curve_b) = (self.curve_ids[a], self.curve_ids[b]);
+        self.links.swap_next(a, b);
+
+        let (start, curve_id) = if curve_a == curve_b {
+            self.curves.push(self.curves[curve_a]);
+            (b, self.curves.len() - 1)
+        } else {
+            // The curve of `b` is left without particles.
+            (a, curve_a)
+        };
+        for i in self.links.walk(start).0 {
+            self.curve_ids[i] = curve_id;
+        }
+    }
+
+    fn update_colors(&mut self) {
+        let oldest = self
+            .particle_indices()
+            .map(|i| self.ages[i])
+            .fold(0.0, f32::max);
+        let oldest = self.max_age.unwrap_or(oldest).max(f32::EPSILON);
+
+        if self.coloring == Coloring::StableForces {
+            let targets = [self.pressure_summary.p95, self.attraction_summary.p95];
+            let smoothing = self.color_smoothing.clamp(0.0, 0.999);
+            for (scale, target) in self.color_scales.iter_mut().zip(targets) {
+                *scale = if *scale > 0.0 {
+                    *scale * smoothing + target * (1.0 - smoothing)
+                } else {
+                    target
+                };
+            }
+        }
+
+        for i in 0..self.positions.len() {
+            if !self.alive[i] {
+                continue;
+            }
+
+            self.colors[i] = match self.coloring {
+                Coloring::Forces => {
+                    let p =
+                        self.pressures[i].length() / self.pressure_summary.max.max(f32::EPSILON);
+                    let a = self.attractions[i].length()
+                        / self.attraction_summary.max.max(f32::EPSILON);
+                    [p, a, p * a + 0.1, 1.0]
+                }
+                Coloring::StableForces => {
+                    let [pressure_scale, attraction_scale] = self.color_scales;
+                    let p =
+                        (self.pressures[i].length() / pressure_scale.max(f32::EPSILON)).min(1.0);
+                    let a = (self.attractions[i].length() / attraction_scale.max(f32::EPSILON))
+                        .min(1.0);
+                    [p, a, p * a + 0.1, 1.0]
+                }
+                Coloring::Age => {
+                    let t = (self.ages[i] / oldest).min(1.0);
+                    let (young, old) = ([1.0, 0.9, 0.6], [0.15, 0.2, 0.4]);
+                    let c = |k: usize| young[k] + (old[k] - young[k]) * t;
+                    [c(0), c(1), c(2), 1.0]
+                }
+            };
+        }
+    }
+
+    /// Whether particle `i` expired and stopped moving, see `Expiry::Freeze`, or its curve spent
+    /// its growth budget.
+    fn is_frozen(&self, i: usize) -> bool {
+        (self.expiry == Expiry::Freeze
+            && self.max_age.is_some_and(|max_age| self.ages[i] > max_age))
+            || self.curves[self.curve_ids[i]].frozen
+    }
+
+    /// Whether particle `i` is pinned or frozen.
+    fn is_fixed(&self, i: usize) -> bool {
+        self.pinned[i] || self.is_frozen(i)
+    }
+
+    /// Where a particle moving from `from` to `to` ends up once the boundary, floor and obstacles
+    /// stopped it.
+    fn confine(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let to = match &self.boundary {
+            Some(boundary) => boundary.confine(from, to, self.boundary_response),
+            None => to,
+        };
+        let to = match &self.floor {
+            Some(floor) => floor.confine(from, to),
+            None => to,
+        };
+        self.obstacles
+            .iter()
+            .fold(to, |to, obstacle| obstacle.block(from, to))
+    }
+
+    /// Moves apart the unlinked particles closer than the separation distance, with Jacobi
+    /// iterations: the moves of each pass are all worked out from the same positions.
+    fn separate(&mut self, separation: &Separation) {
+        for _ in 0..separation.iterations {
+            self.index.rebuild(separation.distance, &self.positions);
+            let moves: Vec<Vec2> = (0..self.positions.len())
+                .into_par_iter()
+                .map(|i| self.separation_of(i, separation.distance))
+                .collect();
+            for (i, &step) in moves.iter().enumerate() {
+                if step == Vec2::ZERO {
+                    continue;
+                }
+                let from = self.positions[i];
+                self.positions[i] = self.wrap(self.confine(from, from + step));
+                if let Integration::Inertial { .. } = self.integration {
+                    if self.dt > 0.0 {
+                        let moved = self.offset(from, self.positions[i]);
+                        self.velocities[i] += moved / self.dt;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The move taking particle `i` out of the unlinked particles within `distance`, averaged
+    /// over them so that crowded particles don't overshoot.
+    fn separation_of(&self, i: usize, distance: f32) -> Vec2 {
+        if !self.alive[i] || self.mobility(i) <= 0.0 {
+            return Vec2::ZERO;
+        }
+
+        let position = self.positions[i];
+        let link = self.links.get(i);
+        let (mut sum, mut count) = (Vec2::ZERO, 0);
+        self.for_each_nearby(position, distance, &mut |j| {
+            if j == i || !self.alive[j] || link.prev == Some(j) || link.next == Some(j) {
+                return;
+            }
+            let offset = position - self.position_near(j, position);
+            let length = offset.length();
+            if length >= distance || length <= f32::EPSILON {
+                return;
+            }
+            // Fixed particles don't give way, leaving the whole overlap to this one.
+            let share = if self.mobility(j) <= 0.0 { 1.0 } else { 0.5 };
+            sum += offset / length * (distance - length) * share;
+            count += 1;
+        });
+
+        if count > 0 {
+            sum / count as f32
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    /// How much of its displacement particle `i` gets to move by, from 1 when young down to 0 once
+    /// frozen or pinned, see `stiffening`.
+    fn mobility(&self, i: usize) -> f32 {
+        if self.is_fixed(i) {
+            0.0
+        } else if self.stiffening > 0.0 {
+            (1.0 - self.stiffening.min(1.0)).powf(self.ages[i])
+        } else {
+            1.0
+        }
+    }
+
+    /// Removes particle `i` from its curve, linking its neighbors to each other instead. Closed
+    /// curves are never reduced below a triangle, nor open ones below a single segment.
+    fn dissolve(&mut self, i: usize) {
+        let too_small = match self.links.get(i) {
+            Link {
+                prev: Some(prev),
+                next: Some(next),
+            } => prev == next || self.links.next(next) == Some(prev),
+            Link {
+                prev: Some(other),
+                next: None,
+            } => self.links.prev(other).is_none(),
+            Link {
+                prev: None,
+                next: Some(other),
+            } => self.links.next(other).is_none(),
+            Link { .. } => false,
+        };
+        if too_small {
+            return;
+        }
+
+        self.links.remove(i);
+        self.remove_particle(i);
+    }
+
+    /// Panics in debug builds if the curves came apart: links that aren't mutual, removed
+    /// particles still on a curve, curves joined to each other, or closed curves with an end.
+    fn debug_assert_links(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        if let Err(err) = self.links.validate() {
+            panic!("invalid links: {}", err);
+        }
+        for i in 0..self.links.len() {
+            let link = self.links.get(i);
+            if !self.alive[i] {
+                assert_eq!(link, Link::default(), "removed particle {} is linked", i);
+                continue;
+            }
+            for j in link.prev.into_iter().chain(link.next) {
+                assert!(self.alive[j], "{} is linked to removed particle {}", i, j);
+                assert_eq!(self.curve_ids[i], self.curve_ids[j], "{} links curves", i);
+            }
+            if !self.curves[self.curve_ids[i]].open {
+                assert!(
+                    link.prev.is_some() && link.next.is_some(),
+                    "closed curve ends at {}",
+                    i
+                );
+            }
+        }
+    }
+
+    /// Whether moving particle `i` to `position` would make one of its edges cross another edge.
+    ///
+    /// Candidates come from the index built at the start of the step. Particles move much less
+    /// than the influence radius per step, so padding the query by it still finds every edge.
+    fn crosses_curve(&self, i: usize, position: Vec2) -> bool {
+        let at = |j: usize| if j == i { position } else { self.positions[j] };
+
+        self.links.edges_of(i).any(|(a, b)| {
+            let pa = at(a);
+            let pb = self.image_near(at(b), pa);
+            let radius = (pb - pa).length() / 2.0 + self.influence_radius;
+
+            let mut crosses = false;
+            self.for_each_nearby((pa + pb) / 2.0, radius, &mut |j| {
+                let k = match self.links.next(j) {
+                    Some(k) if self.alive[j] => k,
+                    _ => return,
+                };
+                if crosses || j == a || j == b || k == a || k == b {
+                    return;
+                }
+                let pj = self.image_near(at(j), pa);
+                let pk = self.image_near(at(k), pj);
+                crosses = segments_intersect(pa, pb, pj, pk);
+            });
+            crosses
+        })
+    }
+
+    /// How sharply the curve turns at particle `i`, from 0 when straight to 1 when it folds back
+    /// on itself. Endpoints count as straight.
+    fn curvature(&self, i: usize) -> f32 {
+        let (prev, next) = match self.links.get(i) {
+            Link {
+                prev: Some(prev),
+                next: Some(next),
+            } => (prev, next),
+            _ => return 0.0,
+        };
+
+        let p = self.positions[i];
+        let incoming = p - self.position_near(prev, p);
+        let outgoing = self.position_near(next, p) - p;
+        incoming.angle_between(outgoing).abs() / PI
+    }
+
+    /// The middle of the edge between particles `p0` and `p1`.
+    fn midpoint(&self, p0: usize, p1: usize) -> Vec2 {
+        let p = self.positions[p0];
+        self.wrap((p + self.position_near(p1, p)) / 2.0)
+    }
+
+    /// Adds a particle at the midpoint of the edge from `p0` to its successor `p1`. Returns its
+    /// index.
+    fn split_at(&mut self, p0: usize, p1: usize) -> usize {
+        let position = self.midpoint(p0, p1);
+        self.insert_between(p0, p1, position)
+    }
+
+    /// Adds a particle at `position` into the edge from `p0` to its successor `p1`, blending
+    /// their colors and velocities. Returns its index.
+    fn insert_between(&mut self, p0: usize, p1: usize, position: Vec2) -> usize {
+        let (c0, c1) = (self.colors[p0], self.colors[p1]);
+        let color = [
+            (c0[0] + c1[0]) / 2.0,
+            (c0[1] + c1[1]) / 2.0,
+            (c0[2] + c1[2]) / 2.0,
+            (c0[3] + c1[3]) / 2.0,
+        ];
+        let pressure = vec2(0.0, 0.0);
+        let attraction = vec2(0.0, 0.0);
+
+        debug_assert_eq!(self.links.next(p0), Some(p1));
+        let curve_id = self.curve_ids[p0];
+        let new_index = self.add_particle(curve_id, position, color, pressure, attraction);
+        self.velocities[new_index] = (self.velocities[p0] + self.velocities[p1]) / 2.0;
+        self.links.insert_after(p0, new_index);
+        new_index
+    }
+
+    /// Collapses the edge from `p0` to its successor `p1`, moving `p0` to the edge midpoint and
+    /// removing `p1`. Curves are never reduced below a triangle, or a single segment when open.
+    fn merge_at(&mut self, p0: usize, p1: usize) {
+        let after = self.links.next(p1);
+
+        let is_triangle = after.and_then(|p2| self.links.next(p2)) == Some(p0);
+        let is_segment = self.links.prev(p0).is_none() && after.is_none();
+        if after == Some(p0) || is_triangle || is_segment {
+            return;
+        }
+
+        self.positions[p0] = self.midpoint(p0, p1);
+        self.velocities[p0] = (self.velocities[p0] + self.velocities[p1]) / 2.0;
+        self.links.remove(p1);
+        self.remove_particle(p1);
+    }
+
+    /// The influence radius of particle `i` for this step, see `AdaptiveRadius`.
+    fn radius_of(&self, i: usize) -> f32 {
+        let position = self.positions[i];
+        self.influence_radius
+            * self.parameter_scale(MappedParameter::InfluenceRadius, position)
+            * self.adaptive_radius.scale(self.ages[i], self.curvature(i))
+    }
+
+    /// Works out the influence radius of every particle for this step.
+    fn update_radii(&mut self) {
+        let mut radii = std::mem::take(&mut self.radii);
+        radii.clear();
+        radii.par_extend((0..self.positions.len()).into_par_iter().map(|i| {
+            if self.alive[i] {
+                self.radius_of(i)
+            } else {
+                0.0
+            }
+        }));
+        self.max_radius = radii.iter().copied().fold(0.0, f32::max);
+        self.radii = radii;
+    }
+
+    /// The distance particles `i` and `j` interact within, the mean of their radii, so that
+    /// they push each other alike whatever their own radii.
+    fn pair_radius(&self, i: usize, j: usize) -> f32 {
+        (self.radii[i] + self.radii[j]) / 2.0
+    }
+
+    /// The particles within the pair radius of particle `index`, along with the positions they
+    /// are seen at, which are the copies under the symmetry group too. Those with larger radii
+    /// reach further, so the query is padded by the largest radius of the step.
+    fn get_neighbors_of_particle(&self, index: usize) -> Vec<(usize, Vec2)> {
+        let mut neighbors = Vec::new();
+        let position = self.positions[index];
+        let reach = (self.radii[index] + self.max_radius) / 2.0;
+
+        self.for_each_nearby(position, reach, &mut |j| {
+            if index == j || !self.alive[j] {
+                return;
+            }
+
+            let near = self.position_near(j, position);
+            if (position - near).length() <= self.pair_radius(index, j) {
+                neighbors.push((j, near));
+            }
+        });
+
+        // The copy of particle `j` under a transformation is as close to particle `index` as `j`
+        // is to the copy of `index` under the inverse, which is what the index can search around.
+        for &ghost in &self.ghosts {
+            let seen_from = ghost.transpose() * position;
+            self.index.for_each_candidate(seen_from, reach, &mut |j| {
+                if !self.alive[j] {
+                    return;
+                }
+                let image = ghost * self.positions[j];
+                if (position - image).length() <= self.pair_radius(index, j) {
+                    neighbors.push((j, image));
+                }
+            });
+        }
+
+        neighbors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(n: usize, radius: f32) -> Vec<Vec2> {
+        (0..n)
+            .map(|i| {
+                let angle = i as f32 / n as f32 * 2.0 * PI;
+                vec2(angle.cos(), angle.sin()) * radius
+            })
+            .collect()
+    }
+
+    /// Asserts that the particles of `curve` form a single ring through all of them.
+    fn assert_single_ring(ps: &ParticleSystem, curve: usize) {
+        ps.debug_assert_links();
+        let on_curve: Vec<usize> = ps
+            .particle_indices()
+            .filter(|&i| ps.curve_ids[i] == curve)
+            .collect();
+        let (mut indices, is_ring) = ps.links.walk(on_curve[0]);
+        assert!(is_ring);
+        indices.sort_unstable();
+        assert_eq!(indices, on_curve);
+    }
+
+    #[test]
+    fn splitting_keeps_a_single_ring() {
+        let mut ps = ParticleSystem::with_seed(1);
+        let curve = ps.add_curve(&circle(8, 10.0), false);
+
+        for _ in 0..20 {
+            let p0 = ps.particle_indices().next().unwrap();
+            let p1 = ps.links.next(p0).unwrap();
+            let new_index = ps.insert_between(p0, p1, ps.positions[p0]);
+            assert_eq!(ps.links.next(p0), Some(new_index));
+            assert_eq!(ps.links.next(new_index), Some(p1));
+            assert_single_ring(&ps, curve);
+        }
+        assert_eq!(ps.num_particles(), 28);
+    }
+
+    #[test]
+    fn growing_keeps_a_single_ring() {
+        let mut ps = ParticleSystem::with_seed(7);
+        let curve = ps.add_curve(&circle(20, 20.0), false);
+
+        for _ in 0..100 {
+            ps.update();
+        }
+        assert!(ps.num_particles() > 20);
+        assert_single_ring(&ps, curve);
+    }
+
+    #[test]
+    fn merging_and_splitting_reuse_slots_without_joining_curves() {
+        let mut ps = ParticleSystem::with_seed(3);
+        ps.min_edge_length = 2.0;
+        let first = ps.add_curve(&circle(30, 4.0), false);
+        let second = ps.add_curve(&circle(30, 4.0), false);
+        ps.positions[30..]
+            .iter_mut()
+            .for_each(|p| *p += vec2(50.0, 0.0));
+
+        let mut merged = false;
+        for _ in 0..50 {
+            ps.update();
+            merged |= !ps.free.is_empty();
+        }
+        assert!(merged);
+        assert_single_ring(&ps, first);
+        assert_single_ring(&ps, second);
+    }
+
+    #[test]
+    fn split_conditions_gate_growth() {
+        let grown = |conditions: Vec<SplitCondition>| {
+            let mut ps = ParticleSystem::with_seed(5);
+            ps.add_curve(&circle(20, 20.0), false);
+            ps.split.conditions = conditions;
+            for _ in 0..50 {
+                ps.update();
+            }
+            ps.num_particles()
+        };
+
+        assert!(grown(Vec::new()) > 20);
+        assert_eq!(grown(vec![SplitCondition::MaxNeighbors(0)]), 20);
+        assert_eq!(grown(vec![SplitCondition::MinAge(1000.0)]), 20);
+        assert_eq!(grown(vec![SplitCondition::MinLength(1000.0)]), 20);
+    }
+
+    #[test]
+    fn interpolated_positions_go_from_the_previous_step_to_the_latest() {
+        let mut ps = ParticleSystem::with_seed(4);
+        ps.add_curve(&circle(20, 20.0), false);
+        for _ in 0..10 {
+            ps.update();
+        }
+        let before = ps.positions.clone();
+        ps.update();
+
+        assert_eq!(ps.interpolated_positions(1.0), ps.positions);
+        let start = ps.interpolated_positions(0.0);
+        for i in ps.particle_indices() {
+            if i < before.len() && ps.previous_positions[i].is_some() {
+                assert!(start[i].distance(before[i]) < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn budgets_measure_curves_across_the_torus_edges() {
+        let mut ps = ParticleSystem::with_seed(5);
+        let torus = Torus {
+            width: 100.0,
+            height: 100.0,
+        };
+        ps.torus = Some(torus);
+        ps.budget.max_size = Some(20.0);
+        let points: Vec<Vec2> = circle(16, 5.0)
+            .into_iter()
+            .map(|p| torus.wrap(p + vec2(50.0, 0.0)))
+            .collect();
+        let curve = ps.add_curve(&points, false);
+
+        ps.spend_budget();
+        assert!(!ps.curves[curve].frozen);
+    }
+}
@@ -0,0 +1,95 @@
+//! Effects applied to the images rendered of the simulation, after everything is drawn, for the
+//! look of a lens and film: chromatic aberration, a vignette and film grain.
+
+use crate::raster::{self, Canvas};
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The effects to apply, each left out unless given its intensity. They are applied in the order
+/// of the fields.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostEffects {
+    /// How many pixels the red and blue channels are shifted apart at the corners, red outwards
+    /// and blue inwards, like through a cheap lens.
+    pub chromatic_aberration: Option<f32>,
+    /// How much the corners are darkened, from 0 to 1 to turn them black, fading in from the
+    /// middle.
+    pub vignette: Option<f32>,
+    /// How far the brightness of each pixel is randomly moved, changing every frame.
+    pub grain: Option<f32>,
+}
+
+impl PostEffects {
+    pub fn is_empty(&self) -> bool {
+        self.chromatic_aberration.is_none() && self.vignette.is_none() && self.grain.is_none()
+    }
+
+    /// How many pixels around a tile `apply_to_part()` reads.
+    pub fn margin(&self) -> usize {
+        self.chromatic_aberration
+            .map_or(0, |shift| shift.abs().ceil() as usize + 1)
+    }
+
+    /// `canvas` with the effects applied, for `frame` of an animation, leaving it as it is so
+    /// that it can be drawn over again for the next frame.
+    pub fn applied<'a>(&self, canvas: &'a Canvas, frame: u64) -> Cow<'a, Canvas> {
+        if self.is_empty() {
+            return Cow::Borrowed(canvas);
+        }
+        let mut canvas = canvas.clone();
+        let size = vec2(canvas.width() as f32, canvas.height() as f32);
+        self.apply_to_part(&mut canvas, Vec2::ZERO, size, frame);
+        Cow::Owned(canvas)
+    }
+
+    /// Applies the effects to `canvas` as the part of an image of `size` pixels whose top left
+    /// corner is at `origin`, e.g. a tile of `raster::render_tiled_with()`.
+    pub fn apply_to_part(&self, canvas: &mut Canvas, origin: Vec2, size: Vec2, frame: u64) {
+        if self.is_empty() {
+            return;
+        }
+        let (width, height) = (canvas.width(), canvas.height());
+        let source = canvas.pixels().to_vec();
+        let local = vec2(width as f32, height as f32).max(Vec2::ONE);
+        let half = size / 2.0;
+
+        for (i, pixel) in canvas.pixels_mut().iter_mut().enumerate() {
+            let (x, y) = (i % width, i / width);
+            let p = origin + vec2(x as f32 + 0.5, y as f32 + 0.5);
+            // From the middle of the whole image, reaching 1 at its corners along each axis.
+            let centered = (p - half) / half.max(Vec2::ONE);
+
+            if let Some(shift) = self.chromatic_aberration {
+                let sample =
+                    |p: Vec2| raster::bilinear(&source, width, height, (p - origin) / local);
+                pixel[0] = sample(p - centered * shift)[0];
+                pixel[2] = sample(p + centered * shift)[2];
+            }
+            if let Some(vignette) = self.vignette {
+                let darkening = (vignette * centered.length_squared() / 2.0).clamp(0.0, 1.0);
+                for c in &mut pixel[..3] {
+                    *c *= 1.0 - darkening;
+                }
+            }
+            if let Some(grain) = self.grain {
+                let noise = hash(p.x as u32, p.y as u32, frame) - 0.5;
+                for c in &mut pixel[..3] {
+                    *c += grain * noise;
+                }
+            }
+        }
+    }
+}
+
+/// A pseudo-random number from 0 to 1 for every pixel and frame, the same every time.
+fn hash(x: u32, y: u32, frame: u64) -> f32 {
+    let mut h = (x as u64) | (y as u64) << 32;
+    h ^= frame.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    // The finalizer of SplitMix64.
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^= h >> 31;
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
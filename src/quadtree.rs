@@ -0,0 +1,236 @@
+//! Point quadtree used as an alternative to the uniform grid for neighbor queries.
+
+use crate::neighbor_index::{NeighborIndex, NeighborIndexKind};
+use glam::Vec2;
+use std::ops::Range;
+
+/// Nodes holding more points than this get split into quadrants.
+const LEAF_CAPACITY: usize = 8;
+/// Keeps many particles sitting on the same spot from splitting forever.
+const MAX_DEPTH: usize = 20;
+
+/// Recursively splits the bounding box of the particles into quadrants until each one holds only
+/// a few of them, so sparse regions cost little and dense ones get fine subdivisions.
+#[derive(Default)]
+pub struct Quadtree {
+    /// The root is the first node, if there are any particles.
+    nodes: Vec<Node>,
+    /// Sorted so that every node's points are a contiguous range, nested within its parent's.
+    points: Vec<(usize, Vec2)>,
+}
+
+struct Node {
+    min: Vec2,
+    max: Vec2,
+    points: Range<usize>,
+    /// The indices of the four quadrants, unless this is a leaf.
+    children: Option<[usize; 4]>,
+}
+
+impl Quadtree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the tree and inserts every position.
+    pub fn rebuild(&mut self, positions: &[Vec2]) {
+        self.nodes.clear();
+        self.points.clear();
+        self.points.extend(positions.iter().copied().enumerate());
+        if self.points.is_empty() {
+            return;
+        }
+
+        let (min, max) = self.points.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), &(_, p)| (min.min(p), max.max(p)),
+        );
+        self.build(min, max, 0..self.points.len(), 0);
+    }
+
+    /// Adds the node covering `min` to `max` with the given points, and its descendants, and
+    /// returns its index.
+    fn build(&mut self, min: Vec2, max: Vec2, points: Range<usize>, depth: usize) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            min,
+            max,
+            points: points.clone(),
+            children: None,
+        });
+        if points.len() <= LEAF_CAPACITY || depth >= MAX_DEPTH {
+            return index;
+        }
+
+        let center = (min + max) / 2.0;
+        let slice = &mut self.points[points.clone()];
+        let left = partition(slice, |p| p.x < center.x);
+        let bottom_left = partition(&mut slice[..left], |p| p.y < center.y);
+        let bottom_right = left + partition(&mut slice[left..], |p| p.y < center.y);
+
+        let start = points.start;
+        let quadrants = [
+            (min, center, start..start + bottom_left),
+            (
+                Vec2::new(min.x, center.y),
+                Vec2::new(center.x, max.y),
+                start + bottom_left..start + left,
+            ),
+            (
+                Vec2::new(center.x, min.y),
+                Vec2::new(max.x, center.y),
+                start + left..start + bottom_right,
+            ),
+            (center, max, start + bottom_right..points.end),
+        ];
+        let children = quadrants.map(|(min, max, points)| self.build(min, max, points, depth + 1));
+        self.nodes[index].children = Some(children);
+        index
+    }
+
+    /// Calls `f` with every index whose position lies in the square bounding the circle at
+    /// `center` with the given `radius`. Callers still need to do an exact distance check.
+    pub fn for_each_candidate<F>(&self, center: Vec2, radius: f32, mut f: F)
+    where
+        F: FnMut(usize),
+    {
+        if !self.nodes.is_empty() {
+            let (min, max) = (center - Vec2::splat(radius), center + Vec2::splat(radius));
+            self.visit(0, min, max, &mut f);
+        }
+    }
+
+    fn visit<F>(&self, node: usize, min: Vec2, max: Vec2, f: &mut F)
+    where
+        F: FnMut(usize),
+    {
+        let node = &self.nodes[node];
+        if node.max.cmplt(min).any() || node.min.cmpgt(max).any() {
+            return;
+        }
+
+        match node.children {
+            // Everything below a node inside the query is a candidate, no need to descend.
+            _ if node.min.cmpge(min).all() && node.max.cmple(max).all() => {
+                self.points[node.points.clone()]
+                    .iter()
+                    .for_each(|&(i, _)| f(i));
+            }
+            Some(children) => {
+                for child in children {
+                    self.visit(child, min, max, f);
+                }
+            }
+            None => {
+                for &(i, p) in &self.points[node.points.clone()] {
+                    if p.cmpge(min).all() && p.cmple(max).all() {
+                        f(i);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reorders `points` so that those matching `predicate` come first, and returns how many do.
+fn partition<F>(points: &mut [(usize, Vec2)], predicate: F) -> usize
+where
+    F: Fn(Vec2) -> bool,
+{
+    let mut matching = 0;
+    for i in 0..points.len() {
+        if predicate(points[i].1) {
+            points.swap(i, matching);
+            matching += 1;
+        }
+    }
+    matching
+}
+
+impl NeighborIndex for Quadtree {
+    fn kind(&self) -> NeighborIndexKind {
+        NeighborIndexKind::Quadtree
+    }
+
+    fn rebuild(&mut self, _radius: f32, positions: &[Vec2]) {
+        Quadtree::rebuild(self, positions);
+    }
+
+    fn for_each_candidate(&self, center: Vec2, radius: f32, f: &mut dyn FnMut(usize)) {
+        Quadtree::for_each_candidate(self, center, radius, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighbor_index::BruteForce;
+    use glam::vec2;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    /// The indices of `positions` within `radius` of `center` among the candidates given by
+    /// `for_each_candidate`, sorted, so that repeated candidates show up too.
+    fn neighbors<F>(
+        positions: &[Vec2],
+        center: Vec2,
+        radius: f32,
+        for_each_candidate: F,
+    ) -> Vec<usize>
+    where
+        F: FnOnce(&mut dyn FnMut(usize)),
+    {
+        let mut found = Vec::new();
+        for_each_candidate(&mut |i| {
+            if positions[i].distance(center) <= radius {
+                found.push(i);
+            }
+        });
+        found.sort_unstable();
+        found
+    }
+
+    #[test]
+    fn finds_the_same_neighbors_as_brute_force() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(29);
+        let mut positions: Vec<Vec2> = (0..500)
+            .map(|_| vec2(rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0)))
+            .collect();
+        // Corners making the root exactly 100 wide, so that quadrants split at multiples of 6.25
+        // a few levels down, and points on those lines.
+        positions.extend([vec2(-50.0, -50.0), vec2(50.0, 50.0)]);
+        for i in -8..=8 {
+            let along = i as f32 * 6.25;
+            positions.extend([vec2(along, 0.0), vec2(0.0, along), vec2(along, along)]);
+        }
+        // More particles on one spot than a leaf holds, and copies of random ones.
+        positions.extend(std::iter::repeat_n(vec2(12.5, -7.0), LEAF_CAPACITY * 3));
+        for _ in 0..50 {
+            positions.push(positions[rng.gen_range(0..positions.len())]);
+        }
+
+        let mut tree = Quadtree::new();
+        tree.rebuild(&positions);
+        let mut brute_force = BruteForce::default();
+        brute_force.rebuild(6.25, &positions);
+
+        let queries = positions
+            .iter()
+            .map(|&p| (p, 6.25))
+            .chain((0..200).map(|_| {
+                let center = vec2(rng.gen_range(-60.0..60.0), rng.gen_range(-60.0..60.0));
+                (center, rng.gen_range(0.0..20.0))
+            }));
+        for (center, radius) in queries {
+            assert_eq!(
+                neighbors(&positions, center, radius, |f| tree
+                    .for_each_candidate(center, radius, f)),
+                neighbors(&positions, center, radius, |f| brute_force
+                    .for_each_candidate(center, radius, f)),
+                "around {} within {}",
+                center,
+                radius
+            );
+        }
+    }
+}
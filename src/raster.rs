@@ -0,0 +1,648 @@
+//! A small anti-aliased software rasterizer.
+//!
+//! Used to produce images of the simulation without going through a GPU or a window, e.g. for
+//! recording frames or exporting stills.
+
+use crate::backdrop::Backdrop;
+use crate::fill::{self, Fill};
+use crate::history::History;
+use crate::instances;
+use crate::space_curve::{OrbitCamera, SpaceCurve};
+use crate::stroke::{self, Thickness};
+use crate::torus::Torus;
+use crate::{Color, ParticleSystem};
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The largest canvas `render_tiled()` draws to at once.
+const TILE_SIZE: usize = 1024;
+
+/// How colors drawn onto a canvas combine with what is already there, each weighted by the alpha
+/// of the color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    /// Covers it in proportion to the alpha.
+    #[default]
+    Normal,
+    /// Adds to it, so that overlapping strokes brighten into a glow.
+    Add,
+    /// Multiplies it, so that overlapping strokes darken like layers of ink, which shows on a
+    /// light background.
+    Multiply,
+    /// Multiplies its inverse, brightening like `Add` but without going past white.
+    Screen,
+}
+
+/// An RGBA image in linear `f32` components, drawn to with simulation coordinates with y pointing
+/// up. By default the origin is at the center of the canvas with one unit per pixel, see
+/// `set_view()`.
+#[derive(Clone)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    center: Vec2,
+    scale: f32,
+    blend_mode: BlendMode,
+}
+
+impl Canvas {
+    /// Creates an opaque black canvas.
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![[0.0, 0.0, 0.0, 1.0]; width * height],
+            center: Vec2::ZERO,
+            scale: 1.0,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+
+    /// Makes subsequent drawing put `center` in the middle of the canvas, with `scale` pixels per
+    /// simulation unit. Thicknesses and radii are scaled too.
+    pub fn set_view(&mut self, center: Vec2, scale: f32) {
+        self.center = center;
+        self.scale = scale;
+    }
+
+    /// Makes subsequent drawing, including `fill()`, combine with the canvas through `mode`.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Color] {
+        &mut self.pixels
+    }
+
+    /// The `(min, max)` corners of the part of the plane the canvas shows.
+    pub fn view_bounds(&self) -> (Vec2, Vec2) {
+        view_bounds(self.center, self.scale, self.width, self.height)
+    }
+
+    fn to_pixel(&self, p: Vec2) -> Vec2 {
+        let p = (p - self.center) * self.scale;
+        vec2(
+            p.x + self.width as f32 / 2.0,
+            self.height as f32 / 2.0 - p.y,
+        )
+    }
+
+    fn to_world(&self, pixel: Vec2) -> Vec2 {
+        let p = vec2(
+            pixel.x - self.width as f32 / 2.0,
+            self.height as f32 / 2.0 - pixel.y,
+        );
+        p / self.scale + self.center
+    }
+
+    fn blend(&mut self, x: usize, y: usize, color: Color, coverage: f32) {
+        let alpha = color[3] * coverage;
+        let dst = &mut self.pixels[y * self.width + x];
+        match self.blend_mode {
+            BlendMode::Normal => {
+                for c in 0..3 {
+                    dst[c] = color[c] * alpha + dst[c] * (1.0 - alpha);
+                }
+                dst[3] = alpha + dst[3] * (1.0 - alpha);
+            }
+            BlendMode::Add => {
+                for c in 0..3 {
+                    dst[c] += color[c] * alpha;
+                }
+                dst[3] = (dst[3] + alpha).min(1.0);
+            }
+            BlendMode::Multiply => {
+                for c in 0..3 {
+                    dst[c] *= 1.0 - alpha + color[c] * alpha;
+                }
+            }
+            BlendMode::Screen => {
+                for c in 0..3 {
+                    dst[c] += color[c] * alpha * (1.0 - dst[c]);
+                }
+                dst[3] = alpha + dst[3] * (1.0 - alpha);
+            }
+        }
+    }
+
+    /// Blends `color` over the whole canvas. A translucent color fades out what was drawn before,
+    /// leaving trails.
+    pub fn fill(&mut self, color: Color) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    /// Calls `f` with every pixel in the canvas overlapping the rectangle between `min` and `max`,
+    /// given in pixel coordinates, along with the position of the pixel center.
+    fn for_each_pixel_in<F>(&mut self, min: Vec2, max: Vec2, mut f: F)
+    where
+        F: FnMut(&mut Self, usize, usize, Vec2),
+    {
+        let x0 = min.x.floor().max(0.0) as usize;
+        let y0 = min.y.floor().max(0.0) as usize;
+        let x1 = (max.x.ceil().max(0.0) as usize).min(self.width);
+        let y1 = (max.y.ceil().max(0.0) as usize).min(self.height);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                f(self, x, y, vec2(x as f32 + 0.5, y as f32 + 0.5));
+            }
+        }
+    }
+
+    /// The color at `uv`, from `(0, 0)` at the top left corner of the canvas to `(1, 1)` at the
+    /// bottom right one, blended between the nearest pixels.
+    pub fn sample(&self, uv: Vec2) -> Color {
+        bilinear(&self.pixels, self.width, self.height, uv)
+    }
+
+    /// Draws `backdrop` fitted into `frame`, which is the whole canvas given `view_bounds()`, or
+    /// the whole image being rendered in tiles.
+    pub fn draw_backdrop(&mut self, backdrop: &Backdrop, frame: (Vec2, Vec2)) {
+        let (min, max) = backdrop.placement(frame);
+        let top_left = self.to_pixel(vec2(min.x, max.y));
+        let bottom_right = self.to_pixel(vec2(max.x, min.y));
+        let size = bottom_right - top_left;
+
+        self.for_each_pixel_in(top_left, bottom_right, |canvas, x, y, p| {
+            canvas.blend(x, y, backdrop.sample((p - top_left) / size), 1.0);
+        });
+    }
+
+    pub fn line(&mut self, start: Vec2, end: Vec2, thickness: f32, color: Color) {
+        let (a, b) = (self.to_pixel(start), self.to_pixel(end));
+        let thickness = thickness * self.scale;
+        let half = thickness / 2.0;
+        let pad = Vec2::splat(half + 1.0);
+
+        self.for_each_pixel_in(a.min(b) - pad, a.max(b) + pad, |canvas, x, y, p| {
+            let d = distance_to_segment(p, a, b);
+            // Lines thinner than a pixel fade out instead of getting thinner.
+            let coverage = if thickness < 1.0 {
+                thickness * (1.0 - d).max(0.0)
+            } else {
+                (half + 0.5 - d).clamp(0.0, 1.0)
+            };
+            if coverage > 0.0 {
+                canvas.blend(x, y, color, coverage);
+            }
+        });
+    }
+
+    /// Like `line()`, but with a thickness going from `start_thickness` to `end_thickness`.
+    pub fn tapered_line(
+        &mut self,
+        start: Vec2,
+        end: Vec2,
+        start_thickness: f32,
+        end_thickness: f32,
+        color: Color,
+    ) {
+        let (a, b) = (self.to_pixel(start), self.to_pixel(end));
+        let (half0, half1) = (
+            start_thickness * self.scale / 2.0,
+            end_thickness * self.scale / 2.0,
+        );
+        let pad = Vec2::splat(half0.max(half1) + 1.0);
+
+        self.for_each_pixel_in(a.min(b) - pad, a.max(b) + pad, |canvas, x, y, p| {
+            let t = closest_on_segment(p, a, b);
+            let d = (p - a.lerp(b, t)).length();
+            let half = half0 + (half1 - half0) * t;
+            let coverage = if half < 0.5 {
+                half * 2.0 * (1.0 - d).max(0.0)
+            } else {
+                (half + 0.5 - d).clamp(0.0, 1.0)
+            };
+            if coverage > 0.0 {
+                canvas.blend(x, y, color, coverage);
+            }
+        });
+    }
+
+    pub fn disc(&mut self, center: Vec2, radius: f32, color: Color) {
+        let c = self.to_pixel(center);
+        let radius = radius * self.scale;
+        let pad = Vec2::splat(radius + 1.0);
+
+        self.for_each_pixel_in(c - pad, c + pad, |canvas, x, y, p| {
+            let coverage = (radius + 0.5 - (p - c).length()).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                canvas.blend(x, y, color, coverage);
+            }
+        });
+    }
+
+    /// Draws the particle system with the same look as the interactive app: edges in
+    /// `line_color` with the particles as colored dots on top. See `stroke::stroke_paths()` for
+    /// `thickness` and `subdivisions`.
+    pub fn draw_particle_system(
+        &mut self,
+        ps: &ParticleSystem,
+        line_color: Color,
+        thickness: &Thickness,
+        subdivisions: usize,
+    ) {
+        self.draw_particle_system_at(ps, ps.positions(), line_color, thickness, subdivisions);
+    }
+
+    /// Like `draw_particle_system()`, with the particles at `positions`, e.g. part of the way
+    /// between two steps.
+    pub fn draw_particle_system_at(
+        &mut self,
+        ps: &ParticleSystem,
+        positions: &[Vec2],
+        line_color: Color,
+        thickness: &Thickness,
+        subdivisions: usize,
+    ) {
+        self.draw_edges_at(ps, positions, line_color, thickness, subdivisions);
+        for instance in instances::particles(ps, positions) {
+            self.disc(instance.position, instance.size / 2.0, instance.color);
+        }
+    }
+
+    /// Draws only the edges `draw_particle_system_at()` draws, leaving out the particles.
+    pub fn draw_edges_at(
+        &mut self,
+        ps: &ParticleSystem,
+        positions: &[Vec2],
+        line_color: Color,
+        thickness: &Thickness,
+        subdivisions: usize,
+    ) {
+        for path in stroke::stroke_paths_at(ps, positions, thickness, subdivisions) {
+            let n = path.points.len();
+            let segments = if path.closed { n } else { n.saturating_sub(1) };
+            for i in 0..segments {
+                let j = (i + 1) % n;
+                if *thickness == Thickness::Uniform {
+                    self.line(path.points[i], path.points[j], path.widths[i], line_color);
+                } else {
+                    self.tapered_line(
+                        path.points[i],
+                        path.points[j],
+                        path.widths[i],
+                        path.widths[j],
+                        line_color,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draws the edges of a curve in space as seen by `camera`, dimming the far ones. Points
+    /// project to -1 to 1 vertically, which the view has to be set up to show.
+    pub fn draw_space_curve(
+        &mut self,
+        curve: &SpaceCurve,
+        camera: &OrbitCamera,
+        color: Color,
+        thickness: f32,
+    ) {
+        let positions = curve.positions();
+        for (i, next) in curve.links().edges() {
+            let (start, end) = match (
+                camera.project(positions[i]),
+                camera.project(positions[next]),
+            ) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+            let mut color = color;
+            color[3] *= camera.shade((start.1 + end.1) / 2.0);
+            self.line(start.0, end.0, thickness, color);
+        }
+    }
+
+    /// Draws the past outlines in `history`, fading out the older ones.
+    pub fn draw_history(&mut self, history: &History, color: Color, thickness: f32) {
+        for (i, snapshot) in history.snapshots().enumerate() {
+            let mut color = color;
+            color[3] *= history.opacity(i);
+
+            for polyline in &snapshot.polylines {
+                let points = &polyline.points;
+                let n = points.len();
+                let segments = if polyline.closed {
+                    n
+                } else {
+                    n.saturating_sub(1)
+                };
+                for j in 0..segments {
+                    self.line(points[j], points[(j + 1) % n], thickness, color);
+                }
+            }
+        }
+    }
+
+    /// Fills every closed curve of the particle system, see `Fill`.
+    pub fn fill_particle_system(&mut self, ps: &ParticleSystem, fill: &Fill) {
+        self.fill_particle_system_at(ps, ps.positions(), fill);
+    }
+
+    /// Like `fill_particle_system()`, with the particles at `positions`.
+    pub fn fill_particle_system_at(
+        &mut self,
+        ps: &ParticleSystem,
+        positions: &[Vec2],
+        fill: &Fill,
+    ) {
+        let bounds = match ps.bounding_box() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        for polyline in ps
+            .polylines_at(positions)
+            .iter()
+            .filter(|polyline| polyline.closed)
+        {
+            self.fill_polygon_with(&polyline.points, |p| fill.color_at(p, bounds));
+        }
+    }
+
+    /// Fills the polygon through `points` with a single color.
+    pub fn fill_polygon(&mut self, points: &[Vec2], color: Color) {
+        self.fill_polygon_with(points, |_| color);
+    }
+
+    /// Fills the polygon through `points`, coloring each pixel by its position in simulation
+    /// coordinates.
+    fn fill_polygon_with<F>(&mut self, points: &[Vec2], color_at: F)
+    where
+        F: Fn(Vec2) -> Color,
+    {
+        let points: Vec<Vec2> = points.iter().map(|&p| self.to_pixel(p)).collect();
+
+        for [a, b, c] in fill::triangulate(&points) {
+            let (a, b, c) = (points[a], points[b], points[c]);
+            self.for_each_pixel_in(a.min(b).min(c), a.max(b).max(c), |canvas, x, y, p| {
+                if in_triangle(p, a, b, c) {
+                    let color = color_at(canvas.to_world(p));
+                    canvas.blend(x, y, color, 1.0);
+                }
+            });
+        }
+    }
+
+    pub fn to_image(&self) -> image::RgbaImage {
+        image::RgbaImage::from_raw(self.width as u32, self.height as u32, self.to_rgba8())
+            .expect("the canvas holds width * height pixels")
+    }
+
+    /// Converts the canvas to 8-bit RGBA rows, top to bottom.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| {
+                pixel
+                    .iter()
+                    .map(|&c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+            })
+            .collect()
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> image::ImageResult<()> {
+        image::save_buffer(
+            path,
+            &self.to_rgba8(),
+            self.width as u32,
+            self.height as u32,
+            image::ColorType::Rgba8,
+        )
+    }
+}
+
+/// The view that fits the `(min, max)` bounding box into an image of `width` by `height` pixels,
+/// leaving `margin` pixels around it, as the center and scale to pass to `Canvas::set_view()`.
+pub fn fit_view((min, max): (Vec2, Vec2), width: usize, height: usize, margin: f32) -> (Vec2, f32) {
+    let size = (max - min).max(Vec2::splat(1.0));
+    let scale = (vec2(width as f32, height as f32) - Vec2::splat(2.0 * margin)) / size;
+    ((min + max) / 2.0, scale.x.min(scale.y).max(f32::EPSILON))
+}
+
+/// The `(min, max)` corners of the part of the plane an image of `width` by `height` pixels
+/// shows, centered at `center` with `scale` pixels per simulation unit.
+pub fn view_bounds(center: Vec2, scale: f32, width: usize, height: usize) -> (Vec2, Vec2) {
+    let half = vec2(width as f32, height as f32) / (2.0 * scale);
+    (center - half, center + half)
+}
+
+/// The color at `uv` in an image of `width` by `height` `pixels`, as in `Canvas::sample()`.
+pub fn bilinear(pixels: &[Color], width: usize, height: usize, uv: Vec2) -> Color {
+    if pixels.is_empty() {
+        return [0.0; 4];
+    }
+    let size = vec2(width as f32, height as f32);
+    let p = (uv * size - Vec2::splat(0.5)).clamp(Vec2::ZERO, size - Vec2::ONE);
+    let (x0, y0) = (p.x.floor() as usize, p.y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (p.x.fract(), p.y.fract());
+
+    let pixel = |x: usize, y: usize| pixels[y * width + x];
+    let mut color = [0.0; 4];
+    for (c, value) in color.iter_mut().enumerate() {
+        let top = pixel(x0, y0)[c] * (1.0 - tx) + pixel(x1, y0)[c] * tx;
+        let bottom = pixel(x0, y1)[c] * (1.0 - tx) + pixel(x1, y1)[c] * tx;
+        *value = top * (1.0 - ty) + bottom * ty;
+    }
+    color
+}
+
+/// An image of `width` by `height` pixels laid over the `(min, max)` corners, with y pointing up,
+/// as a mesh of vertices colored by `sample` at their `uv`, no more than `cells` quads across. It
+/// draws images where there are no textures, e.g. in the window.
+pub fn image_grid<F>(
+    (width, height): (usize, usize),
+    (min, max): (Vec2, Vec2),
+    cells: usize,
+    sample: F,
+) -> (Vec<(Vec2, Color)>, Vec<[usize; 3]>)
+where
+    F: Fn(Vec2) -> Color,
+{
+    let longest = width.max(height).max(1) as f32;
+    let columns = ((width as f32 / longest * cells as f32).round() as usize).max(1);
+    let rows = ((height as f32 / longest * cells as f32).round() as usize).max(1);
+
+    let mut vertices = Vec::with_capacity((columns + 1) * (rows + 1));
+    for y in 0..=rows {
+        for x in 0..=columns {
+            let uv = vec2(x as f32 / columns as f32, y as f32 / rows as f32);
+            let p = vec2(
+                min.x + uv.x * (max.x - min.x),
+                max.y - uv.y * (max.y - min.y),
+            );
+            vertices.push((p, sample(uv)));
+        }
+    }
+    let mut triangles = Vec::with_capacity(columns * rows * 2);
+    for y in 0..rows {
+        for x in 0..columns {
+            let i = y * (columns + 1) + x;
+            let below = i + columns + 1;
+            triangles.push([i, i + 1, below]);
+            triangles.push([i + 1, below + 1, below]);
+        }
+    }
+    (vertices, triangles)
+}
+
+/// Renders an image of any size, e.g. for print, by drawing it in tiles no larger than
+/// `TILE_SIZE`. Only one tile's worth of floating point pixels is held at a time, which would
+/// otherwise take gigabytes for the largest images.
+///
+/// `draw` is called on every tile with a canvas already showing its part of the view centered
+/// at `center` with `scale` pixels per simulation unit, so it must not change the view itself.
+pub fn render_tiled<F>(
+    width: usize,
+    height: usize,
+    center: Vec2,
+    scale: f32,
+    draw: F,
+) -> image::RgbaImage
+where
+    F: FnMut(&mut Canvas),
+{
+    render_tiled_with(width, height, center, scale, 0, draw, |_, _| {})
+}
+
+/// Like `render_tiled()`, then calls `post` on every tile drawn with `margin` more pixels around
+/// it, along with where its top left corner is in the image, e.g. to apply `PostEffects` that
+/// read the pixels around each one. The margin is left out of the image.
+pub fn render_tiled_with<F, G>(
+    width: usize,
+    height: usize,
+    center: Vec2,
+    scale: f32,
+    margin: usize,
+    mut draw: F,
+    mut post: G,
+) -> image::RgbaImage
+where
+    F: FnMut(&mut Canvas),
+    G: FnMut(&mut Canvas, Vec2),
+{
+    let mut image = image::RgbaImage::new(width as u32, height as u32);
+
+    for y0 in (0..height).step_by(TILE_SIZE) {
+        for x0 in (0..width).step_by(TILE_SIZE) {
+            let (w, h) = (TILE_SIZE.min(width - x0), TILE_SIZE.min(height - y0));
+
+            // The offset in pixels from the middle of the image to the middle of the tile.
+            let offset = vec2(
+                x0 as f32 + w as f32 / 2.0 - width as f32 / 2.0,
+                height as f32 / 2.0 - (y0 as f32 + h as f32 / 2.0),
+            );
+            // The margin is on every side, so the tile stays centered where it was.
+            let mut tile = Canvas::new(w + 2 * margin, h + 2 * margin);
+            tile.set_view(center + offset / scale, scale);
+            draw(&mut tile);
+            let corner = vec2(x0 as f32, y0 as f32) - Vec2::splat(margin as f32);
+            post(&mut tile, corner);
+
+            let pixels = tile.to_rgba8();
+            let rows = pixels.chunks_exact(tile.width * 4).skip(margin).take(h);
+            for (y, row) in rows.enumerate() {
+                let row = &row[margin * 4..(margin + w) * 4];
+                for (x, pixel) in row.chunks_exact(4).enumerate() {
+                    let rgba = image::Rgba([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                    image.put_pixel((x0 + x) as u32, (y0 + y) as u32, rgba);
+                }
+            }
+        }
+    }
+
+    image
+}
+
+/// Renders one period of `torus`, `width` pixels wide and as tall as its proportions make it, so
+/// that copies of the image laid side by side continue each other seamlessly, e.g. as a wallpaper
+/// or material texture.
+///
+/// The curves of `ps` stick out of the period where they cross its edges, so after filling the
+/// `background`, `draw` is called once for every copy of the period they reach, with the view
+/// shifted to lay that copy over the image. Like with `render_tiled()`, it must not change the
+/// view itself.
+pub fn render_seamless<F>(
+    ps: &ParticleSystem,
+    torus: &Torus,
+    width: usize,
+    background: Color,
+    mut draw: F,
+) -> image::RgbaImage
+where
+    F: FnMut(&mut Canvas),
+{
+    let height = ((width as f32 * torus.height / torus.width).round() as usize).max(1);
+    let scale = width as f32 / torus.width;
+    let size = torus.size();
+
+    let (min, max) = ps
+        .polylines()
+        .iter()
+        .flat_map(|polyline| polyline.points.iter())
+        .fold((-size / 2.0, size / 2.0), |(min, max), &p| {
+            (min.min(p), max.max(p))
+        });
+    // The copies moved by whole periods that still overlap the one in the middle.
+    let first = ((-size / 2.0 - max) / size).ceil();
+    let last = ((size / 2.0 - min) / size).floor();
+    let mut shifts = Vec::new();
+    for x in first.x as i32..=last.x as i32 {
+        for y in first.y as i32..=last.y as i32 {
+            shifts.push(vec2(x as f32, y as f32) * size);
+        }
+    }
+
+    render_tiled(width, height, Vec2::ZERO, scale, |canvas| {
+        canvas.fill(background);
+        let center = canvas.center;
+        for &shift in &shifts {
+            canvas.set_view(center - shift, scale);
+            draw(canvas);
+        }
+        canvas.set_view(center, scale);
+    })
+}
+
+fn distance_to_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    (p - a.lerp(b, closest_on_segment(p, a, b))).length()
+}
+
+/// How far along the segment from `a` to `b` its closest point to `p` is, from 0 to 1.
+fn closest_on_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    if ab.length_squared() > 0.0 {
+        ((p - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let d0 = (b - a).perp_dot(p - a);
+    let d1 = (c - b).perp_dot(p - b);
+    let d2 = (a - c).perp_dot(p - c);
+
+    (d0 >= 0.0 && d1 >= 0.0 && d2 >= 0.0) || (d0 <= 0.0 && d1 <= 0.0 && d2 <= 0.0)
+}
@@ -0,0 +1,236 @@
+//! A Gray-Scott reaction-diffusion field simulated under the curves, coupling the two classic
+//! generative systems: the particles feed the reaction, and its concentration steers where they
+//! split and which way they drift.
+
+use glam::{vec2, Vec2};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How the field is laid out and reacts, and how strongly it couples to the curves.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrayScott {
+    /// The middle of the square the field covers.
+    pub center: Vec2,
+    /// The width of that square, in simulation units. The field is empty outside of it.
+    pub size: f32,
+    /// How many cells the square is split into along each side.
+    pub resolution: usize,
+    /// The rate the U chemical is fed at.
+    pub feed: f32,
+    /// The rate the V chemical is removed at.
+    pub kill: f32,
+    pub diffusion_u: f32,
+    pub diffusion_v: f32,
+    /// How many times the reaction advances per step of the curves.
+    pub iterations: usize,
+    /// How many random patches of V the field starts with.
+    pub seeds: usize,
+    /// How much V every particle adds to its cell each step.
+    pub deposit: f32,
+    /// How much the concentration of V gates splitting, from -1 for splitting only where there is
+    /// none to 1 for splitting only where it peaks. 0 leaves splitting alone.
+    pub split: f32,
+    /// How strongly particles are pushed up the gradient of V, or down it when negative.
+    pub force: f32,
+}
+
+impl Default for GrayScott {
+    fn default() -> Self {
+        GrayScott {
+            center: Vec2::ZERO,
+            size: 800.0,
+            resolution: 200,
+            feed: 0.055,
+            kill: 0.062,
+            diffusion_u: 1.0,
+            diffusion_v: 0.5,
+            iterations: 8,
+            seeds: 10,
+            deposit: 0.02,
+            split: 1.0,
+            force: 0.0,
+        }
+    }
+}
+
+impl GrayScott {
+    fn cell_size(&self) -> f32 {
+        self.size / self.resolution.max(1) as f32
+    }
+
+    /// Whether `other` lays out its cells differently, so that the field has to start over.
+    fn moves_cells(&self, other: &GrayScott) -> bool {
+        (self.center, self.size, self.resolution) != (other.center, other.size, other.resolution)
+    }
+}
+
+/// The concentrations of the U and V chemicals on a grid.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReactionDiffusion {
+    settings: GrayScott,
+    seed: u64,
+    u: Vec<f32>,
+    v: Vec<f32>,
+    /// The highest concentration of V after the last step, which `split_scale()` is relative to.
+    #[serde(default)]
+    max_v: f32,
+}
+
+impl ReactionDiffusion {
+    /// A field full of U, with `seeds` patches of V placed at random from `seed`.
+    pub fn new(settings: GrayScott, seed: u64) -> Self {
+        let n = settings.resolution.max(1);
+        let mut rd = ReactionDiffusion {
+            settings,
+            seed,
+            u: vec![1.0; n * n],
+            v: vec![0.0; n * n],
+            max_v: 0.0,
+        };
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+        let radius = (n / 40).max(1);
+        for _ in 0..settings.seeds {
+            let (cx, cy) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            for y in cy.saturating_sub(radius)..(cy + radius).min(n) {
+                for x in cx.saturating_sub(radius)..(cx + radius).min(n) {
+                    rd.u[y * n + x] = 0.5;
+                    rd.v[y * n + x] = 0.25;
+                }
+            }
+        }
+        rd
+    }
+
+    pub fn settings(&self) -> &GrayScott {
+        &self.settings
+    }
+
+    /// Changes the settings, starting over only if the cells moved.
+    pub fn configure(&mut self, settings: GrayScott) {
+        if settings.moves_cells(&self.settings) {
+            *self = ReactionDiffusion::new(settings, self.seed);
+        } else {
+            self.settings = settings;
+        }
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.settings.resolution.max(1)
+    }
+
+    /// The concentration of V in every cell, row by row from the bottom left corner.
+    pub fn concentrations(&self) -> &[f32] {
+        &self.v
+    }
+
+    /// The bottom left corner of the cell at `(x, y)`, and the width of every cell.
+    pub fn cell(&self, x: usize, y: usize) -> (Vec2, f32) {
+        let size = self.settings.cell_size();
+        let origin = self.settings.center - Vec2::splat(self.settings.size / 2.0);
+        (origin + vec2(x as f32, y as f32) * size, size)
+    }
+
+    /// Adds V under each of `positions`, then advances the reaction.
+    pub fn step(&mut self, positions: impl Iterator<Item = Vec2>) {
+        let n = self.resolution();
+        for p in positions {
+            if let Some((x, y)) = self.cell_of(p) {
+                let v = &mut self.v[y * n + x];
+                *v = (*v + self.settings.deposit).min(1.0);
+            }
+        }
+
+        let GrayScott {
+            feed,
+            kill,
+            diffusion_u,
+            diffusion_v,
+            ..
+        } = self.settings;
+        let mut next_u = vec![0.0; n * n];
+        let mut next_v = vec![0.0; n * n];
+        for _ in 0..self.settings.iterations {
+            let (u, v) = (&self.u, &self.v);
+            next_u
+                .par_chunks_mut(n)
+                .zip(next_v.par_chunks_mut(n))
+                .enumerate()
+                .for_each(|(y, (row_u, row_v))| {
+                    for x in 0..n {
+                        let i = y * n + x;
+                        let reaction = u[i] * v[i] * v[i];
+                        row_u[x] = u[i] + diffusion_u * laplacian(u, n, x, y) - reaction
+                            + feed * (1.0 - u[i]);
+                        row_v[x] = v[i] + diffusion_v * laplacian(v, n, x, y) + reaction
+                            - (kill + feed) * v[i];
+                    }
+                });
+            std::mem::swap(&mut self.u, &mut next_u);
+            std::mem::swap(&mut self.v, &mut next_v);
+        }
+        self.max_v = self.v.iter().copied().fold(0.0, f32::max);
+    }
+
+    /// The concentration of V at `p`, interpolated between cells. 0 outside of the field.
+    pub fn concentration_at(&self, p: Vec2) -> f32 {
+        let n = self.resolution();
+        let origin = self.settings.center - Vec2::splat(self.settings.size / 2.0);
+        // Relative to the cell centers.
+        let q = (p - origin) / self.settings.cell_size() - Vec2::splat(0.5);
+        if q.x < 0.0 || q.y < 0.0 || q.x >= (n - 1) as f32 || q.y >= (n - 1) as f32 {
+            return 0.0;
+        }
+
+        let (x, y) = (q.x as usize, q.y as usize);
+        let (tx, ty) = (q.x.fract(), q.y.fract());
+        let v = |x: usize, y: usize| self.v[y * n + x];
+        let bottom = v(x, y) + (v(x + 1, y) - v(x, y)) * tx;
+        let top = v(x, y + 1) + (v(x + 1, y + 1) - v(x, y + 1)) * tx;
+        bottom + (top - bottom) * ty
+    }
+
+    /// How much the probability of splitting an edge at `p` is scaled by, from 0 to 1.
+    pub fn split_scale(&self, p: Vec2) -> f32 {
+        let split = self.settings.split.clamp(-1.0, 1.0);
+        let c = (self.concentration_at(p) / self.max_v.max(f32::EPSILON)).min(1.0);
+        if split >= 0.0 {
+            1.0 - split * (1.0 - c)
+        } else {
+            1.0 + split * c
+        }
+    }
+
+    /// The push on a particle at `p` along the gradient of V.
+    pub fn force_at(&self, p: Vec2) -> Vec2 {
+        if self.settings.force == 0.0 {
+            return Vec2::ZERO;
+        }
+        let h = self.settings.cell_size();
+        let gradient = vec2(
+            self.concentration_at(p + vec2(h, 0.0)) - self.concentration_at(p - vec2(h, 0.0)),
+            self.concentration_at(p + vec2(0.0, h)) - self.concentration_at(p - vec2(0.0, h)),
+        ) / 2.0;
+        gradient * self.settings.force
+    }
+
+    fn cell_of(&self, p: Vec2) -> Option<(usize, usize)> {
+        let n = self.resolution();
+        let origin = self.settings.center - Vec2::splat(self.settings.size / 2.0);
+        let q = (p - origin) / self.settings.cell_size();
+        let inside = q.x >= 0.0 && q.y >= 0.0 && q.x < n as f32 && q.y < n as f32;
+        inside.then_some((q.x as usize, q.y as usize))
+    }
+}
+
+/// The discrete Laplacian of `values` at `(x, y)`, over the 3 by 3 cells around it, treating the
+/// edges of the grid as wrapping around.
+fn laplacian(values: &[f32], n: usize, x: usize, y: usize) -> f32 {
+    let at = |dx: usize, dy: usize| values[(y + dy + n - 1) % n * n + (x + dx + n - 1) % n];
+    let sides = at(0, 1) + at(2, 1) + at(1, 0) + at(1, 2);
+    let corners = at(0, 0) + at(2, 0) + at(0, 2) + at(2, 2);
+    0.2 * sides + 0.05 * corners - at(1, 1)
+}
@@ -0,0 +1,427 @@
+//! A compact binary recording of a growth step by step, so that it can be rendered again at any
+//! size or speed without simulating it again, see the `play` subcommand.
+//!
+//! A replay starts with a header, followed by a frame for every recorded step. Each frame lists
+//! the particles whose successor along the curve changed since the previous frame, which covers
+//! splits, merges and reconnections, then how far every live particle moved since it, in whole
+//! `QUANTUM`s. Integers are written as LEB128 varints, signed ones zigzag encoded, so the small
+//! moves of a step mostly take a byte per coordinate.
+
+use crate::{ParticleSystem, Symmetry, Torus};
+use glam::{vec2, Mat2, Vec2};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"DLRP";
+const VERSION: u8 = 2;
+/// The first version recording the symmetry, earlier ones are read without one.
+const SYMMETRY_VERSION: u8 = 2;
+
+/// The precision positions are recorded with, in simulation units, well below a pixel at the
+/// scales the growth is usually drawn at.
+pub const QUANTUM: f32 = 1.0 / 64.0;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// The data isn't a replay, or one of a later version.
+    Format(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::Format(reason) => write!(f, "Not a valid replay: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+/// What a replay records once, at its start.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayHeader {
+    pub quantum: f32,
+    pub particle_radius: f32,
+    /// The space the growth wrapped around in, which edges are drawn across.
+    pub torus: Option<Torus>,
+    /// The group the growth was replicated under, whose copies of the curves are drawn along
+    /// with them.
+    pub symmetry: Option<Symmetry>,
+}
+
+impl ReplayHeader {
+    /// The transformations of the symmetry group, or just the identity without one.
+    pub fn symmetry_transforms(&self) -> Vec<Mat2> {
+        match &self.symmetry {
+            Some(symmetry) => symmetry.transforms(),
+            None => vec![Mat2::IDENTITY],
+        }
+    }
+}
+
+/// A particle slot as of the last frame: 0 when removed, 1 when alive without a successor, and
+/// the successor plus 2 otherwise, along with its quantized position.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Slot {
+    state: usize,
+    position: [i32; 2],
+}
+
+/// Appends a frame to a replay on every call to `record()`.
+pub struct ReplayWriter<W: Write> {
+    out: W,
+    slots: Vec<Slot>,
+}
+
+impl ReplayWriter<BufWriter<File>> {
+    /// Starts a replay of `ps` at `path`, creating its directory if needed, with its current
+    /// state as the first frame.
+    pub fn create<P: AsRef<Path>>(path: P, ps: &ParticleSystem) -> io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        ReplayWriter::new(BufWriter::new(File::create(path)?), ps)
+    }
+}
+
+impl<W: Write> ReplayWriter<W> {
+    /// Writes the header and the current state of `ps` as the first frame.
+    pub fn new(mut out: W, ps: &ParticleSystem) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        out.write_all(&QUANTUM.to_le_bytes())?;
+        out.write_all(&ps.particle_radius.to_le_bytes())?;
+        let (width, height) = ps.torus.map_or((0.0, 0.0), |t| (t.width, t.height));
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+        let (kind, parameter) = match ps.symmetry {
+            None => (0, [0; 4]),
+            Some(Symmetry::Mirror(angle)) => (1, angle.to_le_bytes()),
+            Some(Symmetry::Rotation(n)) => (2, (n as u32).to_le_bytes()),
+            Some(Symmetry::Dihedral(n)) => (3, (n as u32).to_le_bytes()),
+        };
+        out.write_all(&[kind])?;
+        out.write_all(&parameter)?;
+
+        let mut writer = ReplayWriter {
+            out,
+            slots: Vec::new(),
+        };
+        writer.record(ps)?;
+        Ok(writer)
+    }
+
+    /// Appends the current state of `ps` as a frame. Call it after every step to record.
+    pub fn record(&mut self, ps: &ParticleSystem) -> io::Result<()> {
+        let (positions, alive, links) = (ps.positions(), ps.alive(), ps.links());
+        let count = positions.len();
+        self.slots.resize(count, Slot::default());
+        write_varint(&mut self.out, ps.steps())?;
+        write_varint(&mut self.out, count as u64)?;
+
+        let mut changes = Vec::new();
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let state = match (alive[i], links.get(i).next) {
+                (false, _) => 0,
+                (true, None) => 1,
+                (true, Some(next)) => next + 2,
+            };
+            if state != slot.state {
+                slot.state = state;
+                changes.push((i, state));
+            }
+        }
+        write_varint(&mut self.out, changes.len() as u64)?;
+        let mut expected = 0;
+        for (i, state) in changes {
+            write_varint(&mut self.out, (i - expected) as u64)?;
+            write_varint(&mut self.out, state as u64)?;
+            expected = i + 1;
+        }
+
+        for (slot, p) in self.slots.iter_mut().zip(positions) {
+            if slot.state == 0 {
+                continue;
+            }
+            let quantized = [quantize(p.x), quantize(p.y)];
+            for (old, new) in slot.position.iter_mut().zip(quantized) {
+                write_varint(&mut self.out, zigzag(new.wrapping_sub(*old)))?;
+                *old = new;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the frames written so far, returning the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// A recorded step, with removed particles kept in their slots like in `ParticleSystem`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frame {
+    pub step: u64,
+    pub positions: Vec<Vec2>,
+    pub alive: Vec<bool>,
+    /// The successor of every particle along its curve.
+    pub next: Vec<Option<usize>>,
+}
+
+impl Frame {
+    /// Every edge as a particle and its successor.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.next
+            .iter()
+            .enumerate()
+            .filter_map(|(i, next)| next.map(|next| (i, next)))
+    }
+
+    /// The `(min, max)` corners of the smallest axis aligned box holding every live particle and
+    /// its copies under `transforms`, or `None` if there are none.
+    pub fn bounding_box(&self, transforms: &[Mat2]) -> Option<(Vec2, Vec2)> {
+        self.positions
+            .iter()
+            .zip(&self.alive)
+            .filter(|(_, &alive)| alive)
+            .flat_map(|(&p, _)| transforms.iter().map(move |&transform| transform * p))
+            .fold(None, |bounds, p| match bounds {
+                Some((min, max)) => Some((p.min(min), p.max(max))),
+                None => Some((p, p)),
+            })
+    }
+}
+
+/// Reads the frames of a replay one after the other.
+pub struct ReplayReader<R: Read> {
+    input: R,
+    header: ReplayHeader,
+    slots: Vec<Slot>,
+}
+
+impl ReplayReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        ReplayReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> ReplayReader<R> {
+    /// Reads the header, leaving the frames to `next_frame()`.
+    pub fn new(mut input: R) -> Result<Self, ReplayError> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ReplayError::Format("it doesn't start like one"));
+        }
+        let mut version = [0];
+        input.read_exact(&mut version)?;
+        if version[0] == 0 || version[0] > VERSION {
+            return Err(ReplayError::Format("unknown version"));
+        }
+        let quantum = read_f32(&mut input)?;
+        let particle_radius = read_f32(&mut input)?;
+        let (width, height) = (read_f32(&mut input)?, read_f32(&mut input)?);
+        let torus = (width > 0.0 && height > 0.0).then_some(Torus { width, height });
+        let symmetry = match version[0] {
+            version if version < SYMMETRY_VERSION => None,
+            _ => {
+                let mut kind = [0];
+                input.read_exact(&mut kind)?;
+                let mut parameter = [0; 4];
+                input.read_exact(&mut parameter)?;
+                let n = u32::from_le_bytes(parameter) as usize;
+                match kind[0] {
+                    0 => None,
+                    1 => Some(Symmetry::Mirror(f32::from_le_bytes(parameter))),
+                    2 => Some(Symmetry::Rotation(n)),
+                    3 => Some(Symmetry::Dihedral(n)),
+                    _ => return Err(ReplayError::Format("an unknown symmetry")),
+                }
+            }
+        };
+
+        Ok(ReplayReader {
+            input,
+            header: ReplayHeader {
+                quantum,
+                particle_radius,
+                torus,
+                symmetry,
+            },
+            slots: Vec::new(),
+        })
+    }
+
+    pub fn header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    /// The next recorded step, or `None` at the end of the replay.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, ReplayError> {
+        let step = match read_varint_or_end(&mut self.input)? {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+        let count = read_varint(&mut self.input)? as usize;
+        self.slots.resize(count, Slot::default());
+
+        let changes = read_varint(&mut self.input)?;
+        let mut expected = 0;
+        for _ in 0..changes {
+            let i = expected + read_varint(&mut self.input)? as usize;
+            let state = read_varint(&mut self.input)? as usize;
+            let slot = self
+                .slots
+                .get_mut(i)
+                .ok_or(ReplayError::Format("a particle out of range"))?;
+            slot.state = state;
+            expected = i + 1;
+        }
+
+        for slot in &mut self.slots {
+            if slot.state == 0 {
+                continue;
+            }
+            for coordinate in &mut slot.position {
+                *coordinate = coordinate.wrapping_add(unzigzag(read_varint(&mut self.input)?));
+            }
+        }
+
+        let quantum = self.header.quantum;
+        Ok(Some(Frame {
+            step,
+            positions: self
+                .slots
+                .iter()
+                .map(|slot| vec2(slot.position[0] as f32, slot.position[1] as f32) * quantum)
+                .collect(),
+            alive: self.slots.iter().map(|slot| slot.state != 0).collect(),
+            next: self
+                .slots
+                .iter()
+                .map(|slot| slot.state.checked_sub(2))
+                .collect(),
+        }))
+    }
+}
+
+fn quantize(x: f32) -> i32 {
+    (x / QUANTUM).round() as i32
+}
+
+fn zigzag(x: i32) -> u64 {
+    ((x << 1) ^ (x >> 31)) as u32 as u64
+}
+
+fn unzigzag(x: u64) -> i32 {
+    let x = x as u32;
+    ((x >> 1) as i32) ^ -((x & 1) as i32)
+}
+
+fn write_varint<W: Write>(out: &mut W, mut x: u64) -> io::Result<()> {
+    while x >= 0x80 {
+        out.write_all(&[(x as u8) | 0x80])?;
+        x >>= 7;
+    }
+    out.write_all(&[x as u8])
+}
+
+fn read_varint<R: Read>(input: &mut R) -> Result<u64, ReplayError> {
+    read_varint_or_end(input)?.ok_or(ReplayError::Format("it ends in the middle of a frame"))
+}
+
+/// Reads a varint, or returns `None` if the input ends before it starts.
+fn read_varint_or_end<R: Read>(input: &mut R) -> Result<Option<u64>, ReplayError> {
+    let mut x = 0;
+    for (i, shift) in (0..64).step_by(7).enumerate() {
+        let mut byte = [0];
+        if input.read(&mut byte)? == 0 {
+            return match i {
+                0 => Ok(None),
+                _ => Err(ReplayError::Format("it ends in the middle of a frame")),
+            };
+        }
+        x |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(x));
+        }
+    }
+    Err(ReplayError::Format("a number is too long"))
+}
+
+fn read_f32<R: Read>(input: &mut R) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_read_back_as_recorded() {
+        let mut ps = ParticleSystem::with_seed(9);
+        let ring: Vec<Vec2> = (0..16)
+            .map(|i| {
+                let angle = i as f32 / 16.0 * std::f32::consts::TAU;
+                vec2(angle.cos(), angle.sin()) * 12.0
+            })
+            .collect();
+        ps.add_curve(&ring, false);
+
+        let mut writer = ReplayWriter::new(Vec::new(), &ps).unwrap();
+        let mut expected = Vec::new();
+        for _ in 0..30 {
+            ps.update();
+            writer.record(&ps).unwrap();
+            expected.push((ps.steps(), ps.positions().to_vec(), ps.num_particles()));
+        }
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = ReplayReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.next_frame().unwrap().unwrap().step, 0);
+        for (step, positions, particles) in expected {
+            let frame = reader.next_frame().unwrap().unwrap();
+            assert_eq!(frame.step, step);
+            assert_eq!(frame.edges().count(), particles);
+            for (i, &alive) in frame.alive.iter().enumerate() {
+                if alive {
+                    assert!(frame.positions[i].distance(positions[i]) <= QUANTUM);
+                }
+            }
+        }
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn the_symmetry_reads_back() {
+        let mut ps = ParticleSystem::with_seed(9);
+        ps.add_curve(&[vec2(5.0, 0.0), vec2(6.0, 1.0), vec2(7.0, 0.0)], true);
+        for symmetry in [
+            None,
+            Some(Symmetry::Mirror(0.5)),
+            Some(Symmetry::Dihedral(6)),
+        ] {
+            ps.symmetry = symmetry;
+            let bytes = ReplayWriter::new(Vec::new(), &ps)
+                .unwrap()
+                .finish()
+                .unwrap();
+            let reader = ReplayReader::new(&bytes[..]).unwrap();
+            assert_eq!(reader.header().symmetry, symmetry);
+        }
+    }
+}
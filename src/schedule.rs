@@ -0,0 +1,103 @@
+//! Parameters changing over simulated time along keyframes, for growth going through stages
+//! without touching any code, e.g. an influence radius ramping up while splits die down.
+
+use serde::{Deserialize, Serialize};
+
+/// How a `Schedule` goes from one keyframe to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// At a constant rate.
+    #[default]
+    Linear,
+    /// Slowly at first and last, so that stages blend into each other.
+    Smooth,
+    /// All at once when reaching the next keyframe.
+    Step,
+}
+
+/// The values a parameter takes over time, given by keyframes.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    /// The dotted path of the parameter within `[simulation]`, e.g. `influence_radius` or
+    /// `throttle.max_particles`.
+    pub parameter: String,
+    /// Pairs of a simulated time and the value of the parameter then, in increasing time.
+    pub keyframes: Vec<[f64; 2]>,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl Schedule {
+    /// The value of the parameter at `time`, held at the first and last keyframes before and
+    /// after them. None without any keyframes.
+    pub fn value_at(&self, time: f64) -> Option<f64> {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|&[keyframe_time, _]| keyframe_time > time);
+        let (start, end) = match next {
+            Some(0) => return self.keyframes.first().map(|&[_, value]| value),
+            Some(next) => (self.keyframes[next - 1], self.keyframes[next]),
+            None => return self.keyframes.last().map(|&[_, value]| value),
+        };
+
+        let [start_time, start_value] = start;
+        let [end_time, end_value] = end;
+        let t = (time - start_time) / (end_time - start_time);
+        let t = match self.easing {
+            Easing::Linear => t,
+            Easing::Smooth => t * t * (3.0 - 2.0 * t),
+            Easing::Step => 0.0,
+        };
+        Some(start_value + (end_value - start_value) * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(easing: Easing) -> Schedule {
+        Schedule {
+            parameter: "influence_radius".to_string(),
+            keyframes: vec![[100.0, 10.0], [200.0, 20.0], [300.0, 0.0]],
+            easing,
+        }
+    }
+
+    #[test]
+    fn values_hold_before_the_first_keyframe_and_after_the_last() {
+        for easing in [Easing::Linear, Easing::Smooth, Easing::Step] {
+            let schedule = schedule(easing);
+            assert_eq!(schedule.value_at(-50.0), Some(10.0));
+            assert_eq!(schedule.value_at(100.0), Some(10.0));
+            assert_eq!(schedule.value_at(300.0), Some(0.0));
+            assert_eq!(schedule.value_at(1e9), Some(0.0));
+        }
+
+        let empty = Schedule {
+            keyframes: Vec::new(),
+            ..schedule(Easing::Linear)
+        };
+        assert_eq!(empty.value_at(0.0), None);
+    }
+
+    #[test]
+    fn easings_go_from_one_keyframe_to_the_next() {
+        let value_at = |easing, time| schedule(easing).value_at(time).unwrap();
+
+        assert!((value_at(Easing::Linear, 125.0) - 12.5).abs() < 1e-9);
+        assert!((value_at(Easing::Linear, 250.0) - 10.0).abs() < 1e-9);
+
+        // Slower than linear near the keyframes, and the same halfway.
+        assert!((value_at(Easing::Smooth, 125.0) - 11.5625).abs() < 1e-9);
+        assert!((value_at(Easing::Smooth, 150.0) - 15.0).abs() < 1e-9);
+        assert!((value_at(Easing::Smooth, 275.0) - 3.125).abs() < 1e-9);
+
+        assert_eq!(value_at(Easing::Step, 199.0), 10.0);
+        assert_eq!(value_at(Easing::Step, 200.0), 20.0);
+        assert_eq!(value_at(Easing::Step, 299.0), 20.0);
+    }
+}
@@ -0,0 +1,189 @@
+//! Growth rules written in Rhai, run every step, to prototype new behaviors without recompiling.
+//!
+//! A script may define either of these functions:
+//!
+//! ```rhai
+//! // Called before every step with the tunable parameters and the state of the whole system.
+//! // The parameters it returns replace the current ones.
+//! fn step(params, state) {
+//!     params.curvature_bias = if state.particles > 2000 { 0.8 } else { 0.0 };
+//!     params
+//! }
+//!
+//! // Called for every particle with its state, returning a displacement added to it.
+//! fn force(p) {
+//!     [0.0, -0.05 * p.age / 100.0]
+//! }
+//! ```
+
+use crate::ParticleSystem;
+use glam::{vec2, Vec2};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use std::fmt;
+use std::path::Path;
+
+/// The parameters `step` receives and may change, matching the fields of `ParticleSystem`.
+const PARAMETERS: [&str; 9] = [
+    "dt",
+    "particle_radius",
+    "influence_radius",
+    "attraction_weight",
+    "pressure_weight",
+    "inter_curve_pressure",
+    "max_edge_length",
+    "min_edge_length",
+    "curvature_bias",
+];
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Load(Box<EvalAltResult>),
+    Run(Box<EvalAltResult>),
+    /// `step` returned something other than a map of numbers, or `force` something other than an
+    /// array of two numbers.
+    Type(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Load(err) => write!(f, "{}", err),
+            ScriptError::Run(err) => write!(f, "{}", err),
+            ScriptError::Type(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// A compiled script, see the module documentation for what it may define.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    has_step: bool,
+    has_force: bool,
+}
+
+impl Script {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile_file(path.as_ref().to_path_buf())
+            .map_err(ScriptError::Load)?;
+        Ok(Script::new(engine, ast))
+    }
+
+    pub fn compile(source: &str) -> Result<Self, ScriptError> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile(source)
+            .map_err(|err| ScriptError::Load(err.into()))?;
+        Ok(Script::new(engine, ast))
+    }
+
+    fn new(engine: Engine, ast: AST) -> Self {
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name);
+        Script {
+            has_step: defines("step"),
+            has_force: defines("force"),
+            engine,
+            ast,
+        }
+    }
+
+    /// Calls `step` with the parameters and state of `ps`, and sets the parameters it returns.
+    pub(crate) fn step(&self, ps: &mut ParticleSystem) -> Result<(), ScriptError> {
+        if !self.has_step {
+            return Ok(());
+        }
+
+        let mut params = Map::new();
+        for name in PARAMETERS {
+            params.insert(name.into(), Dynamic::from_float(*parameter(ps, name)));
+        }
+        let mut state = Map::new();
+        state.insert("step".into(), Dynamic::from_int(ps.steps() as i64));
+        state.insert("time".into(), Dynamic::from_float(ps.time()));
+        state.insert(
+            "particles".into(),
+            Dynamic::from_int(ps.num_particles() as i64),
+        );
+
+        let params: Map = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, "step", (params, state))
+            .map_err(ScriptError::Run)?;
+        for (name, value) in params {
+            if !PARAMETERS.contains(&name.as_str()) {
+                continue;
+            }
+            let value = value.as_float().map_err(|_| {
+                ScriptError::Type(format!("step() returned a non-number for {}", name))
+            })?;
+            *parameter(ps, &name) = value;
+        }
+        Ok(())
+    }
+
+    /// Whether `force()` adds anything, so that gathering the particles can be skipped.
+    pub(crate) fn has_force(&self) -> bool {
+        self.has_force
+    }
+
+    /// Calls `force` with the state of particle `i` of `ps`.
+    pub(crate) fn force(
+        &self,
+        ps: &ParticleSystem,
+        curvature: f32,
+        i: usize,
+    ) -> Result<Vec2, ScriptError> {
+        let position = ps.positions()[i];
+        let mut p = Map::new();
+        p.insert("index".into(), Dynamic::from_int(i as i64));
+        p.insert("x".into(), Dynamic::from_float(position.x));
+        p.insert("y".into(), Dynamic::from_float(position.y));
+        p.insert("age".into(), Dynamic::from_float(ps.ages()[i]));
+        p.insert("curve".into(), Dynamic::from_int(ps.curve_ids()[i] as i64));
+        p.insert("curvature".into(), Dynamic::from_float(curvature));
+        p.insert(
+            "neighbors".into(),
+            Dynamic::from_int(ps.num_neighbors()[i] as i64),
+        );
+        p.insert(
+            "pressure".into(),
+            Dynamic::from_float(ps.pressures()[i].length()),
+        );
+
+        let force: Array = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, "force", (p,))
+            .map_err(ScriptError::Run)?;
+        match force[..] {
+            [ref x, ref y] => match (x.as_float(), y.as_float()) {
+                (Ok(x), Ok(y)) => Ok(vec2(x, y)),
+                _ => Err(ScriptError::Type(
+                    "force() returned non-numbers".to_string(),
+                )),
+            },
+            _ => Err(ScriptError::Type(
+                "force() returned something other than [x, y]".to_string(),
+            )),
+        }
+    }
+}
+
+/// The field of `ps` a name in `PARAMETERS` refers to.
+fn parameter<'a>(ps: &'a mut ParticleSystem, name: &str) -> &'a mut f32 {
+    match name {
+        "dt" => &mut ps.dt,
+        "particle_radius" => &mut ps.particle_radius,
+        "influence_radius" => &mut ps.influence_radius,
+        "attraction_weight" => &mut ps.attraction_weight,
+        "pressure_weight" => &mut ps.pressure_weight,
+        "inter_curve_pressure" => &mut ps.inter_curve_pressure,
+        "max_edge_length" => &mut ps.max_edge_length,
+        "min_edge_length" => &mut ps.min_edge_length,
+        "curvature_bias" => &mut ps.curvature_bias,
+        _ => unreachable!("{} isn't a parameter", name),
+    }
+}
@@ -0,0 +1,533 @@
+//! Drawing the edges and particles with a WGSL fragment shader of one's own, for looks the
+//! built-in drawing doesn't have.
+//!
+//! Each edge and particle is drawn as a quad, shaded by `fs_main` of the shader with the
+//! pressure, age and curvature of its particles, see `shader.wgsl` for everything it receives.
+//! With the `gpu` feature, `ShaderPass::load()` compiles it with wgpu, which renders offscreen
+//! and composites the result over the canvas.
+
+use crate::raster::Canvas;
+use crate::stroke::Thickness;
+use crate::{Color, ParticleSystem};
+use glam::Vec2;
+
+#[cfg(feature = "gpu")]
+pub use self::gpu::{GpuShader, ShaderError};
+
+#[cfg(feature = "gpu")]
+mod gpu {
+    use crate::gpu::{self, GpuError};
+    use crate::instances;
+    use crate::raster::Canvas;
+    use crate::stroke::Thickness;
+    use crate::{Color, ParticleSystem};
+    use bytemuck::{Pod, Zeroable};
+    use glam::{vec2, Vec2};
+    use std::fmt;
+    use std::io;
+
+    /// The images are rendered in one piece, so they are at most this many pixels across.
+    const MAX_SIZE: u32 = 8192;
+    /// Matches `MIN_QUAD_SIZE` of `shader.wgsl`.
+    const MIN_QUAD_SIZE: f32 = 2.0;
+
+    /// Why a shader couldn't be used.
+    #[derive(Debug)]
+    pub enum ShaderError {
+        Read(io::Error),
+        Gpu(GpuError),
+        /// The shader didn't compile, with the messages of the compiler.
+        Compile(String),
+    }
+
+    impl fmt::Display for ShaderError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ShaderError::Read(err) => write!(f, "{}", err),
+                ShaderError::Gpu(err) => write!(f, "{}", err),
+                ShaderError::Compile(message) => write!(f, "{}", message),
+            }
+        }
+    }
+
+    impl std::error::Error for ShaderError {}
+
+    impl From<io::Error> for ShaderError {
+        fn from(err: io::Error) -> Self {
+            ShaderError::Read(err)
+        }
+    }
+
+    impl From<GpuError> for ShaderError {
+        fn from(err: GpuError) -> Self {
+            ShaderError::Gpu(err)
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct Globals {
+        resolution: [f32; 2],
+        time: f32,
+        step: u32,
+    }
+
+    /// Matches `EdgeVertex` of `shader.wgsl`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct EdgeVertex {
+        position: [f32; 2],
+        uv: [f32; 2],
+        color: Color,
+        pressure: f32,
+        age: f32,
+        curvature: f32,
+        width: f32,
+    }
+
+    /// Matches `Particle` of `shader.wgsl`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    struct Particle {
+        center: [f32; 2],
+        color: Color,
+        pressure: f32,
+        age: f32,
+        curvature: f32,
+        width: f32,
+    }
+
+    /// A GPU device with a fragment shader compiled into pipelines for the edges and for the
+    /// particles, which are drawn as instances of one quad.
+    pub struct GpuShader {
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        layout: wgpu::BindGroupLayout,
+        pipelines: Pipelines,
+    }
+
+    struct Pipelines {
+        edges: wgpu::RenderPipeline,
+        particles: wgpu::RenderPipeline,
+    }
+
+    impl GpuShader {
+        /// Picks the default adapter and compiles `source`, blocking until done.
+        pub fn new(source: &str) -> Result<Self, ShaderError> {
+            let limits = wgpu::Limits {
+                max_texture_dimension_2d: MAX_SIZE,
+                ..wgpu::Limits::downlevel_defaults()
+            };
+            let (device, queue) = gpu::open_device(limits)?;
+            let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+            let pipelines = compile(&device, &layout, source)?;
+
+            Ok(GpuShader {
+                device,
+                queue,
+                layout,
+                pipelines,
+            })
+        }
+
+        /// Replaces the shader with `source`, keeping the one so far if it doesn't compile.
+        pub fn recompile(&mut self, source: &str) -> Result<(), ShaderError> {
+            self.pipelines = compile(&self.device, &self.layout, source)?;
+            Ok(())
+        }
+
+        /// Whether `canvas` is small enough to render in one piece.
+        pub fn fits(&self, canvas: &Canvas) -> bool {
+            canvas.width() as u32 <= MAX_SIZE && canvas.height() as u32 <= MAX_SIZE
+        }
+
+        /// Shades the edges of `ps` between the particles at `positions`, then the particles,
+        /// over `canvas`, which has to `fit()`.
+        pub fn draw(
+            &self,
+            canvas: &mut Canvas,
+            ps: &ParticleSystem,
+            positions: &[Vec2],
+            line_color: Color,
+            thickness: &Thickness,
+            time: f32,
+        ) -> Result<(), GpuError> {
+            let (width, height) = (canvas.width() as u32, canvas.height() as u32);
+            let curvatures = ps.curvatures();
+            let edges = edge_vertices(canvas, ps, positions, &curvatures, line_color, thickness);
+            let particles = particles(canvas, ps, positions, &curvatures);
+            if width == 0 || height == 0 || (edges.is_empty() && particles.is_empty()) {
+                return Ok(());
+            }
+            let globals = Globals {
+                resolution: [width as f32, height as f32],
+                time,
+                step: ps.steps() as u32,
+            };
+
+            let buffer = |label, size: u64, usage| {
+                self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            };
+            let copy_dst = wgpu::BufferUsages::COPY_DST;
+            let uniform = buffer(
+                "globals",
+                std::mem::size_of::<Globals>() as u64,
+                wgpu::BufferUsages::UNIFORM | copy_dst,
+            );
+            let vertex_buffer = |label, data: &[u8]| {
+                let vertices = buffer(
+                    label,
+                    data.len() as u64,
+                    wgpu::BufferUsages::VERTEX | copy_dst,
+                );
+                self.queue.write_buffer(&vertices, 0, data);
+                vertices
+            };
+            let edge_buffer = vertex_buffer("edges", bytemuck::cast_slice(&edges));
+            let particle_buffer = vertex_buffer("particles", bytemuck::cast_slice(&particles));
+            // Rows are copied out of the texture at multiples of this many bytes.
+            let row = (4 * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+            let size = row as u64 * height as u64;
+            let readback = buffer("readback", size, wgpu::BufferUsages::MAP_READ | copy_dst);
+            self.queue
+                .write_buffer(&uniform, 0, bytemuck::bytes_of(&globals));
+
+            let extent = wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            };
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("image"),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&Default::default());
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                }],
+            });
+
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            {
+                let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                pass.set_bind_group(0, &bind_group, &[]);
+                if !edges.is_empty() {
+                    pass.set_pipeline(&self.pipelines.edges);
+                    pass.set_vertex_buffer(0, edge_buffer.slice(..));
+                    pass.draw(0..edges.len() as u32, 0..1);
+                }
+                // The six corners of the two triangles of a quad, for every particle at once.
+                if !particles.is_empty() {
+                    pass.set_pipeline(&self.pipelines.particles);
+                    pass.set_vertex_buffer(0, particle_buffer.slice(..));
+                    pass.draw(0..6, 0..particles.len() as u32);
+                }
+            }
+            encoder.copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &readback,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(row),
+                        rows_per_image: Some(height),
+                    },
+                },
+                extent,
+            );
+            self.queue.submit(Some(encoder.finish()));
+
+            // The colors were blended over transparent black, which leaves them premultiplied.
+            let bytes: Vec<u8> = gpu::read_back(&self.device, &readback, size)?;
+            let (width, row) = (width as usize, row as usize);
+            for (y, pixels) in canvas.pixels_mut().chunks_mut(width).enumerate() {
+                for (x, pixel) in pixels.iter_mut().enumerate() {
+                    let offset = y * row + 4 * x;
+                    let shaded = &bytes[offset..offset + 4];
+                    let alpha = shaded[3] as f32 / 255.0;
+                    for c in 0..4 {
+                        pixel[c] = shaded[c] as f32 / 255.0 + pixel[c] * (1.0 - alpha);
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Compiles the fragment shader `source` after `shader.wgsl`, with the errors of the driver
+    /// caught rather than panicking.
+    fn compile(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        source: &str,
+    ) -> Result<Pipelines, ShaderError> {
+        let source = format!("{}\n{}", include_str!("shader.wgsl"), source);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point, buffer: wgpu::VertexBufferLayout| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point,
+                    compilation_options: Default::default(),
+                    buffers: &[buffer],
+                },
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: "fs_main",
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let pipelines = Pipelines {
+            edges: pipeline(
+                "vs_edge",
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<EdgeVertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x2,
+                        1 => Float32x2,
+                        2 => Float32x4,
+                        3 => Float32,
+                        4 => Float32,
+                        5 => Float32,
+                        6 => Float32,
+                    ],
+                },
+            ),
+            particles: pipeline(
+                "vs_particle",
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Particle>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x2,
+                        1 => Float32x4,
+                        2 => Float32,
+                        3 => Float32,
+                        4 => Float32,
+                        5 => Float32,
+                    ],
+                },
+            ),
+        };
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(err) => Err(ShaderError::Compile(err.to_string())),
+            None => Ok(pipelines),
+        }
+    }
+
+    /// Maps the plane to the pixels of `canvas`, from its top left corner, along with how many
+    /// pixels a unit is.
+    fn to_pixels(canvas: &Canvas) -> (impl Fn(Vec2) -> Vec2, f32) {
+        let (min, max) = canvas.view_bounds();
+        let scale = canvas.width() as f32 / (max.x - min.x);
+        (move |p: Vec2| vec2(p.x - min.x, max.y - p.y) * scale, scale)
+    }
+
+    /// Two triangles for every edge of `ps` and its copies under the symmetry, in the pixels of
+    /// `canvas`.
+    fn edge_vertices(
+        canvas: &Canvas,
+        ps: &ParticleSystem,
+        positions: &[Vec2],
+        curvatures: &[f32],
+        line_color: Color,
+        thickness: &Thickness,
+    ) -> Vec<EdgeVertex> {
+        let (to_pixel, scale) = to_pixels(canvas);
+        let widths = thickness.widths(ps);
+        let vertex = |i: usize, position: Vec2, uv: Vec2, width: f32| EdgeVertex {
+            position: position.into(),
+            uv: uv.into(),
+            color: line_color,
+            pressure: ps.pressures()[i].length(),
+            age: ps.ages()[i],
+            curvature: curvatures[i],
+            width,
+        };
+
+        let mut vertices = Vec::new();
+        let transforms = ps.symmetry_transforms();
+        for &transform in &transforms {
+            for (i, next) in ps.links().edges() {
+                let (start, end) = ps.edge_at(positions, i, next);
+                let (start, end) = (to_pixel(transform * start), to_pixel(transform * end));
+                let across = (end - start).perp().normalize_or_zero();
+                let (w0, w1) = (widths[i] * scale, widths[next] * scale);
+                let (a, b) = (
+                    across * w0.max(MIN_QUAD_SIZE) / 2.0,
+                    across * w1.max(MIN_QUAD_SIZE) / 2.0,
+                );
+                let corners = [
+                    vertex(i, start - a, vec2(0.0, -1.0), w0),
+                    vertex(i, start + a, vec2(0.0, 1.0), w0),
+                    vertex(next, end + b, vec2(1.0, 1.0), w1),
+                    vertex(next, end - b, vec2(1.0, -1.0), w1),
+                ];
+                vertices.extend([0, 1, 2, 0, 2, 3].map(|k| corners[k]));
+            }
+        }
+        vertices
+    }
+
+    /// The particles of `ps` and their copies under the symmetry as instances, in the pixels of
+    /// `canvas`.
+    fn particles(
+        canvas: &Canvas,
+        ps: &ParticleSystem,
+        positions: &[Vec2],
+        curvatures: &[f32],
+    ) -> Vec<Particle> {
+        let (to_pixel, scale) = to_pixels(canvas);
+        instances::particles(ps, positions)
+            .into_iter()
+            .map(|instance| Particle {
+                center: to_pixel(instance.position).into(),
+                color: instance.color,
+                pressure: ps.pressures()[instance.index].length(),
+                age: ps.ages()[instance.index],
+                curvature: curvatures[instance.index],
+                width: instance.size * scale,
+            })
+            .collect()
+    }
+}
+
+/// Draws with the shader `load()` compiled, if one is loaded.
+#[derive(Default)]
+pub struct ShaderPass {
+    #[cfg(feature = "gpu")]
+    gpu: Option<(GpuShader, std::time::Instant)>,
+}
+
+impl ShaderPass {
+    /// Compiles the fragment shader at `path`, on the device of the one loaded so far if there
+    /// is one. When it doesn't compile, the one loaded so far stays.
+    #[cfg(feature = "gpu")]
+    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<(), ShaderError> {
+        let source = std::fs::read_to_string(path)?;
+        match &mut self.gpu {
+            Some((shader, _)) => shader.recompile(&source)?,
+            None => self.gpu = Some((GpuShader::new(&source)?, std::time::Instant::now())),
+        }
+        Ok(())
+    }
+
+    /// Stops drawing with the shader.
+    pub fn unload(&mut self) {
+        *self = ShaderPass::default();
+    }
+
+    #[cfg(feature = "gpu")]
+    pub fn is_loaded(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    #[cfg(not(feature = "gpu"))]
+    pub fn is_loaded(&self) -> bool {
+        false
+    }
+
+    /// Shades the edges of `ps` between the particles at `positions` and then the particles over
+    /// `canvas`, with `line_color` and `thickness` passed on like the built-in drawing uses them.
+    /// Returns whether it did, which it doesn't without a shader loaded, for canvases too large
+    /// for the GPU or if the GPU fails.
+    #[cfg(feature = "gpu")]
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas,
+        ps: &ParticleSystem,
+        positions: &[Vec2],
+        line_color: Color,
+        thickness: &Thickness,
+    ) -> bool {
+        match &self.gpu {
+            Some((shader, loaded)) if shader.fits(canvas) => {
+                let time = loaded.elapsed().as_secs_f32();
+                shader
+                    .draw(canvas, ps, positions, line_color, thickness, time)
+                    .is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    #[cfg(not(feature = "gpu"))]
+    pub fn draw(
+        &self,
+        _canvas: &mut Canvas,
+        _ps: &ParticleSystem,
+        _positions: &[Vec2],
+        _line_color: Color,
+        _thickness: &Thickness,
+    ) -> bool {
+        false
+    }
+}
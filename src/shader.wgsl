@@ -0,0 +1,107 @@
+// Put in front of the fragment shaders set as `[render] shader`. They define
+// `@fragment fn fs_main(in: Fragment) -> @location(0) vec4<f32>`, shading edges and particles,
+// which are drawn as quads.
+
+struct Globals {
+    // The size of the image in pixels.
+    resolution: vec2<f32>,
+    // Seconds since the shader was loaded.
+    time: f32,
+    // The steps simulated so far.
+    step: u32,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+
+// What `Fragment.kind` is for the quads of edges and of particles.
+const EDGE: u32 = 0u;
+const PARTICLE: u32 = 1u;
+
+// How many pixels across quads are at least, so that thin ones still cover pixels.
+const MIN_QUAD_SIZE: f32 = 2.0;
+
+// A corner of the quad of an edge.
+struct EdgeVertex {
+    // In pixels, from the top left corner.
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) pressure: f32,
+    @location(4) age: f32,
+    @location(5) curvature: f32,
+    @location(6) width: f32,
+}
+
+// A particle, drawn as an instance of the same quad.
+struct Particle {
+    // In pixels, from the top left corner.
+    @location(0) center: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) pressure: f32,
+    @location(3) age: f32,
+    @location(4) curvature: f32,
+    @location(5) width: f32,
+}
+
+struct Fragment {
+    @builtin(position) position: vec4<f32>,
+    // Along edges, x goes from 0 at the first particle to 1 at the second and y from -1 to 1
+    // across. Over particles, both go from -1 to 1. Quads are at least 2 pixels across, so they
+    // can reach past the thickness or radius of thin edges and small particles.
+    @location(0) uv: vec2<f32>,
+    // The color the built-in drawing uses, the line color for edges and the particle's own.
+    @location(1) color: vec4<f32>,
+    // How hard the particle is pushed by its neighbors, blended between the two along edges.
+    @location(2) pressure: f32,
+    // The simulated time since the particle was added.
+    @location(3) age: f32,
+    // From 0 where the curve is straight to 1 where it folds back on itself.
+    @location(4) curvature: f32,
+    // How thick the edge is in pixels, blended between its particles, or the particle across.
+    // The built-in drawing fades what is thinner than a pixel instead of thinning it.
+    @location(5) width: f32,
+    @location(6) @interpolate(flat) kind: u32,
+}
+
+fn clip_position(pixel: vec2<f32>) -> vec4<f32> {
+    let ndc = pixel / globals.resolution * 2.0 - 1.0;
+    return vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+}
+
+@vertex
+fn vs_edge(vertex: EdgeVertex) -> Fragment {
+    var out: Fragment;
+    out.position = clip_position(vertex.position);
+    out.uv = vertex.uv;
+    out.color = vertex.color;
+    out.pressure = vertex.pressure;
+    out.age = vertex.age;
+    out.curvature = vertex.curvature;
+    out.width = vertex.width;
+    out.kind = EDGE;
+    return out;
+}
+
+@vertex
+fn vs_particle(@builtin(vertex_index) corner: u32, particle: Particle) -> Fragment {
+    // The two triangles of the quad.
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let uv = corners[corner];
+    var out: Fragment;
+    out.position = clip_position(particle.center + uv * max(particle.width, MIN_QUAD_SIZE) / 2.0);
+    out.uv = uv;
+    out.color = particle.color;
+    out.pressure = particle.pressure;
+    out.age = particle.age;
+    out.curvature = particle.curvature;
+    out.width = particle.width;
+    out.kind = PARTICLE;
+    return out;
+}
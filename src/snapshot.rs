@@ -0,0 +1,50 @@
+//! A borrowed view of a particle system's state, for exporters and external renderers that read
+//! every particle each frame without copying them.
+
+use crate::{Color, Link, ParticleSystem};
+use glam::Vec2;
+
+/// The state of a `ParticleSystem` after some step, as parallel slices indexed like its
+/// particles. Slots of removed particles are included, marked as not `alive`.
+#[derive(Clone, Copy, Debug)]
+pub struct Snapshot<'a> {
+    pub steps: u64,
+    pub positions: &'a [Vec2],
+    pub colors: &'a [Color],
+    pub alive: &'a [bool],
+    /// The index into `ParticleSystem::curves()` of the curve each particle belongs to.
+    pub curve_ids: &'a [usize],
+    pub links: &'a [Link],
+}
+
+impl Snapshot<'_> {
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Every edge as a particle and its successor.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.links
+            .iter()
+            .enumerate()
+            .filter_map(|(i, link)| link.next.map(|next| (i, next)))
+    }
+}
+
+impl ParticleSystem {
+    /// Borrows the current state, valid until the next change to the system.
+    pub fn snapshot(&self) -> Snapshot<'_> {
+        Snapshot {
+            steps: self.steps(),
+            positions: self.positions(),
+            colors: self.colors(),
+            alive: self.alive(),
+            curve_ids: self.curve_ids(),
+            links: self.links().as_slice(),
+        }
+    }
+}
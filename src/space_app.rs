@@ -0,0 +1,149 @@
+//! The window of `--3d`, growing a single curve in space seen through an orbiting camera.
+
+use crate::{load_config, APP_NAME};
+use differential_lines::cli::Args;
+use differential_lines::{vec2, Config, FixedTimestep, OrbitCamera, SpaceCurve, Vec2};
+use nannou::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// How far the camera turns, in radians, per pixel the mouse is dragged.
+const ORBIT_SPEED: f32 = 0.01;
+
+pub struct Model {
+    config: Config,
+    curve: SpaceCurve,
+    seed: u64,
+    paused: bool,
+    timestep: FixedTimestep,
+    /// Follows the curve as it grows, backed up to fit all of it and then scaled by `zoom`.
+    camera: OrbitCamera,
+    zoom: f32,
+    /// Where the mouse was last while the left button is held down to orbit.
+    drag: Option<Vec2>,
+}
+
+pub fn model(app: &App) -> Model {
+    let args = Args::parse(APP_NAME);
+    let (_, config) = load_config(&args);
+
+    app.new_window()
+        .with_dimensions(config.window.width, config.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .mouse_pressed(mouse_pressed)
+        .mouse_released(mouse_released)
+        .mouse_moved(mouse_moved)
+        .mouse_wheel(mouse_wheel)
+        .build()
+        .unwrap();
+
+    let seed = args.seed.unwrap_or_else(random);
+    println!("Seed: {}", seed);
+    let timestep = FixedTimestep::new(config.simulation.steps_per_second);
+
+    Model {
+        curve: config.new_space_curve(seed),
+        config,
+        seed,
+        paused: false,
+        timestep,
+        camera: OrbitCamera::default(),
+        zoom: 1.0,
+        drag: None,
+    }
+}
+
+fn key_pressed(_app: &App, m: &mut Model, key: Key) {
+    match key {
+        Key::Space => m.paused = !m.paused,
+        Key::Back => m.curve = m.config.new_space_curve(m.seed),
+        Key::F => m.zoom = 1.0,
+        Key::E => {
+            let name = format!("diff-lines-3d-{}-{}.obj", m.seed, m.curve.steps());
+            let path = Path::new("output").join(name);
+            let result =
+                fs::create_dir_all("output").and_then(|()| fs::write(&path, m.curve.to_obj()));
+            match result {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(err) => eprintln!("Failed to save {}: {}", path.display(), err),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn mouse_pressed(app: &App, m: &mut Model, button: MouseButton) {
+    if button == MouseButton::Left {
+        m.drag = Some(vec2(app.mouse.x, app.mouse.y));
+    }
+}
+
+fn mouse_released(_app: &App, m: &mut Model, button: MouseButton) {
+    if button == MouseButton::Left {
+        m.drag = None;
+    }
+}
+
+fn mouse_moved(_app: &App, m: &mut Model, position: Point2) {
+    let position = vec2(position.x, position.y);
+    if let Some(last) = m.drag {
+        let delta = position - last;
+        m.camera
+            .orbit(delta.x * ORBIT_SPEED, -delta.y * ORBIT_SPEED);
+        m.drag = Some(position);
+    }
+}
+
+fn mouse_wheel(_app: &App, m: &mut Model, delta: MouseScrollDelta, _phase: TouchPhase) {
+    let lines = match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(p) => p.y as f32 / 20.0,
+    };
+    m.zoom = (m.zoom / 1.1f32.powf(lines)).clamp(0.05, 10.0);
+}
+
+pub fn update(_app: &App, m: &mut Model, update: Update) {
+    if !m.paused {
+        for _ in 0..m.timestep.advance(update.since_last) {
+            m.curve.update();
+        }
+    }
+
+    if let Some(bounds) = m.curve.bounding_box() {
+        m.camera.fit(bounds);
+        m.camera.distance *= m.zoom;
+    }
+}
+
+fn view(app: &App, m: &Model, frame: Frame) -> Frame {
+    let draw = app.draw();
+    let [r, g, b, a] = m.config.colors.background;
+    draw.background().color(Rgba::new(r, g, b, a));
+
+    // Projected points span -1 to 1 vertically, which fills the shorter side of the window.
+    let (w, h) = app.window_rect().w_h();
+    let scale = w.min(h) / 2.0;
+    let [lr, lg, lb, _] = m.config.colors.line;
+
+    let positions = m.curve.positions();
+    for (i, next) in m.curve.links().edges() {
+        let (start, end) = match (
+            m.camera.project(positions[i]),
+            m.camera.project(positions[next]),
+        ) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+        let shade = m.camera.shade((start.1 + end.1) / 2.0);
+        draw.line()
+            .start(pt2(start.0.x * scale, start.0.y * scale))
+            .end(pt2(end.0.x * scale, end.0.y * scale))
+            .thickness(1.0)
+            .rgba(lr, lg, lb, shade);
+    }
+
+    draw.to_frame(app, &frame).unwrap();
+
+    frame
+}
@@ -0,0 +1,348 @@
+//! Differential growth of a closed curve in space instead of the plane, which crumples into knots
+//! rather than filling a region. Seen through an `OrbitCamera` and exported as OBJ.
+
+use crate::grid::SpaceGrid;
+use crate::links::{Link, Links};
+use glam::{vec2, vec3, Vec2, Vec3};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use rayon::prelude::*;
+use std::f32::consts::{PI, TAU};
+use std::fmt::Write;
+
+/// A single closed curve growing in space, with the same forces and rules as `ParticleSystem`
+/// but none of its fields, boundaries or coloring.
+pub struct SpaceCurve {
+    pub influence_radius: f32,
+    /// How strongly particles are pulled toward their neighbors along the curve each step.
+    pub attraction_weight: f32,
+    /// How strongly particles are pushed away from nearby particles each step.
+    pub pressure_weight: f32,
+    /// Edges longer than this get split in half by inserting a new particle.
+    pub max_edge_length: f32,
+    /// Edges shorter than this collapse by merging their two particles into one.
+    pub min_edge_length: f32,
+    /// Edges whose particles have this many neighbors between them or more never split.
+    pub max_split_neighbors: usize,
+    /// How much splitting favors tightly curved parts of the curve, from 0 to 1.
+    pub curvature_bias: f32,
+    steps: u64,
+    num_particles: usize,
+    /// Removed particles stay as tombstones recycled through `free`, like in `ParticleSystem`.
+    alive: Vec<bool>,
+    free: Vec<usize>,
+    positions: Vec<Vec3>,
+    links: Links,
+    num_neighbors: Vec<usize>,
+    grid: SpaceGrid,
+    seed: u64,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl SpaceCurve {
+    /// Creates an empty curve with the default parameters of `ParticleSystem`, whose randomness
+    /// is entirely derived from `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        SpaceCurve {
+            influence_radius: 12.0,
+            attraction_weight: 0.6,
+            pressure_weight: 0.2,
+            max_edge_length: 3.0,
+            min_edge_length: 1.0,
+            max_split_neighbors: 16,
+            curvature_bias: 0.0,
+            steps: 0,
+            num_particles: 0,
+            alive: Vec::new(),
+            free: Vec::new(),
+            positions: Vec::new(),
+            links: Links::new(),
+            num_neighbors: Vec::new(),
+            grid: SpaceGrid::default(),
+            seed,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn num_particles(&self) -> usize {
+        self.num_particles
+    }
+
+    /// The positions of every particle slot, including removed ones.
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    pub fn links(&self) -> &Links {
+        &self.links
+    }
+
+    /// The smallest box containing every particle, as its minimum and maximum corners.
+    pub fn bounding_box(&self) -> Option<(Vec3, Vec3)> {
+        (0..self.positions.len())
+            .filter(|&i| self.alive[i])
+            .map(|i| self.positions[i])
+            .fold(None, |bounds, p| match bounds {
+                Some((min, max)) => Some((p.min(min), p.max(max))),
+                None => Some((p, p)),
+            })
+    }
+
+    /// The particles along the curve in order, starting anywhere. The curve is closed, so the
+    /// last one links back to the first.
+    pub fn points(&self) -> Vec<Vec3> {
+        match (0..self.alive.len()).find(|&i| self.alive[i]) {
+            Some(start) => self
+                .links
+                .walk(start)
+                .0
+                .into_iter()
+                .map(|i| self.positions[i])
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Adds a circle of `num_particles` in the horizontal plane, nudged up and down by a little
+    /// noise so that growth can leave the plane.
+    pub fn spawn_ring(&mut self, num_particles: usize, radius: f32) {
+        let points: Vec<Vec3> = (0..num_particles.max(3))
+            .map(|i| {
+                let angle = i as f32 / num_particles.max(3) as f32 * TAU;
+                let height = (self.rng.gen::<f32>() - 0.5) * self.min_edge_length;
+                vec3(angle.cos() * radius, height, angle.sin() * radius)
+            })
+            .collect();
+        self.add_ring(&points);
+    }
+
+    /// Adds a closed curve going through `points`. Only one curve is grown, so this replaces any
+    /// previous one.
+    pub fn add_ring(&mut self, points: &[Vec3]) {
+        self.num_particles = points.len();
+        self.alive = vec![true; points.len()];
+        self.free.clear();
+        self.positions = points.to_vec();
+        self.num_neighbors = vec![0; points.len()];
+        self.links = Links::new();
+        for _ in points {
+            self.links.push();
+        }
+        for i in 0..points.len() {
+            self.links.link(i, (i + 1) % points.len());
+        }
+    }
+
+    /// How many particles are within the influence radius of particle `i`, and how far its pull
+    /// toward the midpoint of its neighbors along the curve and its push away from those nearby
+    /// particles move it.
+    fn forces_of(&self, i: usize) -> (usize, Vec3) {
+        if !self.alive[i] {
+            return (0, Vec3::ZERO);
+        }
+        let position = self.positions[i];
+        let attraction = match self.links.get(i) {
+            Link {
+                prev: Some(b0),
+                next: Some(b1),
+            } => (self.positions[b0] + self.positions[b1]) / 2.0 - position,
+            _ => Vec3::ZERO,
+        };
+
+        let radius = self.influence_radius;
+        let (mut num_neighbors, mut pressure) = (0, Vec3::ZERO);
+        self.grid.for_each_candidate(position, radius, |j| {
+            if j == i || !self.alive[j] || (position - self.positions[j]).length() > radius {
+                return;
+            }
+            num_neighbors += 1;
+            pressure += (position - self.positions[j]) / (radius * 0.5);
+        });
+        let pressure = pressure.clamp_length_max(2.0);
+
+        (
+            num_neighbors,
+            attraction * self.attraction_weight + pressure * self.pressure_weight,
+        )
+    }
+
+    pub fn update(&mut self) {
+        self.grid.rebuild(self.influence_radius, &self.positions);
+
+        let forces: Vec<(usize, Vec3)> = (0..self.positions.len())
+            .into_par_iter()
+            .map(|i| self.forces_of(i))
+            .collect();
+        for (i, (num_neighbors, force)) in forces.into_iter().enumerate() {
+            self.num_neighbors[i] = num_neighbors;
+            self.positions[i] += force;
+        }
+
+        for p0 in 0..self.links.len() {
+            let p1 = match self.links.next(p0) {
+                Some(next) => next,
+                None => continue,
+            };
+            let length = (self.positions[p1] - self.positions[p0]).length();
+            if self.num_neighbors[p0] + self.num_neighbors[p1] >= self.max_split_neighbors
+                || length <= self.max_edge_length
+            {
+                continue;
+            }
+
+            let curvature = (self.curvature(p0) + self.curvature(p1)) / 2.0;
+            let probability = 1.0 - self.curvature_bias * (1.0 - curvature);
+            if probability >= 1.0 || self.rng.gen::<f32>() < probability {
+                let position = (self.positions[p0] + self.positions[p1]) / 2.0;
+                let i = self.add_particle(position);
+                self.links.insert_after(p0, i);
+            }
+        }
+
+        for p0 in 0..self.links.len() {
+            let p1 = match self.links.next(p0) {
+                Some(next) => next,
+                None => continue,
+            };
+            let is_triangle = self.links.next(p1).and_then(|p2| self.links.next(p2)) == Some(p0);
+            let length = (self.positions[p1] - self.positions[p0]).length();
+            if length < self.min_edge_length && !is_triangle {
+                self.positions[p0] = (self.positions[p0] + self.positions[p1]) / 2.0;
+                self.links.remove(p1);
+                self.alive[p1] = false;
+                self.free.push(p1);
+                self.num_particles -= 1;
+            }
+        }
+
+        self.steps += 1;
+    }
+
+    fn add_particle(&mut self, position: Vec3) -> usize {
+        self.num_particles += 1;
+        if let Some(i) = self.free.pop() {
+            self.alive[i] = true;
+            self.positions[i] = position;
+            self.num_neighbors[i] = 0;
+            return i;
+        }
+        self.alive.push(true);
+        self.positions.push(position);
+        self.num_neighbors.push(0);
+        self.links.push()
+    }
+
+    /// How sharply the curve turns at particle `i`, from 0 when straight to 1 when it folds back
+    /// on itself.
+    fn curvature(&self, i: usize) -> f32 {
+        match self.links.get(i) {
+            Link {
+                prev: Some(prev),
+                next: Some(next),
+            } => {
+                let incoming = self.positions[i] - self.positions[prev];
+                let outgoing = self.positions[next] - self.positions[i];
+                incoming.angle_between(outgoing) / PI
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// The curve as a Wavefront OBJ file of its vertices and a single closed line element.
+    pub fn to_obj(&self) -> String {
+        let points = self.points();
+        let mut obj = format!(
+            "# Differential line growth, {} particles after {} steps with seed {}\no curve\n",
+            points.len(),
+            self.steps,
+            self.seed
+        );
+        for p in &points {
+            writeln!(obj, "v {} {} {}", p.x, p.y, p.z).unwrap();
+        }
+        if !points.is_empty() {
+            obj.push('l');
+            // Indices start at 1, and repeating the first one closes the curve.
+            for i in (1..=points.len()).chain([1]) {
+                write!(obj, " {}", i).unwrap();
+            }
+            obj.push('\n');
+        }
+        obj
+    }
+}
+
+/// Looks at a point from a distance, orbiting around it, and projects space onto the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitCamera {
+    pub target: Vec3,
+    /// The angle around the vertical axis, in radians.
+    pub yaw: f32,
+    /// The angle above the horizontal plane, in radians, kept short of straight up or down.
+    pub pitch: f32,
+    pub distance: f32,
+    /// The vertical field of view, in radians.
+    pub fov: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        OrbitCamera {
+            target: Vec3::ZERO,
+            yaw: 0.6,
+            pitch: 0.4,
+            distance: 500.0,
+            fov: PI / 4.0,
+        }
+    }
+}
+
+impl OrbitCamera {
+    /// Turns around the target by the given angles.
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let limit = PI / 2.0 - 0.01;
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-limit, limit);
+    }
+
+    /// Aims at the middle of the box between `min` and `max`, backing up until it fits.
+    pub fn fit(&mut self, (min, max): (Vec3, Vec3)) {
+        let radius = ((max - min).length() / 2.0).max(1.0);
+        self.target = (min + max) / 2.0;
+        self.distance = radius / (self.fov / 2.0).sin();
+    }
+
+    /// Where `p` appears, with the field of view spanning -1 to 1 vertically, and how far it is
+    /// in front of the camera. None for points behind it.
+    pub fn project(&self, p: Vec3) -> Option<(Vec2, f32)> {
+        let p = p - self.target;
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+
+        let x = p.x * cos_yaw - p.z * sin_yaw;
+        let z = p.x * sin_yaw + p.z * cos_yaw;
+        let y = p.y * cos_pitch - z * sin_pitch;
+        let z = p.y * sin_pitch + z * cos_pitch;
+
+        let depth = self.distance + z;
+        if depth <= 1e-3 {
+            return None;
+        }
+        let focal = 1.0 / (self.fov / 2.0).tan();
+        Some((vec2(x, y) * focal / depth, depth))
+    }
+
+    /// How bright a line at `depth` is drawn, fading the far side of the target to give a sense
+    /// of depth.
+    pub fn shade(&self, depth: f32) -> f32 {
+        (1.5 - depth / self.distance).clamp(0.2, 1.0)
+    }
+}
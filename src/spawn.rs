@@ -0,0 +1,185 @@
+//! Shapes for the initial curves the growth starts from.
+
+use crate::cyclic;
+use crate::{ParticleSystem, Polyline};
+use glam::{vec2, Vec2};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+/// An initial curve, sized by a radius around the origin unless it has its own points.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnShape {
+    /// A circle.
+    Circle,
+    /// A circle with a wavy radius.
+    Ring,
+    /// A straight open curve along the x axis.
+    Line,
+    /// A rectangle twice as wide as it is tall.
+    Rectangle,
+    /// A five pointed star, with the inner corners at half the radius.
+    Star,
+    /// A circle with a radius randomly modulated by a few low frequencies.
+    RandomBlob,
+    /// The closed polygon through the given points, in simulation units.
+    Polygon(Vec<Vec2>),
+}
+
+impl FromStr for SpawnShape {
+    type Err = toml::de::Error;
+
+    /// Parses the TOML value used in config files, where plain names can go without quotes, e.g.
+    /// `star` or `{ polygon = [[0, 0], [100, 0], [0, 100]] }`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[derive(Deserialize)]
+        struct Value {
+            shape: SpawnShape,
+        }
+
+        toml::from_str::<Value>(&format!("shape = \"{}\"", s))
+            .or_else(|_| toml::from_str::<Value>(&format!("shape = {}", s)))
+            .map(|value| value.shape)
+    }
+}
+
+impl SpawnShape {
+    /// Whether the shape is a closed ring rather than a curve with two endpoints.
+    pub fn is_closed(&self) -> bool {
+        *self != SpawnShape::Line
+    }
+
+    /// Places `num_points` points along the shape. Randomized shapes draw from `rng`.
+    pub fn points<R: Rng>(&self, num_points: usize, radius: f32, rng: &mut R) -> Vec<Vec2> {
+        match self {
+            SpawnShape::Circle => around(num_points, |_| radius),
+            SpawnShape::Ring => around(num_points, |phi| radius + (phi * 6.2).sin() * 50.0),
+            SpawnShape::Line => {
+                let n = num_points.max(2);
+                (0..n)
+                    .map(|i| {
+                        let t = i as f32 / (n - 1) as f32;
+                        // A slight bend gives the pressure something to push against.
+                        vec2((t * 2.0 - 1.0) * radius, (t * PI).sin())
+                    })
+                    .collect()
+            }
+            SpawnShape::Rectangle => {
+                let (w, h) = (radius, radius / 2.0);
+                let corners = [vec2(-w, -h), vec2(w, -h), vec2(w, h), vec2(-w, h)];
+                resample(&corners, num_points)
+            }
+            SpawnShape::Star => {
+                let corners: Vec<_> = (0..10)
+                    .map(|i| {
+                        let phi = PI / 2.0 + i as f32 * PI / 5.0;
+                        let r = if i % 2 == 0 { radius } else { radius / 2.0 };
+                        vec2(phi.cos(), phi.sin()) * r
+                    })
+                    .collect();
+                resample(&corners, num_points)
+            }
+            SpawnShape::RandomBlob => {
+                let harmonics: Vec<(f32, f32, f32)> = (2..6)
+                    .map(|k| {
+                        let amplitude = rng.gen::<f32>() * 0.3 / k as f32;
+                        (k as f32, amplitude, rng.gen::<f32>() * 2.0 * PI)
+                    })
+                    .collect();
+                around(num_points, |phi| {
+                    let wobble: f32 = harmonics
+                        .iter()
+                        .map(|&(k, amplitude, phase)| amplitude * (k * phi + phase).sin())
+                        .sum();
+                    radius * (1.0 + wobble)
+                })
+            }
+            SpawnShape::Polygon(corners) => resample(corners, num_points),
+        }
+    }
+}
+
+/// Places points at evenly spaced angles around the origin, `radius(phi)` away from it.
+fn around<F>(num_points: usize, radius: F) -> Vec<Vec2>
+where
+    F: Fn(f32) -> f32,
+{
+    (0..num_points)
+        .map(|i| {
+            let phi = i as f32 * 2.0 * PI / num_points as f32;
+            vec2(phi.cos(), phi.sin()) * radius(phi)
+        })
+        .collect()
+}
+
+/// Places points evenly spaced along the perimeter of the closed polygon through `corners`.
+fn resample(corners: &[Vec2], num_points: usize) -> Vec<Vec2> {
+    let segments: Vec<(Vec2, Vec2)> = (0..corners.len())
+        .map(|i| (corners[i], corners[cyclic::offset(i, 1, corners.len())]))
+        .collect();
+    let perimeter: f32 = segments.iter().map(|(a, b)| (*b - *a).length()).sum();
+    if perimeter <= 0.0 {
+        return corners.to_vec();
+    }
+
+    let spacing = perimeter / num_points as f32;
+    let mut points = Vec::with_capacity(num_points);
+    let mut offset = 0.0;
+    for (a, b) in segments {
+        let length = (b - a).length();
+        while offset < length && points.len() < num_points {
+            points.push(a.lerp(b, offset / length));
+            offset += spacing;
+        }
+        offset -= length;
+    }
+
+    points
+}
+
+impl ParticleSystem {
+    /// Adds a curve of `num_particles` particles in the given shape and returns its index.
+    pub fn spawn(&mut self, shape: &SpawnShape, num_particles: usize, radius: f32) -> usize {
+        let points = shape.points(num_particles, radius, self.rng());
+        self.add_curve(&points, !shape.is_closed())
+    }
+
+    /// Adds a copy of the shape for each of `radii`, scaled about the origin so the copies nest
+    /// inside each other without crossing, with particles in proportion to their size.
+    /// `num_particles` and `radius` are those of a full size copy. Returns the new curves.
+    pub fn spawn_nested(
+        &mut self,
+        shape: &SpawnShape,
+        num_particles: usize,
+        radius: f32,
+        radii: &[f32],
+    ) -> Vec<usize> {
+        // Every copy draws the same random numbers, so randomized shapes nest too.
+        let rng = self.rng().clone();
+
+        let mut curves = Vec::with_capacity(radii.len());
+        for &r in radii {
+            let scale = r / radius;
+            let n = ((num_particles as f32 * scale).round() as usize).max(3);
+            let points: Vec<Vec2> = shape
+                .points(n, radius, &mut rng.clone())
+                .into_iter()
+                .map(|p| p * scale)
+                .collect();
+            curves.push(self.add_curve(&points, !shape.is_closed()));
+        }
+        curves
+    }
+
+    /// Adds a curve for each polyline, skipping those too short to form one, and returns the
+    /// indices of the new curves.
+    pub fn spawn_polylines(&mut self, polylines: &[Polyline]) -> Vec<usize> {
+        polylines
+            .iter()
+            .filter(|polyline| polyline.points.len() >= if polyline.closed { 3 } else { 2 })
+            .map(|polyline| self.add_curve(&polyline.points, !polyline.closed))
+            .collect()
+    }
+}
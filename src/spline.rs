@@ -0,0 +1,70 @@
+//! Smooth curves through the particles, for drawing low particle counts without visible corners.
+
+use crate::cyclic;
+use glam::Vec2;
+
+/// Samples a Catmull-Rom spline through `points`, dividing the span between each pair of points
+/// into `subdivisions` pieces. The spline passes through every point, so the samples include
+/// them. With `subdivisions` of 1 or less the points are returned as they are.
+pub fn catmull_rom(points: &[Vec2], closed: bool, subdivisions: usize) -> Vec<Vec2> {
+    let n = points.len();
+    if subdivisions <= 1 || n < 3 {
+        return points.to_vec();
+    }
+
+    // Open curves are extended by mirroring their ends, so the spline reaches the endpoints.
+    let at = |i: isize| -> Vec2 {
+        if closed {
+            points[cyclic::wrap(i, n)]
+        } else if i < 0 {
+            points[0] * 2.0 - points[1]
+        } else if i >= n as isize {
+            points[n - 1] * 2.0 - points[n - 2]
+        } else {
+            points[i as usize]
+        }
+    };
+
+    let spans = if closed { n } else { n - 1 };
+    let mut samples = Vec::with_capacity(spans * subdivisions + 1);
+    for i in 0..spans as isize {
+        let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+        for s in 0..subdivisions {
+            let t = s as f32 / subdivisions as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            samples.push(
+                (p1 * 2.0
+                    + (p2 - p0) * t
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+                    * 0.5,
+            );
+        }
+    }
+    if !closed {
+        samples.push(points[n - 1]);
+    }
+    samples
+}
+
+/// Linearly interpolates `values` given at each point to the samples of `catmull_rom()` with the
+/// same arguments.
+pub fn subdivide_values(values: &[f32], closed: bool, subdivisions: usize) -> Vec<f32> {
+    let n = values.len();
+    if subdivisions <= 1 || n < 3 {
+        return values.to_vec();
+    }
+
+    let spans = if closed { n } else { n - 1 };
+    let mut samples = Vec::with_capacity(spans * subdivisions + 1);
+    for i in 0..spans {
+        let (a, b) = (values[i], values[cyclic::offset(i, 1, n)]);
+        for s in 0..subdivisions {
+            samples.push(a + (b - a) * s as f32 / subdivisions as f32);
+        }
+    }
+    if !closed {
+        samples.push(values[n - 1]);
+    }
+    samples
+}
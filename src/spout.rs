@@ -0,0 +1,285 @@
+//! Frames shared with other apps on the same Windows machine through Spout, for compositing live
+//! in Resolume or OBS. SpoutLibrary is loaded when sharing starts, which needs the `spout`
+//! feature.
+
+#[cfg(feature = "spout")]
+pub use sender::SpoutSender;
+
+/// Bindings to the few methods of SpoutLibrary's C++ interface that sending pixels needs, and to
+/// the Win32 functions making the OpenGL context Spout shares them through.
+#[cfg(feature = "spout")]
+mod sender {
+    use crate::Canvas;
+    use libloading::Library;
+    use std::error::Error;
+    use std::ffi::{c_char, c_int, c_uint, c_void, CString};
+    use std::path::Path;
+    use std::ptr;
+
+    /// Where the 64 bit SpoutLibrary of the Spout SDK is found, next to the app or on the `PATH`.
+    const LIBRARY_NAME: &str = "SpoutLibrary.dll";
+
+    /// The places of the methods used in the virtual table of `SPOUTLIBRARY`, in the order
+    /// `SpoutLibrary.h` of Spout 2.007 declares them: `SetSenderName`, `SetSenderFormat`,
+    /// `ReleaseSender`, `SendFbo`, `SendTexture` and `SendImage` come first.
+    const SET_SENDER_NAME: usize = 0;
+    const RELEASE_SENDER: usize = 2;
+    const SEND_IMAGE: usize = 5;
+    /// `GL_RGBA`, 8 bits per component in memory order.
+    const GL_RGBA: c_uint = 0x1908;
+
+    /// `PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | PFD_DOUBLEBUFFER`.
+    const PIXEL_FORMAT_FLAGS: u32 = 0x4 | 0x20 | 0x1;
+
+    /// The C++ object `GetSpout()` returns, of which only the virtual table is reached into.
+    #[repr(C)]
+    struct SpoutLibrary {
+        methods: *const *const c_void,
+    }
+
+    type Handle = *mut c_void;
+    // On 64 bit Windows, methods take the object first and follow the C calling convention.
+    type SetSenderName = unsafe extern "C" fn(*mut SpoutLibrary, *const c_char);
+    type ReleaseSender = unsafe extern "C" fn(*mut SpoutLibrary, u32);
+    type SendImage = unsafe extern "C" fn(
+        *mut SpoutLibrary,
+        *const u8,
+        c_uint,
+        c_uint,
+        c_uint,
+        bool,
+        c_uint,
+    ) -> bool;
+
+    /// `PIXELFORMATDESCRIPTOR`, asking for any RGBA format OpenGL can draw to a window with.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PixelFormat {
+        size: u16,
+        version: u16,
+        flags: u32,
+        pixel_type: u8,
+        color_bits: u8,
+        // The channel, accumulation, depth and stencil bits up to the layer type, left to the
+        // driver.
+        bits: [u8; 18],
+        layer_mask: u32,
+        visible_mask: u32,
+        damage_mask: u32,
+    }
+
+    /// A hidden window with an OpenGL context current on the thread that created it, which
+    /// Spout needs to share textures while the app draws with Vulkan.
+    struct GlContext {
+        window: Handle,
+        device: Handle,
+        context: Handle,
+        destroy_window: unsafe extern "system" fn(Handle) -> c_int,
+        release_dc: unsafe extern "system" fn(Handle, Handle) -> c_int,
+        make_current: unsafe extern "system" fn(Handle, Handle) -> c_int,
+        delete_context: unsafe extern "system" fn(Handle) -> c_int,
+        // Keeps the functions above loaded, so they have to outlive them.
+        _libraries: [Library; 3],
+    }
+
+    impl GlContext {
+        fn new() -> Result<Self, Box<dyn Error>> {
+            unsafe {
+                let user32 = Library::new("user32.dll")?;
+                let gdi32 = Library::new("gdi32.dll")?;
+                let opengl32 = Library::new("opengl32.dll")?;
+                let create_window = *user32.get::<unsafe extern "system" fn(
+                    u32,
+                    *const c_char,
+                    *const c_char,
+                    u32,
+                    c_int,
+                    c_int,
+                    c_int,
+                    c_int,
+                    Handle,
+                    Handle,
+                    Handle,
+                    *mut c_void,
+                ) -> Handle>(b"CreateWindowExA")?;
+                let destroy_window =
+                    *user32.get::<unsafe extern "system" fn(Handle) -> c_int>(b"DestroyWindow")?;
+                let get_dc =
+                    *user32.get::<unsafe extern "system" fn(Handle) -> Handle>(b"GetDC")?;
+                let release_dc = *user32
+                    .get::<unsafe extern "system" fn(Handle, Handle) -> c_int>(b"ReleaseDC")?;
+                let choose_pixel_format =
+                    *gdi32.get::<unsafe extern "system" fn(Handle, *const PixelFormat) -> c_int>(
+                        b"ChoosePixelFormat",
+                    )?;
+                let set_pixel_format = *gdi32.get::<unsafe extern "system" fn(
+                    Handle,
+                    c_int,
+                    *const PixelFormat,
+                ) -> c_int>(b"SetPixelFormat")?;
+                let create_context = *opengl32
+                    .get::<unsafe extern "system" fn(Handle) -> Handle>(b"wglCreateContext")?;
+                let make_current = *opengl32
+                    .get::<unsafe extern "system" fn(Handle, Handle) -> c_int>(b"wglMakeCurrent")?;
+                let delete_context = *opengl32
+                    .get::<unsafe extern "system" fn(Handle) -> c_int>(b"wglDeleteContext")?;
+
+                // A window of the built-in static class, never shown.
+                let class = CString::new("STATIC")?;
+                let window = create_window(
+                    0,
+                    class.as_ptr(),
+                    class.as_ptr(),
+                    0,
+                    0,
+                    0,
+                    1,
+                    1,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                );
+                if window.is_null() {
+                    return Err("failed to create a window for OpenGL".into());
+                }
+                let mut gl = GlContext {
+                    window,
+                    device: get_dc(window),
+                    context: ptr::null_mut(),
+                    destroy_window,
+                    release_dc,
+                    make_current,
+                    delete_context,
+                    _libraries: [user32, gdi32, opengl32],
+                };
+
+                let format = PixelFormat {
+                    size: std::mem::size_of::<PixelFormat>() as u16,
+                    version: 1,
+                    flags: PIXEL_FORMAT_FLAGS,
+                    color_bits: 32,
+                    ..PixelFormat::default()
+                };
+                let index = choose_pixel_format(gl.device, &format);
+                if index == 0 || set_pixel_format(gl.device, index, &format) == 0 {
+                    return Err("no pixel format supports OpenGL".into());
+                }
+                gl.context = create_context(gl.device);
+                if gl.context.is_null() || make_current(gl.device, gl.context) == 0 {
+                    return Err("failed to create an OpenGL context".into());
+                }
+                Ok(gl)
+            }
+        }
+    }
+
+    impl Drop for GlContext {
+        fn drop(&mut self) {
+            unsafe {
+                if !self.context.is_null() {
+                    (self.make_current)(ptr::null_mut(), ptr::null_mut());
+                    (self.delete_context)(self.context);
+                }
+                (self.release_dc)(self.window, self.device);
+                (self.destroy_window)(self.window);
+            }
+        }
+    }
+
+    /// A Spout sender other apps on the machine see under its name, sharing the frames given to
+    /// it. Sending has to happen on the thread that started it, where its OpenGL context is.
+    pub struct SpoutSender {
+        spout: *mut SpoutLibrary,
+        send_image: SendImage,
+        release_sender: ReleaseSender,
+        // Dropped after the sender is released, in the order of the fields.
+        _gl: GlContext,
+        _library: Library,
+    }
+
+    impl SpoutSender {
+        /// Loads SpoutLibrary from `library`, or from next to the app or the `PATH`, and starts
+        /// a sender called `name`.
+        pub fn start(name: &str, library: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+            if !cfg!(all(windows, target_pointer_width = "64")) {
+                return Err("Spout only runs on 64 bit Windows".into());
+            }
+            let library = unsafe { Library::new(library.unwrap_or(Path::new(LIBRARY_NAME))) }
+                .map_err(|err| format!("{}, set spout.library to SpoutLibrary.dll", err))?;
+            let name = CString::new(name)?;
+            let gl = GlContext::new()?;
+
+            unsafe {
+                let get_spout = *library
+                    .get::<unsafe extern "system" fn() -> *mut SpoutLibrary>(b"GetSpout")?;
+                let spout = get_spout();
+                if spout.is_null() {
+                    return Err("failed to create a Spout sender".into());
+                }
+                let method = |index: usize| *(*spout).methods.add(index);
+                let set_sender_name: SetSenderName = std::mem::transmute(method(SET_SENDER_NAME));
+                let release_sender: ReleaseSender = std::mem::transmute(method(RELEASE_SENDER));
+                let send_image: SendImage = std::mem::transmute(method(SEND_IMAGE));
+                set_sender_name(spout, name.as_ptr());
+
+                Ok(SpoutSender {
+                    spout,
+                    send_image,
+                    release_sender,
+                    _gl: gl,
+                    _library: library,
+                })
+            }
+        }
+
+        /// Shares the canvas as the next frame, resizing the shared texture along with it. Spout
+        /// copies it before returning.
+        pub fn send(&mut self, canvas: &Canvas) -> Result<(), Box<dyn Error>> {
+            let pixels = canvas.to_rgba8();
+            let (width, height) = (canvas.width() as c_uint, canvas.height() as c_uint);
+            // The rows are already top down, like the shared texture.
+            let sent = unsafe {
+                (self.send_image)(
+                    self.spout,
+                    pixels.as_ptr(),
+                    width,
+                    height,
+                    GL_RGBA,
+                    false,
+                    0,
+                )
+            };
+            match sent {
+                true => Ok(()),
+                false => Err("Spout failed to share the frame".into()),
+            }
+        }
+    }
+
+    impl Drop for SpoutSender {
+        fn drop(&mut self) {
+            // The object's own `Release()` comes last in a virtual table that grows with every
+            // version of the SDK, so it is left to the end of the process rather than guessed at.
+            unsafe { (self.release_sender)(self.spout, 0) };
+        }
+    }
+}
+
+/// Stands in for the sender without the `spout` feature, failing to start.
+#[cfg(not(feature = "spout"))]
+pub enum SpoutSender {}
+
+#[cfg(not(feature = "spout"))]
+impl SpoutSender {
+    pub fn start(
+        _name: &str,
+        _library: Option<&std::path::Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("sharing over Spout needs the spout feature".into())
+    }
+
+    pub fn send(&mut self, _canvas: &crate::Canvas) -> Result<(), Box<dyn std::error::Error>> {
+        match *self {}
+    }
+}
@@ -0,0 +1,105 @@
+//! Checkpoints of the whole simulation, so long growths can be stopped and resumed later.
+//!
+//! Files ending in `.json` are written as JSON, anything else as the much smaller MessagePack.
+
+use crate::ParticleSystem;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum StateError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Encode(rmp_serde::encode::Error),
+    Decode(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::Io(err) => write!(f, "{}", err),
+            StateError::Json(err) => write!(f, "{}", err),
+            StateError::Encode(err) => write!(f, "{}", err),
+            StateError::Decode(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl From<io::Error> for StateError {
+    fn from(err: io::Error) -> Self {
+        StateError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for StateError {
+    fn from(err: serde_json::Error) -> Self {
+        StateError::Json(err)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for StateError {
+    fn from(err: rmp_serde::encode::Error) -> Self {
+        StateError::Encode(err)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for StateError {
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        StateError::Decode(err)
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().and_then(|extension| extension.to_str()) == Some("json")
+}
+
+impl ParticleSystem {
+    /// Writes the full state of the simulation to `path`, creating its directory if needed.
+    pub fn save_state<P: AsRef<Path>>(&self, path: P) -> Result<(), StateError> {
+        let path = path.as_ref();
+        let bytes = if is_json(path) {
+            serde_json::to_vec(self)?
+        } else {
+            rmp_serde::to_vec_named(self)?
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Reads a simulation written by `save_state()`.
+    pub fn load_state<P: AsRef<Path>>(path: P) -> Result<Self, StateError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+
+        let mut ps: ParticleSystem = if is_json(path) {
+            serde_json::from_slice(&bytes)?
+        } else {
+            rmp_serde::from_slice(&bytes)?
+        };
+        ps.fill_untracked();
+        Ok(ps)
+    }
+
+    /// The full state of the simulation as MessagePack, like `save_state()` writes, but kept in
+    /// memory.
+    pub fn encode_state(&self) -> Result<Vec<u8>, StateError> {
+        Ok(rmp_serde::to_vec_named(self)?)
+    }
+
+    /// Goes back to a state from `encode_state()`, still computing on the GPU if it did.
+    pub fn restore_state(&mut self, bytes: &[u8]) -> Result<(), StateError> {
+        let mut ps: ParticleSystem = rmp_serde::from_slice(bytes)?;
+        ps.fill_untracked();
+        ps.keep_runtime_of(self);
+        *self = ps;
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! Summary figures of a particle system, for its coloring and the app's overlay.
+
+use crate::ParticleSystem;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub particles: usize,
+    pub edges: usize,
+    pub mean_edge_length: f32,
+    /// How strongly particles got pushed by their neighbors in the last step.
+    pub pressure: Summary,
+}
+
+/// The distribution of some value over the particles.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub max: f32,
+    pub mean: f32,
+    pub median: f32,
+    pub p90: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+impl Summary {
+    /// Summarizes `values`, or is all zeros without any.
+    pub fn of(values: impl Iterator<Item = f32>) -> Self {
+        let mut values: Vec<f32> = values.collect();
+        if values.is_empty() {
+            return Summary::default();
+        }
+        values.sort_unstable_by(f32::total_cmp);
+
+        let percentile = |q: f32| values[((values.len() - 1) as f32 * q).round() as usize];
+        Summary {
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f32>() / values.len() as f32,
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+        }
+    }
+}
+
+impl Stats {
+    pub fn of(ps: &ParticleSystem) -> Self {
+        let snapshot = ps.snapshot();
+        let positions = snapshot.positions;
+        let (edges, length) = snapshot.edges().fold((0, 0.0), |(edges, length), (a, b)| {
+            (edges + 1, length + (positions[b] - positions[a]).length())
+        });
+
+        Stats {
+            particles: ps.num_particles(),
+            edges,
+            mean_edge_length: if edges > 0 {
+                length / edges as f32
+            } else {
+                0.0
+            },
+            pressure: ps.pressure_summary(),
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! How the edges between particles are drawn, with a thickness that may vary along the curves.
+
+use crate::spline;
+use crate::{ParticleSystem, Torus};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// The thickness edges are drawn with when it doesn't vary, in simulation units.
+pub const UNIFORM_THICKNESS: f32 = 0.1;
+
+/// How many triangles round joins and caps take per half turn.
+const ROUND_TRIANGLES: usize = 8;
+
+/// How thick the edges between particles are drawn, in simulation units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Thickness {
+    /// `UNIFORM_THICKNESS` everywhere.
+    #[default]
+    Uniform,
+    /// From `min` where the curve is straight to `max` where it bends the most.
+    Curvature { min: f32, max: f32 },
+    /// From `min` in calm regions to `max` where particles are pushed the hardest, in the folds.
+    Pressure { min: f32, max: f32 },
+}
+
+impl Thickness {
+    /// The thickness of the curve at each particle, indexed like `ParticleSystem::positions()`.
+    pub fn widths(&self, ps: &ParticleSystem) -> Vec<f32> {
+        let (values, min, max) = match *self {
+            Thickness::Uniform => return vec![UNIFORM_THICKNESS; ps.positions().len()],
+            Thickness::Curvature { min, max } => (ps.curvatures(), min, max),
+            Thickness::Pressure { min, max } => {
+                let pressures = ps.pressures().iter().map(|p| p.length()).collect();
+                (pressures, min, max)
+            }
+        };
+
+        // Relative to the largest value, so the whole range is used whatever the parameters.
+        let largest = values.iter().cloned().fold(0.0, f32::max);
+        values
+            .iter()
+            .map(|&value| min + (max - min) * value / largest.max(f32::EPSILON))
+            .collect()
+    }
+}
+
+/// How the edges meet where a curve turns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Join {
+    /// Extends the sides until they meet, but no further than twice the thickness away.
+    #[default]
+    Miter,
+    /// Rounds the outside of the corner off.
+    Round,
+    /// Cuts the outside of the corner off straight.
+    Bevel,
+}
+
+/// How the ends of open curves are drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cap {
+    /// Ends flat at the last particle.
+    #[default]
+    Butt,
+    /// Ends in a half disc around the last particle.
+    Round,
+    /// Ends flat half the thickness past the last particle.
+    Square,
+}
+
+/// A curve of the particle system as it is drawn, with its thickness at every point.
+pub struct StrokePath {
+    pub points: Vec<Vec2>,
+    pub widths: Vec<f32>,
+    pub closed: bool,
+}
+
+/// The curves of `ps` as they are drawn with `thickness`, with every edge divided into
+/// `subdivisions` pieces along a smooth spline, see `spline::catmull_rom()`. With a symmetry, the
+/// copies of the curves under it follow them. On a torus, the curves are cut where they cross its
+/// edges, see `split_at_edges()`.
+pub fn stroke_paths(
+    ps: &ParticleSystem,
+    thickness: &Thickness,
+    subdivisions: usize,
+) -> Vec<StrokePath> {
+    stroke_paths_at(ps, ps.positions(), thickness, subdivisions)
+}
+
+/// Like `stroke_paths()`, with the particles at `positions` rather than where they are.
+pub fn stroke_paths_at(
+    ps: &ParticleSystem,
+    positions: &[Vec2],
+    thickness: &Thickness,
+    subdivisions: usize,
+) -> Vec<StrokePath> {
+    let widths = thickness.widths(ps);
+
+    let mut paths: Vec<StrokePath> = ps
+        .polyline_indices()
+        .into_iter()
+        .map(|(indices, closed)| {
+            let points = ps.curve_points_at(positions, &indices);
+            let widths: Vec<f32> = indices.iter().map(|&i| widths[i]).collect();
+            StrokePath {
+                points: spline::catmull_rom(&points, closed, subdivisions),
+                widths: spline::subdivide_values(&widths, closed, subdivisions),
+                closed,
+            }
+        })
+        .collect();
+    if let Some(torus) = &ps.torus {
+        paths = paths
+            .into_iter()
+            .flat_map(|path| split_at_edges(path, torus))
+            .collect();
+    }
+    if ps.symmetry.is_none() {
+        return paths;
+    }
+    ps.symmetry_transforms()
+        .into_iter()
+        .flat_map(|transform| {
+            paths.iter().map(move |path| StrokePath {
+                points: path.points.iter().map(|&p| transform * p).collect(),
+                widths: path.widths.clone(),
+                closed: path.closed,
+            })
+        })
+        .collect()
+}
+
+/// Cuts `path`, unwrapped across the edges of `torus`, into the pieces between the edges it
+/// crosses, each moved back into the rectangle. The pieces reach one point across the edges on
+/// both sides so that they meet there, and a curve going all the way around is cut open rather
+/// than closed up across the rectangle.
+fn split_at_edges(path: StrokePath, torus: &Torus) -> Vec<StrokePath> {
+    let size = torus.size();
+    let period = |p: Vec2| ((p + size / 2.0) / size).floor();
+    let n = path.points.len();
+    if n < 2 {
+        return vec![path];
+    }
+
+    // Past its end, a closed curve goes around again, moved by however far it winds around.
+    let lap = if path.closed {
+        let last = path.points[n - 1];
+        last + torus.offset(last, path.points[0]) - path.points[0]
+    } else {
+        Vec2::ZERO
+    };
+    let at = |k: usize| {
+        (
+            path.points[k % n] + lap * (k / n) as f32,
+            path.widths[k % n],
+        )
+    };
+    let segments = if path.closed { n } else { n - 1 };
+    // A closed curve is started right after an edge it crosses, so that no piece wraps around.
+    let start = match (0..segments).find(|&k| period(at(k).0) != period(at(k + 1).0)) {
+        Some(k) if path.closed => k + 1,
+        Some(_) => 0,
+        None => return vec![path],
+    };
+
+    let last = start + n - 1;
+    let mut pieces = Vec::new();
+    let mut from = start;
+    for k in start..=last {
+        if k < last && period(at(k).0) == period(at(k + 1).0) {
+            continue;
+        }
+        let shift = period(at(from).0) * size;
+        let before = if path.closed || from > 0 {
+            from - 1
+        } else {
+            from
+        };
+        let after = if path.closed || k < last { k + 1 } else { k };
+        let (points, widths) = (before..=after)
+            .map(|j| {
+                let (p, width) = at(j);
+                (p - shift, width)
+            })
+            .unzip();
+        pieces.push(StrokePath {
+            points,
+            widths,
+            closed: false,
+        });
+        from = k + 1;
+    }
+    pieces
+}
+
+/// All of `paths` in one triangle mesh, see `stroke_with()`, so that they are drawn at once
+/// instead of an edge at a time.
+pub fn mesh(paths: &[StrokePath], join: Join, cap: Cap) -> (Vec<Vec2>, Vec<[usize; 3]>) {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    for path in paths {
+        let (path_vertices, path_triangles) =
+            stroke_with(&path.points, &path.widths, path.closed, join, cap);
+        let first = vertices.len();
+        vertices.extend(path_vertices);
+        triangles.extend(
+            path_triangles
+                .into_iter()
+                .map(|[a, b, c]| [first + a, first + b, first + c]),
+        );
+    }
+    (vertices, triangles)
+}
+
+/// Builds a triangle mesh covering the polyline through `points`, `widths[i]` thick at
+/// `points[i]`, with mitered joins and butt caps. Returns the vertices along with triples of
+/// indices into them.
+pub fn stroke(points: &[Vec2], widths: &[f32], closed: bool) -> (Vec<Vec2>, Vec<[usize; 3]>) {
+    stroke_with(points, widths, closed, Join::Miter, Cap::Butt)
+}
+
+/// Like `stroke()`, with `join` where the polyline turns and `cap` at the ends of open ones.
+pub fn stroke_with(
+    points: &[Vec2],
+    widths: &[f32],
+    closed: bool,
+    join: Join,
+    cap: Cap,
+) -> (Vec<Vec2>, Vec<[usize; 3]>) {
+    let n = points.len();
+    let mut vertices = Vec::with_capacity(n * 2);
+    let mut triangles = Vec::with_capacity(n * 2);
+    if n < 2 {
+        return (vertices, triangles);
+    }
+
+    let mut points = points.to_vec();
+    if !closed {
+        let start = (points[1] - points[0]).normalize_or_zero();
+        let end = (points[n - 1] - points[n - 2]).normalize_or_zero();
+        match cap {
+            Cap::Butt => {}
+            Cap::Round => {
+                for (center, from, radius) in [
+                    (points[0], start.perp(), widths[0] / 2.0),
+                    (points[n - 1], -end.perp(), widths[n - 1] / 2.0),
+                ] {
+                    vertices.push(center);
+                    let hub = vertices.len() - 1;
+                    fan(&mut vertices, &mut triangles, hub, center, from, PI, radius);
+                }
+            }
+            Cap::Square => {
+                points[0] -= start * widths[0] / 2.0;
+                points[n - 1] += end * widths[n - 1] / 2.0;
+            }
+        }
+    }
+
+    ribbon(&points, widths, closed, join, &mut vertices, &mut triangles);
+    (vertices, triangles)
+}
+
+/// Adds the ribbon through `points`, with a `join` wherever it turns.
+fn ribbon(
+    points: &[Vec2],
+    widths: &[f32],
+    closed: bool,
+    join: Join,
+    vertices: &mut Vec<Vec2>,
+    triangles: &mut Vec<[usize; 3]>,
+) {
+    let n = points.len();
+    // The left and right vertices each point starts its outgoing segment from, and those its
+    // incoming segment ends at, which differ on the outside of round and bevel joins.
+    let mut starts = Vec::with_capacity(n);
+    let mut ends = Vec::with_capacity(n);
+
+    for i in 0..n {
+        let prev = match i {
+            0 if closed => Some(points[n - 1]),
+            0 => None,
+            _ => Some(points[i - 1]),
+        };
+        let next = match i {
+            _ if i == n - 1 && closed => Some(points[0]),
+            _ if i == n - 1 => None,
+            _ => Some(points[i + 1]),
+        };
+
+        let p = points[i];
+        let incoming = prev.map_or(Vec2::ZERO, |prev| (p - prev).normalize_or_zero());
+        let outgoing = next.map_or(Vec2::ZERO, |next| (next - p).normalize_or_zero());
+        let normal = (incoming + outgoing).normalize_or_zero().perp();
+
+        // The sides meet at the miter, so the stroke keeps its width around corners, but the
+        // miter is capped to keep sharp corners from spiking out.
+        let edge_normal = if outgoing != Vec2::ZERO {
+            outgoing.perp()
+        } else {
+            incoming.perp()
+        };
+        let miter = 1.0 / normal.dot(edge_normal).max(0.25);
+        let offset = normal * widths[i] / 2.0 * miter;
+
+        let turn = incoming.perp_dot(outgoing);
+        let first = vertices.len();
+        if join == Join::Miter || turn == 0.0 {
+            vertices.push(p + offset);
+            vertices.push(p - offset);
+            starts.push([first, first + 1]);
+            ends.push([first, first + 1]);
+            continue;
+        }
+
+        // The outside of the corner is to the right of a left turn and to the left of a right
+        // one. The inside keeps the miter, and the outside is filled in from it.
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let (from, to) = (incoming.perp() * side, outgoing.perp() * side);
+        let radius = widths[i] / 2.0;
+        vertices.push(p - offset * side);
+        vertices.push(p + from * radius);
+        vertices.push(p + to * radius);
+        if side > 0.0 {
+            starts.push([first + 2, first]);
+            ends.push([first + 1, first]);
+        } else {
+            starts.push([first, first + 2]);
+            ends.push([first, first + 1]);
+        }
+        match join {
+            Join::Round => {
+                let angle = from.perp_dot(to).atan2(from.dot(to));
+                fan(vertices, triangles, first, p, from, angle, radius);
+            }
+            _ => triangles.push([first, first + 1, first + 2]),
+        }
+    }
+
+    let segments = if closed { n } else { n - 1 };
+    for i in 0..segments {
+        let ([a, b], [c, d]) = (starts[i], ends[(i + 1) % n]);
+        triangles.push([a, b, c]);
+        triangles.push([b, d, c]);
+    }
+}
+
+/// Adds the triangles from the vertex `hub` to the arc `radius` around `center`, swept
+/// counterclockwise by `angle` from the unit vector `from`.
+fn fan(
+    vertices: &mut Vec<Vec2>,
+    triangles: &mut Vec<[usize; 3]>,
+    hub: usize,
+    center: Vec2,
+    from: Vec2,
+    angle: f32,
+    radius: f32,
+) {
+    let steps = ((angle.abs() / PI * ROUND_TRIANGLES as f32).ceil() as usize).max(1);
+    let first = vertices.len();
+    for k in 0..=steps {
+        let rotation = Vec2::from_angle(angle * k as f32 / steps as f32);
+        vertices.push(center + rotation.rotate(from) * radius);
+    }
+    triangles.extend((0..steps).map(|k| [hub, first + k, first + k + 1]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec2;
+
+    const TORUS: Torus = Torus {
+        width: 10.0,
+        height: 10.0,
+    };
+
+    fn path(points: Vec<Vec2>, closed: bool) -> StrokePath {
+        let widths = vec![1.0; points.len()];
+        StrokePath {
+            points,
+            widths,
+            closed,
+        }
+    }
+
+    #[test]
+    fn curves_inside_the_torus_stay_whole() {
+        let square = vec![
+            vec2(-1.0, -1.0),
+            vec2(1.0, -1.0),
+            vec2(1.0, 1.0),
+            vec2(-1.0, 1.0),
+        ];
+        let pieces = split_at_edges(path(square.clone(), true), &TORUS);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].points, square);
+        assert!(pieces[0].closed);
+    }
+
+    #[test]
+    fn curves_are_cut_where_they_cross_the_edges() {
+        let points = vec![
+            vec2(3.0, 0.0),
+            vec2(4.5, 0.0),
+            vec2(5.5, 0.0),
+            vec2(7.0, 0.0),
+        ];
+        let pieces = split_at_edges(path(points, false), &TORUS);
+        let points: Vec<Vec<Vec2>> = pieces.into_iter().map(|piece| piece.points).collect();
+        assert_eq!(
+            points,
+            vec![
+                vec![vec2(3.0, 0.0), vec2(4.5, 0.0), vec2(5.5, 0.0)],
+                vec![vec2(-5.5, 0.0), vec2(-4.5, 0.0), vec2(-3.0, 0.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn curves_winding_around_the_torus_are_cut_open() {
+        let points: Vec<Vec2> = (0..10).map(|i| vec2(i as f32 - 4.5, 0.0)).collect();
+        let pieces = split_at_edges(path(points, true), &TORUS);
+        assert_eq!(pieces.len(), 1);
+        assert!(!pieces[0].closed);
+        assert_eq!(pieces[0].points.len(), 12);
+        assert_eq!(pieces[0].points[0], vec2(-5.5, 0.0));
+        assert_eq!(pieces[0].points[11], vec2(5.5, 0.0));
+    }
+}
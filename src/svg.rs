@@ -0,0 +1,323 @@
+//! SVG export of the grown curves, and parsing of SVG path data into polylines.
+
+use crate::{History, ParticleSystem, Polyline};
+use glam::{vec2, Vec2};
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+use svgtypes::{PointsParser, SimplePathSegment, SimplifyingPathParser};
+
+/// Blank space left around the curves, in simulation units.
+const MARGIN: f32 = 10.0;
+
+impl ParticleSystem {
+    /// Renders every curve as a stroked SVG path, one `<path>` per curve, with the points in the
+    /// order they are linked so the output can go straight to a pen plotter.
+    ///
+    /// The simulation's y axis points up, so it is flipped to match SVG's.
+    pub fn to_svg(&self) -> String {
+        let polylines = self.polylines();
+
+        let mut svg = svg_header(&polylines, "");
+        write_paths(&mut svg, &polylines, "  ");
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+impl History {
+    /// Renders every snapshot like `ParticleSystem::to_svg()`, each in a group that Inkscape and
+    /// plotting tools treat as its own layer, so each growth stage can be plotted with a
+    /// different pen. Layers go from the oldest snapshot at the bottom to the newest on top.
+    pub fn to_svg(&self) -> String {
+        let mut svg = svg_header(
+            self.snapshots().flat_map(|snapshot| &snapshot.polylines),
+            r#" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape""#,
+        );
+        for snapshot in self.snapshots() {
+            writeln!(
+                svg,
+                r#"  <g id="step-{0}" inkscape:groupmode="layer" inkscape:label="Step {0}">"#,
+                snapshot.step
+            )
+            .unwrap();
+            write_paths(&mut svg, &snapshot.polylines, "    ");
+            svg.push_str("  </g>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Opens an `<svg>` element whose view box fits `polylines`, with any extra `attributes`.
+fn svg_header<'a, I>(polylines: I, attributes: &str) -> String
+where
+    I: IntoIterator<Item = &'a Polyline>,
+{
+    let mut min = Vec2::splat(f32::INFINITY);
+    let mut max = Vec2::splat(f32::NEG_INFINITY);
+    let mut empty = true;
+    for p in polylines.into_iter().flat_map(|polyline| &polyline.points) {
+        let p = flip(*p);
+        min = min.min(p);
+        max = max.max(p);
+        empty = false;
+    }
+    if empty {
+        min = Vec2::ZERO;
+        max = Vec2::ZERO;
+    }
+    let min = min - Vec2::splat(MARGIN);
+    let size = max - min + Vec2::splat(MARGIN);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg"{} viewBox="{:.3} {:.3} {:.3} {:.3}" width="{:.3}" height="{:.3}">"#,
+        attributes, min.x, min.y, size.x, size.y, size.x, size.y
+    )
+    .unwrap();
+    svg
+}
+
+/// Writes one stroked `<path>` per polyline, each on its own line starting with `indent`.
+fn write_paths(svg: &mut String, polylines: &[Polyline], indent: &str) {
+    for polyline in polylines {
+        let mut points = polyline.points.iter().map(|&p| flip(p));
+        let first = match points.next() {
+            Some(first) => first,
+            None => continue,
+        };
+
+        let mut d = format!("M {:.3} {:.3}", first.x, first.y);
+        for p in points {
+            write!(d, " L {:.3} {:.3}", p.x, p.y).unwrap();
+        }
+        if polyline.closed {
+            d.push_str(" Z");
+        }
+
+        writeln!(
+            svg,
+            r#"{}<path d="{}" fill="none" stroke="black" stroke-width="0.5"/>"#,
+            indent, d
+        )
+        .unwrap();
+    }
+}
+
+fn flip(p: Vec2) -> Vec2 {
+    Vec2::new(p.x, -p.y)
+}
+
+/// Parses SVG path data (the `d` attribute of a `<path>`) into one polyline per subpath, with
+/// curves flattened and straight segments subdivided so that consecutive points are at most
+/// `spacing` apart.
+///
+/// As with `to_svg()`, the y axis is flipped to match the simulation's.
+pub fn parse_path(d: &str, spacing: f32) -> Result<Vec<Polyline>, svgtypes::Error> {
+    let mut polylines = Vec::new();
+    let mut points: Vec<Vec2> = Vec::new();
+    let mut start = Vec2::ZERO;
+    let mut current = Vec2::ZERO;
+
+    fn finish(polylines: &mut Vec<Polyline>, points: &mut Vec<Vec2>, closed: bool) {
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if !points.is_empty() {
+            polylines.push(Polyline {
+                points: std::mem::take(points),
+                closed,
+            });
+        }
+    }
+
+    for segment in SimplifyingPathParser::from(d) {
+        match segment? {
+            SimplePathSegment::MoveTo { x, y } => {
+                finish(&mut polylines, &mut points, false);
+                current = point(x, y);
+                start = current;
+                points.push(current);
+            }
+            SimplePathSegment::LineTo { x, y } => {
+                let to = point(x, y);
+                flatten(&mut points, spacing, current, |t| current.lerp(to, t));
+                current = to;
+            }
+            SimplePathSegment::Quadratic { x1, y1, x, y } => {
+                let (c, to) = (point(x1, y1), point(x, y));
+                let from = current;
+                flatten(&mut points, spacing, current, |t| {
+                    let u = 1.0 - t;
+                    from * (u * u) + c * (2.0 * u * t) + to * (t * t)
+                });
+                current = to;
+            }
+            SimplePathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let (c1, c2, to) = (point(x1, y1), point(x2, y2), point(x, y));
+                let from = current;
+                flatten(&mut points, spacing, current, |t| {
+                    let u = 1.0 - t;
+                    from * (u * u * u)
+                        + c1 * (3.0 * u * u * t)
+                        + c2 * (3.0 * u * t * t)
+                        + to * (t * t * t)
+                });
+                current = to;
+            }
+            SimplePathSegment::ClosePath => {
+                flatten(&mut points, spacing, current, |t| current.lerp(start, t));
+                finish(&mut polylines, &mut points, true);
+                current = start;
+            }
+        }
+    }
+    finish(&mut polylines, &mut points, false);
+
+    Ok(polylines)
+}
+
+#[derive(Debug)]
+pub enum SvgError {
+    Io(io::Error),
+    Xml(roxmltree::Error),
+    Path(svgtypes::Error),
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SvgError::Io(err) => write!(f, "{}", err),
+            SvgError::Xml(err) => write!(f, "{}", err),
+            SvgError::Path(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+impl From<io::Error> for SvgError {
+    fn from(err: io::Error) -> Self {
+        SvgError::Io(err)
+    }
+}
+
+impl From<roxmltree::Error> for SvgError {
+    fn from(err: roxmltree::Error) -> Self {
+        SvgError::Xml(err)
+    }
+}
+
+impl From<svgtypes::Error> for SvgError {
+    fn from(err: svgtypes::Error) -> Self {
+        SvgError::Path(err)
+    }
+}
+
+/// Parses every `<path>`, `<polygon>` and `<polyline>` of an SVG document like `parse_path()`.
+///
+/// Transforms, including those of enclosing groups, are ignored.
+pub fn parse_document(svg: &str, spacing: f32) -> Result<Vec<Polyline>, SvgError> {
+    let document = roxmltree::Document::parse(svg)?;
+    let mut polylines = Vec::new();
+
+    for node in document.descendants() {
+        let d = match node.tag_name().name() {
+            "path" => node.attribute("d").map(str::to_string),
+            // Polygons and polylines are turned into the equivalent path data.
+            name @ ("polygon" | "polyline") => node.attribute("points").map(|points| {
+                let mut d = String::new();
+                for (i, (x, y)) in PointsParser::from(points).enumerate() {
+                    let command = if i == 0 { 'M' } else { 'L' };
+                    write!(d, "{} {} {} ", command, x, y).unwrap();
+                }
+                if name == "polygon" {
+                    d.push('Z');
+                }
+                d
+            }),
+            _ => None,
+        };
+
+        if let Some(d) = d {
+            polylines.extend(parse_path(&d, spacing)?);
+        }
+    }
+
+    Ok(polylines)
+}
+
+/// Reads an SVG file and fits its shapes into a circle of `radius` around the origin, flattened
+/// into polylines with points roughly `spacing` apart. See `parse_document()`.
+pub fn open<P: AsRef<Path>>(path: P, radius: f32, spacing: f32) -> Result<Vec<Polyline>, SvgError> {
+    let svg = fs::read_to_string(path)?;
+    fit(radius, spacing, |spacing| parse_document(&svg, spacing))
+}
+
+/// Centers the polylines produced by `flatten(spacing)` on the origin, scaled to fit in a circle
+/// of `radius` with points roughly `spacing` apart after scaling.
+pub(crate) fn fit<F, E>(radius: f32, spacing: f32, flatten: F) -> Result<Vec<Polyline>, E>
+where
+    F: Fn(f32) -> Result<Vec<Polyline>, E>,
+{
+    // A coarse pass over the shapes finds how much they need to be scaled, so that the final pass
+    // can be flattened at the right density for the scaled size.
+    let points = flatten(f32::INFINITY)?
+        .into_iter()
+        .flat_map(|polyline| polyline.points);
+    let (min, max) = points.fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), p| (min.min(p), max.max(p)),
+    );
+    if min.x > max.x {
+        return Ok(Vec::new());
+    }
+    let center = (min + max) / 2.0;
+    let scale = radius / ((max - min) / 2.0).max_element().max(f32::EPSILON);
+
+    let mut polylines = flatten(spacing / scale)?;
+    for p in polylines
+        .iter_mut()
+        .flat_map(|polyline| &mut polyline.points)
+    {
+        *p = (*p - center) * scale;
+    }
+
+    Ok(polylines)
+}
+
+fn point(x: f64, y: f64) -> Vec2 {
+    vec2(x as f32, -y as f32)
+}
+
+/// Appends points sampled along the parametric segment `at(t)` for `t` in `(0, 1]`, dense enough
+/// that consecutive points are roughly `spacing` apart.
+fn flatten<F>(points: &mut Vec<Vec2>, spacing: f32, from: Vec2, at: F)
+where
+    F: Fn(f32) -> Vec2,
+{
+    // Estimate the arc length from a coarse sampling of the segment.
+    const ESTIMATE_STEPS: usize = 16;
+    let mut length = 0.0;
+    let mut prev = from;
+    for step in 1..=ESTIMATE_STEPS {
+        let p = at(step as f32 / ESTIMATE_STEPS as f32);
+        length += (p - prev).length();
+        prev = p;
+    }
+
+    let steps = (length / spacing).ceil().max(1.0) as usize;
+    for step in 1..=steps {
+        points.push(at(step as f32 / steps as f32));
+    }
+}
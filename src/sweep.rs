@@ -0,0 +1,238 @@
+//! Runs the simulation for every combination of a few parameters, to explore what they do.
+
+use crate::area;
+use crate::{Config, ParticleSystem};
+use std::fmt;
+use std::str::FromStr;
+
+/// A parameter of the config and the values it takes in a sweep.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepParameter {
+    /// The dotted path of the parameter in the config file, e.g. `simulation.curvature_bias`.
+    pub path: String,
+    pub values: Vec<f64>,
+}
+
+impl FromStr for SweepParameter {
+    type Err = String;
+
+    /// Parses `<path>=<start>:<end>:<count>`, for `count` evenly spaced values from `start` to
+    /// `end`, or `<path>=<value>,<value>,...` for a list of values.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, values) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected <path>=<values>, got {}", s))?;
+        let number = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid value {} for {}", value, path))
+        };
+
+        let values = match values.split(':').collect::<Vec<_>>()[..] {
+            [start, end, count] => {
+                let (start, end) = (number(start)?, number(end)?);
+                let count: usize = count
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid count {} for {}", count, path))?;
+                (0..count)
+                    .map(|i| match count {
+                        1 => start,
+                        _ => start + (end - start) * i as f64 / (count - 1) as f64,
+                    })
+                    .collect()
+            }
+            [list] => list.split(',').map(number).collect::<Result<_, _>>()?,
+            _ => return Err(format!("expected <start>:<end>:<count> for {}", path)),
+        };
+
+        Ok(SweepParameter {
+            path: path.trim().to_string(),
+            values,
+        })
+    }
+}
+
+/// Every combination of one value of each parameter, in the order of the parameters, with the
+/// last one varying fastest.
+pub fn combinations(parameters: &[SweepParameter]) -> Vec<Vec<f64>> {
+    parameters
+        .iter()
+        .fold(vec![Vec::new()], |combinations, parameter| {
+            combinations
+                .iter()
+                .flat_map(|combination| {
+                    parameter.values.iter().map(move |&value| {
+                        let mut combination = combination.clone();
+                        combination.push(value);
+                        combination
+                    })
+                })
+                .collect()
+        })
+}
+
+#[derive(Debug)]
+pub enum SweepError {
+    /// The path leads into a value that isn't a table.
+    Path(String),
+    Serialize(toml::ser::Error),
+    Deserialize(toml::de::Error),
+}
+
+impl fmt::Display for SweepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SweepError::Path(path) => write!(f, "{} isn't a parameter", path),
+            SweepError::Serialize(err) => write!(f, "{}", err),
+            SweepError::Deserialize(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SweepError {}
+
+impl From<toml::ser::Error> for SweepError {
+    fn from(err: toml::ser::Error) -> Self {
+        SweepError::Serialize(err)
+    }
+}
+
+impl From<toml::de::Error> for SweepError {
+    fn from(err: toml::de::Error) -> Self {
+        SweepError::Deserialize(err)
+    }
+}
+
+/// `config` with the parameter at the dotted `path` set to `value`, as if written in the config
+/// file. Whole values are written as integers, which float parameters accept too.
+pub fn with_parameter(config: &Config, path: &str, value: f64) -> Result<Config, SweepError> {
+    let mut root = toml::Value::try_from(config)?;
+    set_parameter(&mut root, path, value)?;
+    Ok(root.try_into()?)
+}
+
+/// Sets the value at the dotted `path` of a serialized config, see `with_parameter()`.
+pub(crate) fn set_parameter(
+    root: &mut toml::Value,
+    path: &str,
+    value: f64,
+) -> Result<(), SweepError> {
+    let mut keys = path.split('.').peekable();
+    let mut table = root;
+    while let Some(key) = keys.next() {
+        let entries = table
+            .as_table_mut()
+            .ok_or_else(|| SweepError::Path(path.to_string()))?;
+        if keys.peek().is_none() {
+            let value = if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+                toml::Value::Integer(value as i64)
+            } else {
+                toml::Value::Float(value)
+            };
+            entries.insert(key.to_string(), value);
+            break;
+        }
+        // Unset optional sections aren't serialized, so they are created as needed.
+        table = entries
+            .entry(key)
+            .or_insert_with(|| toml::Value::Table(Default::default()));
+    }
+    Ok(())
+}
+
+/// Measurements of a grown system, to compare the runs of a sweep.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metrics {
+    pub particles: usize,
+    pub steps: u64,
+    /// The summed length of every curve.
+    pub length: f32,
+    /// The summed area enclosed by the closed curves.
+    pub area: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Metrics {
+    pub fn of(ps: &ParticleSystem) -> Self {
+        let polylines = ps.polylines();
+        let length = polylines
+            .iter()
+            .map(|polyline| {
+                let points = &polyline.points;
+                let closing = match (polyline.closed, points.first(), points.last()) {
+                    (true, Some(&first), Some(&last)) => (first - last).length(),
+                    _ => 0.0,
+                };
+                points
+                    .windows(2)
+                    .map(|w| (w[1] - w[0]).length())
+                    .sum::<f32>()
+                    + closing
+            })
+            .sum();
+        let area = polylines
+            .iter()
+            .filter(|polyline| polyline.closed)
+            .map(|polyline| area::signed_area(&polyline.points).abs())
+            .sum();
+        let size = ps
+            .bounding_box()
+            .map_or(glam::Vec2::ZERO, |(min, max)| max - min);
+
+        Metrics {
+            particles: ps.num_particles(),
+            steps: ps.steps(),
+            length,
+            area,
+            width: size.x,
+            height: size.y,
+        }
+    }
+}
+
+/// The header of the CSV of a sweep over `parameters`, see `csv_row()`.
+pub fn csv_header(parameters: &[SweepParameter]) -> String {
+    let mut columns = vec!["index".to_string()];
+    columns.extend(parameters.iter().map(|parameter| parameter.path.clone()));
+    columns.extend(
+        [
+            "particles",
+            "steps",
+            "length",
+            "area",
+            "width",
+            "height",
+            "seconds",
+            "file",
+        ]
+        .iter()
+        .map(|column| column.to_string()),
+    );
+    columns.join(",")
+}
+
+/// A line of the CSV of a sweep, for the run with the given parameter values.
+pub fn csv_row(
+    index: usize,
+    values: &[f64],
+    metrics: &Metrics,
+    seconds: f64,
+    file: &str,
+) -> String {
+    let mut columns = vec![index.to_string()];
+    columns.extend(values.iter().map(|value| value.to_string()));
+    columns.extend([
+        metrics.particles.to_string(),
+        metrics.steps.to_string(),
+        format!("{:.3}", metrics.length),
+        format!("{:.3}", metrics.area),
+        format!("{:.3}", metrics.width),
+        format!("{:.3}", metrics.height),
+        format!("{:.3}", seconds),
+        file.to_string(),
+    ]);
+    columns.join(",")
+}
@@ -0,0 +1,84 @@
+//! Symmetry groups around the origin. The curves push against their own copies under the group as
+//! well as against each other, and are drawn along with them, which grows mandala-like patterns.
+
+use glam::{vec2, Mat2};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Symmetry {
+    /// Mirrored across the line through the origin at this angle from the x axis, in radians.
+    Mirror(f32),
+    /// Turned around the origin by whole `n`ths of a turn.
+    Rotation(usize),
+    /// Turned by whole `n`ths of a turn and mirrored across the x axis, like in a kaleidoscope,
+    /// making `2 * n` copies.
+    Dihedral(usize),
+}
+
+impl Symmetry {
+    /// The transformations of the group, the identity first. They are all rotations and
+    /// reflections, so each one's inverse is its transpose.
+    pub fn transforms(&self) -> Vec<Mat2> {
+        let reflection = |angle: f32| {
+            let (sin, cos) = (2.0 * angle).sin_cos();
+            Mat2::from_cols(vec2(cos, sin), vec2(sin, -cos))
+        };
+        let rotations = |n: usize| {
+            let n = n.max(1);
+            (0..n).map(move |k| Mat2::from_angle(TAU * k as f32 / n as f32))
+        };
+        match *self {
+            Symmetry::Mirror(angle) => vec![Mat2::IDENTITY, reflection(angle)],
+            Symmetry::Rotation(n) => rotations(n).collect(),
+            Symmetry::Dihedral(n) => rotations(n)
+                .chain(rotations(n).map(|rotation| rotation * reflection(0.0)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    fn images(symmetry: Symmetry, p: Vec2) -> Vec<Vec2> {
+        symmetry.transforms().into_iter().map(|t| t * p).collect()
+    }
+
+    fn assert_close(a: &[Vec2], b: &[Vec2]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert!(a.distance(*b) < 1e-5, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn transforms_replicate_points_under_the_group() {
+        let p = vec2(2.0, 1.0);
+        assert_close(
+            &images(Symmetry::Mirror(0.0), p),
+            &[vec2(2.0, 1.0), vec2(2.0, -1.0)],
+        );
+        assert_close(
+            &images(Symmetry::Rotation(4), p),
+            &[
+                vec2(2.0, 1.0),
+                vec2(-1.0, 2.0),
+                vec2(-2.0, -1.0),
+                vec2(1.0, -2.0),
+            ],
+        );
+        assert_close(
+            &images(Symmetry::Dihedral(2), p),
+            &[
+                vec2(2.0, 1.0),
+                vec2(-2.0, -1.0),
+                vec2(2.0, -1.0),
+                vec2(-2.0, 1.0),
+            ],
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Outlines of text set in a font, to grow typography from.
+
+use crate::svg::{self, parse_path};
+use crate::Polyline;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+use ttf_parser::{Face, FaceParsingError, OutlineBuilder};
+
+#[derive(Debug)]
+pub enum TextError {
+    Io(io::Error),
+    Font(FaceParsingError),
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextError::Io(err) => write!(f, "{}", err),
+            TextError::Font(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+impl From<io::Error> for TextError {
+    fn from(err: io::Error) -> Self {
+        TextError::Io(err)
+    }
+}
+
+impl From<FaceParsingError> for TextError {
+    fn from(err: FaceParsingError) -> Self {
+        TextError::Font(err)
+    }
+}
+
+/// Collects glyph outlines as SVG path data, so they can be flattened like any other path.
+struct PathData {
+    d: String,
+    offset: (f32, f32),
+}
+
+impl PathData {
+    // Fonts have y pointing up, so it is flipped here to cancel out the flip in `parse_path()`.
+    fn point(&mut self, command: char, points: &[(f32, f32)]) {
+        self.d.push(command);
+        for &(x, y) in points {
+            write!(self.d, " {} {}", x + self.offset.0, -(y + self.offset.1)).unwrap();
+        }
+        self.d.push(' ');
+    }
+}
+
+impl OutlineBuilder for PathData {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.point('M', &[(x, y)]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.point('L', &[(x, y)]);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.point('Q', &[(x1, y1), (x, y)]);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.point('C', &[(x1, y1), (x2, y2), (x, y)]);
+    }
+
+    fn close(&mut self) {
+        self.d.push_str("Z ");
+    }
+}
+
+/// Sets `text` in the font and returns the closed outlines of its glyphs, fitted into a circle
+/// of `radius` around the origin with points roughly `spacing` apart. Lines are separated by
+/// `\n`.
+pub fn outline(
+    font: &[u8],
+    text: &str,
+    radius: f32,
+    spacing: f32,
+) -> Result<Vec<Polyline>, TextError> {
+    let face = Face::parse(font, 0)?;
+    let line_height = (face.ascender() - face.descender() + face.line_gap()) as f32;
+
+    let mut path = PathData {
+        d: String::new(),
+        offset: (0.0, 0.0),
+    };
+    for (row, line) in text.lines().enumerate() {
+        path.offset = (0.0, -(row as f32) * line_height);
+        for c in line.chars() {
+            let glyph = match face.glyph_index(c) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            face.outline_glyph(glyph, &mut path);
+            path.offset.0 += face.glyph_hor_advance(glyph).unwrap_or(0) as f32;
+        }
+    }
+
+    let polylines = svg::fit(radius, spacing, |spacing| parse_path(&path.d, spacing))
+        .expect("glyph outlines make valid path data");
+    Ok(polylines)
+}
+
+/// Like `outline()`, reading the font from a TrueType or OpenType file.
+pub fn open<P: AsRef<Path>>(
+    font: P,
+    text: &str,
+    radius: f32,
+    spacing: f32,
+) -> Result<Vec<Polyline>, TextError> {
+    outline(&fs::read(font)?, text, radius, spacing)
+}
@@ -0,0 +1,74 @@
+//! Decouples how often the simulation steps from how often it is drawn.
+
+use std::time::Duration;
+
+/// Turns elapsed wall clock time into a number of steps to run, carrying the remainder over to
+/// the next frame. Running that many steps makes the simulation advance at the same pace
+/// whatever the frame rate.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    steps_per_second: f32,
+    /// The fraction of a step left over from the previous frames.
+    carry: f64,
+    /// Multiplies the steps run, above 1 to fast-forward and below it for slow motion.
+    pub speed: f32,
+    /// Caps the steps run for a single frame at normal speed, scaled up when fast-forwarding.
+    /// Time beyond that is dropped, so a slow frame doesn't leave ever more steps to catch up on.
+    pub max_steps: usize,
+}
+
+impl FixedTimestep {
+    pub fn new(steps_per_second: f32) -> Self {
+        FixedTimestep {
+            steps_per_second: clamp_steps_per_second(steps_per_second),
+            carry: 0.0,
+            speed: 1.0,
+            max_steps: 8,
+        }
+    }
+
+    pub fn steps_per_second(&self) -> f32 {
+        self.steps_per_second
+    }
+
+    pub fn set_steps_per_second(&mut self, steps_per_second: f32) {
+        self.steps_per_second = clamp_steps_per_second(steps_per_second);
+    }
+
+    /// Adds `elapsed` to the time to simulate and returns how many whole steps fit into it.
+    pub fn advance(&mut self, elapsed: Duration) -> usize {
+        self.carry += elapsed.as_secs_f64() * self.steps_per_second as f64 * self.speed() as f64;
+        let steps = self.carry.floor();
+        let max_steps = (self.max_steps as f32 * self.speed().max(1.0)).ceil();
+        if steps > max_steps as f64 {
+            self.carry = 0.0;
+            max_steps as usize
+        } else {
+            self.carry -= steps;
+            steps as usize
+        }
+    }
+
+    /// Returns how many steps to run for a frame that runs `steps_per_frame` of them at normal
+    /// speed, whatever the time it took, carrying over fractions of a step in slow motion.
+    pub fn advance_frame(&mut self, steps_per_frame: usize) -> usize {
+        self.carry += steps_per_frame as f64 * self.speed() as f64;
+        let steps = self.carry.floor();
+        self.carry -= steps;
+        steps as usize
+    }
+
+    /// How far the simulation is between its latest step and the next one, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        (self.carry as f32).clamp(0.0, 1.0)
+    }
+
+    fn speed(&self) -> f32 {
+        self.speed.max(0.0)
+    }
+}
+
+fn clamp_steps_per_second(steps_per_second: f32) -> f32 {
+    // Anything slower than a step a minute is as good as paused.
+    steps_per_second.clamp(1.0 / 60.0, 1_000_000.0)
+}
@@ -0,0 +1,113 @@
+//! Space wrapping around at its edges like the surface of a torus, so that curves leaving one side
+//! come back in on the other and the growth tiles seamlessly.
+
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// A `width` by `height` rectangle centered on the origin whose opposite edges are glued together.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Torus {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Torus {
+    pub fn size(&self) -> Vec2 {
+        vec2(self.width, self.height)
+    }
+
+    /// `p` moved by whole widths and heights into the rectangle.
+    pub fn wrap(&self, p: Vec2) -> Vec2 {
+        let size = self.size();
+        (p + size / 2.0).rem_euclid(size) - size / 2.0
+    }
+
+    /// The shortest move from `from` to `to`, going around whichever way is closer.
+    pub fn offset(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let size = self.size();
+        let offset = to - from;
+        offset - (offset / size).round() * size
+    }
+
+    /// The copies of `center` whose circles of `radius` overlap the rectangle, `center` itself
+    /// first. Searching around each of them finds everything within `radius` across the edges.
+    pub fn images(&self, center: Vec2, radius: f32) -> impl Iterator<Item = Vec2> {
+        let size = self.size();
+        let half = size / 2.0;
+        let overlaps = move |c: f32, r: f32, half: f32| c + r >= -half && c - r <= half;
+        const SHIFTS: [f32; 3] = [0.0, -1.0, 1.0];
+        SHIFTS
+            .iter()
+            .flat_map(|&x| SHIFTS.iter().map(move |&y| vec2(x, y)))
+            .map(move |shift| center + shift * size)
+            .filter(move |image| {
+                *image == center
+                    || overlaps(image.x, radius, half.x) && overlaps(image.y, radius, half.y)
+            })
+    }
+
+    /// `points` moved by whole widths and heights so that each one lies closest to the one before
+    /// it, making a curve that crosses the edges continuous. It may then stick out of the
+    /// rectangle, and a curve going all the way around never closes up.
+    pub fn unwrap(&self, points: &[Vec2]) -> Vec<Vec2> {
+        let mut unwrapped: Vec<Vec2> = Vec::with_capacity(points.len());
+        for &p in points {
+            let p = match unwrapped.last() {
+                Some(&last) => last + self.offset(last, p),
+                None => p,
+            };
+            unwrapped.push(p);
+        }
+        unwrapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TORUS: Torus = Torus {
+        width: 100.0,
+        height: 50.0,
+    };
+
+    #[test]
+    fn wrap_moves_points_into_the_rectangle() {
+        assert_eq!(TORUS.wrap(vec2(10.0, 5.0)), vec2(10.0, 5.0));
+        assert_eq!(TORUS.wrap(vec2(60.0, -30.0)), vec2(-40.0, 20.0));
+        assert_eq!(TORUS.wrap(vec2(-260.0, 80.0)), vec2(40.0, -20.0));
+    }
+
+    #[test]
+    fn offset_goes_around_the_closer_way() {
+        assert_eq!(
+            TORUS.offset(vec2(-45.0, 0.0), vec2(45.0, 0.0)),
+            vec2(-10.0, 0.0)
+        );
+        assert_eq!(
+            TORUS.offset(vec2(0.0, 20.0), vec2(0.0, -20.0)),
+            vec2(0.0, 10.0)
+        );
+        assert_eq!(
+            TORUS.offset(vec2(0.0, 0.0), vec2(20.0, 10.0)),
+            vec2(20.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn unwrap_makes_curves_continuous_across_the_edges() {
+        let points = [vec2(46.0, 0.0), vec2(49.0, 0.0), vec2(-48.0, 0.0)];
+        assert_eq!(
+            TORUS.unwrap(&points),
+            vec![vec2(46.0, 0.0), vec2(49.0, 0.0), vec2(52.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn images_cover_the_edges_a_circle_overlaps() {
+        let near_corner: Vec<Vec2> = TORUS.images(vec2(48.0, 23.0), 5.0).collect();
+        assert_eq!(near_corner.len(), 4);
+        assert_eq!(TORUS.images(vec2(0.0, 0.0), 5.0).count(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+//! Earlier states of the whole simulation kept in memory, so the growth can be stepped back to
+//! before it went somewhere unwanted and resumed from there.
+
+use crate::state::StateError;
+use crate::ParticleSystem;
+use std::collections::VecDeque;
+
+/// A bounded stack of full states, encoded like `ParticleSystem::encode_state()` and taken every
+/// `every` steps. Once `depth` are kept, each new one drops the oldest. Undoing moves the current
+/// state onto a stack to redo, which stepping on clears as the growth then takes another course.
+pub struct Undo {
+    pub every: u64,
+    pub depth: usize,
+    /// The step each state was taken at, oldest first.
+    past: VecDeque<(u64, Vec<u8>)>,
+    future: Vec<(u64, Vec<u8>)>,
+}
+
+impl Undo {
+    pub fn new(every: u64, depth: usize) -> Self {
+        Undo {
+            every,
+            depth,
+            past: VecDeque::new(),
+            future: Vec::new(),
+        }
+    }
+
+    /// Takes the state of `ps` if its step is a multiple of `every`. Call it after every step.
+    pub fn record(&mut self, ps: &ParticleSystem) -> Result<(), StateError> {
+        self.future.clear();
+        if ps.steps().is_multiple_of(self.every.max(1)) {
+            self.push(ps)?;
+        }
+        Ok(())
+    }
+
+    /// Takes the state of `ps` whatever its step, unless `depth` is 0.
+    pub fn push(&mut self, ps: &ParticleSystem) -> Result<(), StateError> {
+        if self.depth == 0 {
+            return Ok(());
+        }
+        let state = ps.encode_state()?;
+        while self.past.len() >= self.depth {
+            self.past.pop_front();
+        }
+        self.past.push_back((ps.steps(), state));
+        Ok(())
+    }
+
+    /// Rewinds `ps` to the latest state taken before its step, keeping the current one to redo.
+    /// Returns whether there was one.
+    pub fn undo(&mut self, ps: &mut ParticleSystem) -> Result<bool, StateError> {
+        while let Some(&(step, _)) = self.past.back() {
+            if step < ps.steps() {
+                break;
+            }
+            // Taken at the current step, so it is the state being left anyway.
+            self.past.pop_back();
+        }
+        let (_, state) = match self.past.pop_back() {
+            Some(past) => past,
+            None => return Ok(false),
+        };
+        self.future.push((ps.steps(), ps.encode_state()?));
+        ps.restore_state(&state)?;
+        self.past.push_back((ps.steps(), state));
+        Ok(true)
+    }
+
+    /// Brings `ps` forward again to the state the last `undo()` left. Returns whether there was
+    /// one.
+    pub fn redo(&mut self, ps: &mut ParticleSystem) -> Result<bool, StateError> {
+        let (_, state) = match self.future.pop() {
+            Some(future) => future,
+            None => return Ok(false),
+        };
+        ps.restore_state(&state)?;
+        self.push(ps)?;
+        Ok(true)
+    }
+
+    pub fn clear(&mut self) {
+        self.past.clear();
+        self.future.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec2;
+
+    #[test]
+    fn undoing_resumes_exactly_where_the_growth_left_off() {
+        let mut ps = ParticleSystem::with_seed(5);
+        let ring: Vec<_> = (0..20)
+            .map(|i| {
+                let angle = i as f32 / 20.0 * std::f32::consts::TAU;
+                vec2(angle.cos(), angle.sin()) * 20.0
+            })
+            .collect();
+        ps.add_curve(&ring, false);
+        let mut undo = Undo::new(10, 4);
+        undo.push(&ps).unwrap();
+        for _ in 0..25 {
+            ps.update();
+            undo.record(&ps).unwrap();
+        }
+        let grown = ps.positions().to_vec();
+
+        assert!(undo.undo(&mut ps).unwrap());
+        assert_eq!(ps.steps(), 20);
+        assert!(undo.undo(&mut ps).unwrap());
+        assert_eq!(ps.steps(), 10);
+        assert!(undo.redo(&mut ps).unwrap());
+        assert!(undo.redo(&mut ps).unwrap());
+        assert_eq!(ps.steps(), 25);
+        assert_eq!(ps.positions(), &grown[..]);
+
+        assert!(undo.undo(&mut ps).unwrap());
+        for _ in 0..5 {
+            ps.update();
+        }
+        assert_eq!(ps.positions(), &grown[..]);
+    }
+}
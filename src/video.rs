@@ -0,0 +1,82 @@
+//! Video recording by piping raw frames into an ffmpeg process, so long runs don't have to go
+//! through thousands of PNGs on disk.
+
+use crate::config::VideoConfig;
+use crate::Canvas;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+
+/// A running ffmpeg process encoding frames of a fixed size into a video file.
+pub struct VideoRecorder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    width: usize,
+    height: usize,
+}
+
+impl VideoRecorder {
+    /// Starts ffmpeg writing to `path`, overwriting it if it exists. Frames have to be `width`
+    /// by `height`, which most codecs need to be even.
+    pub fn start<P: AsRef<Path>>(
+        path: P,
+        width: usize,
+        height: usize,
+        settings: &VideoConfig,
+    ) -> io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut child = Command::new(&settings.ffmpeg)
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &settings.fps.to_string()])
+            .args(["-i", "-"])
+            .args(["-c:v", &settings.codec, "-b:v", &settings.bitrate])
+            // Players widely support only 4:2:0 chroma subsampling.
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+
+        Ok(VideoRecorder {
+            child,
+            stdin,
+            width,
+            height,
+        })
+    }
+
+    /// Sends the canvas to ffmpeg as the next frame. Fails if its size doesn't match the video's,
+    /// or if ffmpeg exited.
+    pub fn add_frame(&mut self, canvas: &Canvas) -> io::Result<()> {
+        if (canvas.width(), canvas.height()) != (self.width, self.height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "frame is {}x{}, the video is {}x{}",
+                    canvas.width(),
+                    canvas.height(),
+                    self.width,
+                    self.height
+                ),
+            ));
+        }
+
+        match &mut self.stdin {
+            Some(stdin) => stdin.write_all(&canvas.to_rgba8()),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    /// Closes the pipe and waits for ffmpeg to finish writing the file.
+    pub fn finish(mut self) -> io::Result<ExitStatus> {
+        self.stdin = None;
+        self.child.wait()
+    }
+}
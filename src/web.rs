@@ -0,0 +1,120 @@
+//! A browser frontend drawing the simulation onto a `<canvas>`, so that the piece can run in a
+//! gallery page. Only built for `wasm32`, with wasm-pack, see `web/index.html`.
+
+use crate::raster::{self, Canvas};
+use crate::{vec2, Config, ParticleSystem, Vec2};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+#[wasm_bindgen]
+pub struct WebApp {
+    config: Config,
+    ps: ParticleSystem,
+    element: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    /// Drawn to on the CPU, then copied to the page.
+    canvas: Canvas,
+    /// The simulation position shown in the middle of the canvas, and pixels per unit.
+    center: Vec2,
+    zoom: f32,
+}
+
+#[wasm_bindgen]
+impl WebApp {
+    /// Grows the system configured by the TOML `config`, which may be empty for the defaults,
+    /// drawn onto `element`. Without a `seed`, a random one is picked.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        element: HtmlCanvasElement,
+        config: &str,
+        seed: Option<u64>,
+    ) -> Result<WebApp, JsError> {
+        let config: Config = toml::from_str(config)?;
+        let ps = config.new_particle_system(seed.unwrap_or_else(rand::random))?;
+        let context = element
+            .get_context("2d")
+            .map_err(|_| JsError::new("failed to get a 2D context"))?
+            .ok_or_else(|| JsError::new("the canvas has no 2D context"))?
+            .dyn_into::<CanvasRenderingContext2d>()
+            .map_err(|_| JsError::new("the 2D context isn't a CanvasRenderingContext2d"))?;
+        let canvas = Canvas::new(element.width() as usize, element.height() as usize);
+        Ok(WebApp {
+            config,
+            ps,
+            element,
+            context,
+            canvas,
+            center: Vec2::ZERO,
+            zoom: 1.0,
+        })
+    }
+
+    /// Advances the simulation by `steps` steps, following `[[simulation.schedules]]`.
+    pub fn step(&mut self, steps: u32) -> Result<(), JsError> {
+        for _ in 0..steps {
+            self.config.simulation.apply_schedules(&mut self.ps, &[])?;
+            self.ps.update();
+        }
+        Ok(())
+    }
+
+    /// Starts over from the spawn shape, with the same seed.
+    pub fn restart(&mut self) -> Result<(), JsError> {
+        self.ps = self.config.new_particle_system(self.ps.seed())?;
+        Ok(())
+    }
+
+    /// Shows `(x, y)` in the middle of the canvas, with `zoom` pixels per simulation unit.
+    pub fn set_view(&mut self, x: f32, y: f32, zoom: f32) {
+        self.center = vec2(x, y);
+        self.zoom = zoom;
+    }
+
+    /// Zooms and pans to show the whole growth.
+    pub fn fit(&mut self) {
+        if let Some(bounds) = self.ps.bounding_box() {
+            let (width, height) = (self.canvas.width(), self.canvas.height());
+            let (center, zoom) = raster::fit_view(bounds, width, height, 20.0);
+            self.center = center;
+            self.zoom = zoom;
+        }
+    }
+
+    pub fn num_particles(&self) -> usize {
+        self.ps.num_particles()
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.ps.steps()
+    }
+
+    /// Draws the simulation like the app does, resizing along with the canvas element.
+    pub fn render(&mut self) -> Result<(), JsError> {
+        let (width, height) = (self.element.width(), self.element.height());
+        if (width as usize, height as usize) != (self.canvas.width(), self.canvas.height()) {
+            self.canvas = Canvas::new(width as usize, height as usize);
+        }
+
+        let canvas = &mut self.canvas;
+        canvas.set_view(self.center, self.zoom);
+        canvas.fill(self.config.colors.background);
+        for obstacle in &self.ps.obstacles {
+            canvas.fill_polygon(&obstacle.polygon, [0.3, 0.3, 0.35, 1.0]);
+        }
+        let render = &self.config.render;
+        canvas.draw_particle_system(
+            &self.ps,
+            self.config.colors.line,
+            &render.thickness,
+            render.subdivisions,
+        );
+
+        let pixels = canvas.to_rgba8();
+        let image = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&pixels), width, height)
+            .map_err(|_| JsError::new("failed to create the image"))?;
+        self.context
+            .put_image_data(&image, 0.0, 0.0)
+            .map_err(|_| JsError::new("failed to draw the image"))
+    }
+}